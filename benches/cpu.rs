@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+use singularity_emu::cartridge::cart::Cart;
+use singularity_emu::cpu::cpu6502::Cpu6502;
+use singularity_emu::memory::flat_ram::FlatRam;
+use singularity_emu::nes::Nes;
+use singularity_emu::traits::{Clockable, Resettable};
+
+// Run with `cargo bench`. Each case is timed a few times and the fastest run is reported, which
+// is the least disturbed by whatever else the machine is doing.
+const RUNS: usize = 5;
+
+// A loop that goes through most of the addressing modes, read-modify-write instructions, the
+// stack and a taken branch, so that dispatch is exercised over a spread of opcodes
+const PROGRAM: [u8; 31] = [
+    0xA2, 0x00,       // $0200 LDX #$00
+    0xA0, 0x01,       // $0202 LDY #$01
+    0xBD, 0x00, 0x03, // $0204 LDA $0300,X
+    0x69, 0x01,       // $0207 ADC #$01
+    0x9D, 0x00, 0x03, // $0209 STA $0300,X
+    0xE6, 0x10,       // $020C INC $10
+    0x0A,             // $020E ASL A
+    0x26, 0x11,       // $020F ROL $11
+    0x51, 0x20,       // $0211 EOR ($20),Y
+    0xC9, 0x80,       // $0213 CMP #$80
+    0x24, 0x12,       // $0215 BIT $12
+    0x48,             // $0217 PHA
+    0x68,             // $0218 PLA
+    0xCA,             // $0219 DEX
+    0xD0, 0xE8,       // $021A BNE $0204
+    0x4C, 0x00, 0x02  // $021C JMP $0200
+];
+
+fn fastest<F: FnMut()>(mut run: F) -> Duration
+{
+    (0..RUNS).map(|_|
+    {
+        let start = Instant::now();
+        run();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+// The CPU on its own, so nothing but the core is measured
+fn bench_cpu(cycles: u32)
+{
+    let mut ram = FlatRam::new();
+    ram.load(0x0200, &PROGRAM);
+    ram.load(0xFFFC, &[0x00, 0x02]);
+    let mut cpu = Cpu6502::new(ram);
+
+    let elapsed = fastest(||
+    {
+        cpu.reset();
+        for _ in 0..cycles
+        {
+            cpu.clock_tick();
+        }
+    });
+
+    println!("cpu:     {} cycles in {:?}, {:.1} MHz", cycles, elapsed, cycles as f64 / elapsed.as_secs_f64() / 1e6);
+}
+
+// The whole console headless, which is what batch runs spend their time on
+fn bench_console(frames: u32)
+{
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cart::new("data/nestest.nes".to_string()).unwrap());
+
+    let elapsed = fastest(||
+    {
+        nes.reset();
+        for _ in 0..frames
+        {
+            nes.step_frame();
+        }
+    });
+
+    println!("console: {} frames in {:?}, {:.1} fps", frames, elapsed, frames as f64 / elapsed.as_secs_f64());
+}
+
+fn main()
+{
+    bench_cpu(10_000_000);
+    bench_console(120);
+}
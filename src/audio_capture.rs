@@ -0,0 +1,39 @@
+use std::io;
+use std::path::Path;
+
+use crate::cartridge::cart::Cart;
+use crate::nes::Nes;
+use crate::sound::sound_engine::AudioSink;
+use crate::sound::wav_writer::WavWriter;
+use crate::traits::Resettable;
+
+// Runs the machine for a number of frames without a window or audio device, handing every
+// sample it produces to the sink. Returns how many samples that was.
+pub fn capture(nes: &mut Nes, frames: u32, sink: &mut dyn AudioSink) -> io::Result<usize>
+{
+    nes.set_audio_sample_rate(sink.get_sample_rate());
+
+    let mut count = 0;
+    for _ in 0..frames
+    {
+        nes.step_frame();
+        let samples = nes.audio_samples();
+        sink.write_samples(&samples)?;
+        count += samples.len();
+    }
+
+    Ok(count)
+}
+
+// Loads the ROM, runs it from reset and writes what it played to a .wav file
+pub fn run(rom_path: &str, wav_path: &str, frames: u32, sample_rate: u32) -> io::Result<usize>
+{
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cart::new(rom_path.to_string())?);
+    nes.reset();
+
+    let mut writer = WavWriter::create(Path::new(wav_path), sample_rate)?;
+    let count = capture(&mut nes, frames, &mut writer)?;
+    writer.finish()?;
+    Ok(count)
+}
\ No newline at end of file
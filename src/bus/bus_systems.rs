@@ -56,7 +56,7 @@ pub fn get_matching_systems(&mut self, address: u16) -> Vec<&mut System>
         }
 
         // Sort by priority, let the lowest priority handle the operation
-        matching_systems.sort_by(|a, b| a.priority.cmp(&b.priority));
+        matching_systems.sort_by_key(|a| a.priority);
         matching_systems
     }
 
@@ -1,4 +1,9 @@
-use crate::traits::ReadWrite;
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{ReadWrite, Saveable};
 
 
 pub struct DmaInfo
@@ -7,14 +12,18 @@ pub struct DmaInfo
     addr: u8,
     data: u8,
     transfer: bool,
-    sync: bool
+    sync: bool,
+
+    // DMC sample fetches take over the bus for a few cycles, halting the CPU and any OAM DMA
+    dmc_addr: u16,
+    dmc_stall: u8
 }
 
 impl DmaInfo
 {
     pub fn new() -> Self
     {
-        DmaInfo { page: 0, addr: 0, data: 0, transfer: false, sync: true }
+        DmaInfo { page: 0, addr: 0, data: 0, transfer: false, sync: true, dmc_addr: 0, dmc_stall: 0 }
     }
 
     pub fn is_transfer_in_progress(&self) -> bool
@@ -44,6 +53,27 @@ pub fn get_page(&self) -> u8 { self.page }
     pub fn set_data(&mut self, data: u8) { self.data = data; }
     pub fn set_addr(&mut self, addr: u8) { self.addr = addr; }
     pub fn set_page(&mut self, page: u8) { self.page = page; }
+
+    pub fn is_dmc_fetch_in_progress(&self) -> bool
+    {
+        self.dmc_stall > 0
+    }
+
+    // The CPU is halted for stall_cycles, the sample byte is read on the last of them
+    pub fn start_dmc_fetch(&mut self, addr: u16, stall_cycles: u8)
+    {
+        self.dmc_addr = addr;
+        self.dmc_stall = stall_cycles;
+    }
+
+    // Counts down one stalled cycle, returns true when the fetch is due
+    pub fn clock_dmc_stall(&mut self) -> bool
+    {
+        self.dmc_stall = self.dmc_stall.saturating_sub(1);
+        self.dmc_stall == 0
+    }
+
+    pub fn get_dmc_addr(&self) -> u16 { self.dmc_addr }
 }
 
 impl ReadWrite for DmaInfo
@@ -72,6 +102,32 @@ fn ppu_read(&self, _: u16, _: &mut u8) -> bool
     }
 }
 
+impl Saveable for DmaInfo
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u8(self.page)?;
+        writer.write_u8(self.addr)?;
+        writer.write_u8(self.data)?;
+        savestate::write_bool(writer, self.transfer)?;
+        savestate::write_bool(writer, self.sync)?;
+        writer.write_u16::<LittleEndian>(self.dmc_addr)?;
+        writer.write_u8(self.dmc_stall)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.page = reader.read_u8()?;
+        self.addr = reader.read_u8()?;
+        self.data = reader.read_u8()?;
+        self.transfer = savestate::read_bool(reader)?;
+        self.sync = savestate::read_bool(reader)?;
+        self.dmc_addr = reader.read_u16::<LittleEndian>()?;
+        self.dmc_stall = reader.read_u8()?;
+        Ok(())
+    }
+}
+
 impl Default for DmaInfo {
     fn default() -> Self {
         DmaInfo::new()
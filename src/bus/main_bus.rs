@@ -1,10 +1,13 @@
+use std::io::{self, Read, Write};
 use std::sync::{Arc, Mutex};
 
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
 use crate::sound::apu2a03::Apu2a03;
-use crate::traits::ReadWrite;
+use crate::sound::vgm_logger::VgmLogger;
+use crate::traits::{CpuBus, ReadWrite, Saveable};
 
 use crate::memory::ram::Ram;
-use crate::cpu::cpu6502::Cpu6502;
 use crate::gfx::ppu2c02::Ppu2c02;
 use crate::cartridge::cart::Cart;
 
@@ -19,12 +22,12 @@ pub struct MainBus
     bus_systems: BusSystems,
     cpu_ram: Arc<Mutex<Ram>>,
     ppu: Arc<Mutex<Ppu2c02>>,
-    cpu: Arc<Mutex<Cpu6502>>,
     apu: Arc<Mutex<Apu2a03>>,
     cartridge: Option<Arc<Mutex<Cart>>>,
     controllers: [Arc<Mutex<NesController>>; 2],
     system_clock_counter: u32,
     dma_info: Arc<Mutex<DmaInfo>>,
+    vgm_logger: Option<VgmLogger>,
 }
 
 impl MainBus
@@ -36,12 +39,12 @@ pub fn new() -> Self
             bus_systems: BusSystems::new(),
             cpu_ram: Arc::new(Mutex::new(Ram::new(0x1FFF, 0x07FF))),
             ppu: Arc::new(Mutex::new(Ppu2c02::new())),
-            cpu: Arc::new(Mutex::new(Cpu6502::new())),
             apu: Arc::new(Mutex::new(Apu2a03::new())),
             cartridge: None,
             controllers: [ Arc::new(Mutex::new(NesController::new())), Arc::new(Mutex::new(NesController::new())) ],
             system_clock_counter: 0,
             dma_info: Arc::new(Mutex::new(DmaInfo::new())),
+            vgm_logger: None,
         };
 
         let cpu_ram_trait_object = Arc::clone(&s.cpu_ram) as Arc<Mutex<dyn ReadWrite>>;
@@ -83,11 +86,6 @@ pub fn insert_cartridge(&mut self, cartridge: Arc<Mutex<Cart>>)
         self.ppu.lock().unwrap().connect_cartridge(Arc::clone(&cartridge));
     }
     
-    pub fn get_cpu(&mut self) -> Arc<Mutex<Cpu6502>>
-    {
-        Arc::clone(&self.cpu)
-    }
-
     pub fn get_ppu(&mut self) -> Arc<Mutex<Ppu2c02>>
     {
         Arc::clone(&self.ppu)
@@ -108,6 +106,11 @@ pub fn is_dma_transfer_in_progress(&self) -> bool
         self.dma_info.lock().unwrap().is_transfer_in_progress()
     }
 
+    pub fn is_dmc_fetch_in_progress(&self) -> bool
+    {
+        self.dma_info.lock().unwrap().is_dmc_fetch_in_progress()
+    }
+
     pub fn get_clock_counter(&self) -> u32
     {
         self.system_clock_counter
@@ -131,6 +134,63 @@ pub fn increment_clock_counter(&mut self)
     pub fn reset(&mut self)
     {
         self.system_clock_counter = 0;
+        if let Some(vgm_logger) = &mut self.vgm_logger
+        {
+            vgm_logger.resync(self.system_clock_counter);
+        }
+    }
+
+    // Starts logging APU register writes, dropping any log already in progress
+    pub fn start_vgm_log(&mut self)
+    {
+        self.vgm_logger = Some(VgmLogger::new(self.system_clock_counter));
+    }
+
+    // Returns the finished .vgm file, if a log was in progress
+    pub fn stop_vgm_log(&mut self) -> Option<Vec<u8>>
+    {
+        self.vgm_logger.take().map(|x| x.finish(self.system_clock_counter))
+    }
+
+    pub fn is_vgm_logging(&self) -> bool
+    {
+        self.vgm_logger.is_some()
+    }
+
+    // $4014 and $4016 sit in the middle of the APU's registers but belong to the DMA and
+    // controllers, and $4017 is only the APU's when writing
+    fn log_apu_write(&mut self, address: u16, data: u8)
+    {
+        if self.vgm_logger.is_none() || !(0x4000..=0x4017).contains(&address) || address == 0x4014 || address == 0x4016
+        {
+            return;
+        }
+
+        // Sample data has to be in the log before the DMC can start playing it. That is
+        // either right now for a $4015 write, or when it loops after $4012/$4013 changed.
+        let starts_sample = address == 0x4015 && data & 0x10 == 0x10;
+        let sample = if starts_sample || address == 0x4012 || address == 0x4013
+        {
+            let (sample_address, sample_length) = self.apu.lock().unwrap().get_dmc_sample_range();
+            let mut bytes = vec![0u8; sample_length as usize];
+            for (i, byte) in bytes.iter_mut().enumerate()
+            {
+                self.cpu_read(sample_address.wrapping_add(i as u16) | 0x8000, byte);
+            }
+            Some((sample_address, bytes))
+        }
+        else
+        {
+            None
+        };
+
+        let vgm_logger = self.vgm_logger.as_mut().unwrap();
+        if let Some((sample_address, bytes)) = sample
+        {
+            vgm_logger.write_dmc_memory(sample_address, &bytes);
+        }
+
+        vgm_logger.write_register(self.system_clock_counter, address, data);
     }
 
 }
@@ -151,14 +211,11 @@ fn cpu_write(&mut self, address: u16, data: u8) -> bool
             }
         }
 
-        // Add back once we have systems covering the whole address range
-        if !handled
-        {
-            panic!("Issued a write and no system handled it");
-        }
+        // Nothing answers some addresses, such as $4018-$5FFF on boards with no expansion hardware,
+        // so the write goes out onto the open bus and is lost
+        self.log_apu_write(address, data);
 
-        // If not handled, we already panicked
-        true
+        handled
     }
 
     fn cpu_read(&mut self, address: u16, data: &mut u8) -> bool
@@ -189,6 +246,71 @@ fn ppu_read(&self, _: u16, _: &mut u8) -> bool
     }
 }
 
+// How the CPU sees the bus, it shares it with the PPU and DMA so it only holds a handle
+impl CpuBus for Arc<Mutex<MainBus>>
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        let mut data: u8 = 0;
+        self.lock().unwrap().cpu_read(address, &mut data);
+        data
+    }
+
+    fn write(&mut self, address: u16, data: u8)
+    {
+        self.lock().unwrap().cpu_write(address, data);
+    }
+
+    // Reading the PPU, APU and controller registers has side effects (e.g. clearing vblank or
+    // shifting the controller), so those are shown as open bus ($FF) the same way nestest's
+    // reference log shows them
+    fn peek(&mut self, address: u16) -> u8
+    {
+        if (0x2000..=0x401F).contains(&address)
+        {
+            return 0xFF;
+        }
+
+        self.read(address)
+    }
+}
+
+// Only covers what the bus owns by itself, the CPU, PPU, APU and cartridge are saved by Nes
+impl Saveable for MainBus
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u32::<LittleEndian>(self.system_clock_counter)?;
+        self.cpu_ram.lock().unwrap().save_state(writer)?;
+        self.dma_info.lock().unwrap().save_state(writer)?;
+
+        for controller in &self.controllers
+        {
+            controller.lock().unwrap().save_state(writer)?;
+        }
+
+        Ok(())
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.system_clock_counter = reader.read_u32::<LittleEndian>()?;
+        if let Some(vgm_logger) = &mut self.vgm_logger
+        {
+            vgm_logger.resync(self.system_clock_counter);
+        }
+        self.cpu_ram.lock().unwrap().load_state(reader)?;
+        self.dma_info.lock().unwrap().load_state(reader)?;
+
+        for controller in &self.controllers
+        {
+            controller.lock().unwrap().load_state(reader)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Default for MainBus
 {
     fn default() -> Self
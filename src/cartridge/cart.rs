@@ -1,25 +1,16 @@
-use crate::traits::{ReadWrite, MapperTrait};
+use crate::savestate;
+use crate::traits::{ReadWrite, MapperTrait, Resettable, Saveable, MAPPED_INTERNALLY};
 
 use std::sync::{Arc, Mutex};
 use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom};
-use byteorder::ReadBytesExt;
+use std::io::{self, Read, Write};
 
 use crate::mapper::mapper000::Mapper000;
+use crate::mapper::mapper001::Mapper001;
 use crate::mapper::mapper002::Mapper002;
+use crate::mapper::mapper004::Mapper004;
 
-struct InesHeader
-{
-    _name: [u8; 4],
-    prg_rom_chunks: u8,
-    chr_rom_chunks: u8,
-    mapper_1: u8,
-    mapper_2: u8,
-    prg_ram_size: u8,
-    _tv_system_1: u8,
-    _tv_system_2: u8,
-    _unused: [u8; 5]
-}
+use crate::cartridge::ines_header::{CartridgeInfo, ConsoleType, InesHeader};
 
 #[derive(Debug, Clone, Copy)]
 pub enum MirrorMode
@@ -30,148 +21,255 @@ pub enum MirrorMode
     OneScreenHi
 }
 
-impl InesHeader
-{
-    pub fn new<R: Read>(mut reader: R) -> io::Result<Self>
-    {
-        let mut _name = [0u8; 4];
-        reader.read_exact(&mut _name)?;
-
-        let prg_rom_chunks = reader.read_u8()?;
-        let chr_rom_chunks = reader.read_u8()?;
-        let mapper_1 = reader.read_u8()?;
-        let mapper_2 = reader.read_u8()?;
-        let prg_ram_size = reader.read_u8()?;
-        let _tv_system_1 = reader.read_u8()?;
-        let _tv_system_2 = reader.read_u8()?;
-
-        let mut _unused = [0u8; 5];
-        reader.read_exact(&mut _unused)?;
-
-        Ok(InesHeader {
-            _name,
-            prg_rom_chunks,
-            chr_rom_chunks,
-            mapper_1,
-            mapper_2,
-            prg_ram_size,
-            _tv_system_1,
-            _tv_system_2,
-            _unused,
-        })
-    }
-}
-
 pub struct Cart
 {
     prg_memory: Vec<u8>,
     chr_memory: Vec<u8>,
-    mapper_id: u8,
+    prg_ram: Vec<u8>,
+    prg_ram_dirty: bool,
     prg_banks: u16,
     chr_banks: u16,
-    mirror_mode: MirrorMode,
-    mapper: Option<Arc<Mutex<dyn MapperTrait>>>
+    mapper: Option<Arc<Mutex<dyn MapperTrait>>>,
+    rom_hash: u64,
+    info: CartridgeInfo
 }
 
 impl Cart
 {
     pub fn new(filename: String) -> io::Result<Self>
     {
-        let mut file = File::open(filename)?;
-        let header = InesHeader::new(&mut file)?;
+        Cart::from_reader(File::open(filename)?)
+    }
+
+    // Loads a ROM in the iNES format from anything that holds one, e.g. a file or a buffer
+    pub fn from_reader<R: Read>(mut file: R) -> io::Result<Self>
+    {
+        let info = InesHeader::new(&mut file)?.parse()?;
 
-        // Skip training information
-        if header.mapper_1 & 0x04 == 0x04
+        match info.console_type
         {
-            file.seek(SeekFrom::Current(512))?;
+            ConsoleType::Nes => (),
+            ConsoleType::VsSystem { .. } => return Err(unsupported("Vs. System ROMs are not supported".to_string())),
+            ConsoleType::Playchoice10 => return Err(unsupported("PlayChoice-10 ROMs are not supported".to_string())),
+            ConsoleType::Extended(x) => return Err(unsupported(format!("extended console type {} is not supported", x)))
         }
 
+        // The mappers switch PRG in 16KB and CHR in 8KB units (or smaller)
+        if info.prg_rom_size == 0 || info.prg_rom_size % 0x4000 != 0
+        {
+            return Err(unsupported(format!("{} bytes of PRG ROM is not a whole number of 16KB banks", info.prg_rom_size)));
+        }
+
+        if info.chr_rom_size % 0x2000 != 0
+        {
+            return Err(unsupported(format!("{} bytes of CHR ROM is not a whole number of 8KB banks", info.chr_rom_size)));
+        }
+
+        // The trainer comes before the PRG ROM and is meant to be loaded at $7000
+        let trainer = if info.trainer
+        {
+            Cart::read_section(&mut file, InesHeader::TRAINER_SIZE, "trainer")?
+        }
+        else
+        {
+            Vec::new()
+        };
+
+        let prg_memory = Cart::read_section(&mut file, info.prg_rom_size, "PRG ROM")?;
+        let chr_memory = if info.chr_rom_size > 0
+        {
+            Cart::read_section(&mut file, info.chr_rom_size, "CHR ROM")?
+        }
+        else
+        {
+            // The board has CHR RAM instead, the file has no data for it. Headers that claim
+            // to have neither get the usual 8KB.
+            let chr_ram_size = info.chr_ram_size + info.chr_nvram_size;
+            vec![0; if chr_ram_size == 0 { 0x2000 } else { chr_ram_size }]
+        };
+
         let mut s = Cart
         {
-            prg_memory: Vec::new(),
-            chr_memory: Vec::new(),
-            mapper_id: ((header.mapper_2 >> 4) << 4) | (header.mapper_1 >> 4),
-            prg_banks: 0,
-            chr_banks: 0,
+            prg_banks: (info.prg_rom_size / 0x4000) as u16,
+            chr_banks: (info.chr_rom_size / 0x2000) as u16,
+            prg_memory,
+            chr_memory,
+            // Work RAM at $6000-$7FFF, only reachable if the mapper maps it. Volatile and
+            // battery-backed RAM share the range, the whole of it goes to the .sav file.
+            prg_ram: vec![0; info.prg_ram_size + info.prg_nvram_size],
+            prg_ram_dirty: false,
             mapper: None,
-            mirror_mode: MirrorMode::Horizontal
+            rom_hash: 0,
+            info
         };
 
-        if header.mapper_1 & 0x01 == 0x01
+        if s.prg_ram.len() >= 0x2000
         {
-            s.mirror_mode = MirrorMode::Vertical;
+            s.prg_ram[0x1000..0x1000 + trainer.len()].copy_from_slice(&trainer);
         }
 
-        let mut file_type: u8 = 1;
-        if (header.mapper_2 & 0x0C) == 0x08
+        // Only used by boards without mirroring control, the others pick it at runtime
+        let mirror_mode = s.info.mirror_mode;
+
+        s.mapper = match s.info.mapper
+        {
+            0 => Some(Arc::new(Mutex::new(Mapper000::new(s.prg_banks, s.chr_banks, mirror_mode)))),
+            1 => Some(Arc::new(Mutex::new(Mapper001::new(s.prg_banks, s.chr_banks)))),
+            2 => Some(Arc::new(Mutex::new(Mapper002::new(s.prg_banks, s.chr_banks, mirror_mode)))),
+            4 => Some(Arc::new(Mutex::new(Mapper004::new(s.prg_banks, s.chr_banks)))),
+            x => return Err(unsupported(format!("mapper {} is not supported", x)))
+        };
+
+        s.rom_hash = savestate::hash_rom(&[&s.prg_memory, &s.chr_memory]);
+
+        Ok(s)
+    }
+
+    // Reads the next part of the ROM file, the size comes from the header so it's checked
+    // against what the file actually holds before anything is allocated for it
+    fn read_section<R: Read>(file: &mut R, size: usize, name: &str) -> io::Result<Vec<u8>>
+    {
+        let mut data = Vec::new();
+        file.by_ref().take(size as u64).read_to_end(&mut data)?;
+
+        if data.len() != size
         {
-            file_type = 2;
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("the file is too short, the header says it has {} bytes of {} but only {} are left", size, name, data.len())));
         }
 
-        if file_type == 1
+        Ok(data)
+    }
+
+    // What the header says about the cartridge
+    pub fn get_info(&self) -> &CartridgeInfo
+    {
+        &self.info
+    }
+
+    pub fn get_mirror_mode(&self) -> MirrorMode
+    {
+        match &self.mapper
         {
-            s.prg_banks = header.prg_rom_chunks as u16;
-            s.prg_memory.resize(s.prg_banks as usize * 16384, 0);
-            file.read_exact(&mut s.prg_memory)?;
+            Some(x) => x.lock().unwrap().mirror_mode(),
+            None => panic!("No mapper set for cartridge")
+        }
+    }
 
-            s.chr_banks = header.chr_rom_chunks as u16;
-            if s.chr_banks == 0
-            {
-                // This is considered a RAM
-                s.chr_memory.resize(8192, 0);
-            }
-            else
-            {
-                // This is considered a ROM
-                s.chr_memory.resize(s.chr_banks as usize * 8192, 0);
-            }
-            file.read_exact(&mut s.chr_memory)?;
+    // Lets mappers that count CPU cycles keep time
+    pub fn cpu_clock(&mut self)
+    {
+        match &self.mapper
+        {
+            Some(x) => x.lock().unwrap().cpu_clock(),
+            None => panic!("No mapper set for cartridge")
         }
-        else if file_type == 2
+    }
+
+    // Mappers with a scanline counter or similar can interrupt the CPU
+    pub fn is_irq_active(&self) -> bool
+    {
+        match &self.mapper
         {
-            if header.prg_ram_size & 0x0F == 0x0F
-            {
-                // Exponent multiplier notation
-                panic!("not supported")
-            }
-            else
-            {
-                s.prg_banks = ((header.prg_ram_size as u16 & 0x0F) << 8) | header.prg_rom_chunks as u16;
-                s.prg_memory.resize(s.prg_banks as usize * 16384, 0);
-                file.read_exact(&mut s.prg_memory)?;
-            }
+            Some(x) => x.lock().unwrap().irq_active(),
+            None => false
+        }
+    }
 
-            if header.prg_ram_size & 0xF0 == 0xF0
-            {
-                // Exponent multiplier notation
-                panic!("not supported")
-            }
-            else
-            {
-                s.chr_banks = ((header.prg_ram_size as u16 & 0xF0) << 4) | header.chr_rom_chunks as u16;
-                s.chr_memory.resize(s.chr_banks as usize * 8192, 0);
-                file.read_exact(&mut s.chr_memory)?;
-            }
-            
+    // Identifies the game a save state belongs to
+    pub fn get_rom_hash(&self) -> u64
+    {
+        self.rom_hash
+    }
+
+    // Whether the PRG RAM is kept alive by a battery, so it should be persisted to a .sav file
+    pub fn has_battery(&self) -> bool
+    {
+        self.info.battery
+    }
+
+    pub fn get_prg_ram(&self) -> &[u8]
+    {
+        &self.prg_ram
+    }
+
+    // Replaces the PRG RAM, e.g. with the contents of a .sav file
+    pub fn set_prg_ram(&mut self, data: &[u8]) -> io::Result<()>
+    {
+        if data.len() != self.prg_ram.len()
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("expected {} bytes of PRG RAM, got {}", self.prg_ram.len(), data.len())));
         }
 
-        // TODO: file type 0
+        self.prg_ram.copy_from_slice(data);
+        self.prg_ram_dirty = false;
+        Ok(())
+    }
+
+    // Returns whether the PRG RAM was written to since the last call
+    pub fn take_prg_ram_dirty(&mut self) -> bool
+    {
+        std::mem::replace(&mut self.prg_ram_dirty, false)
+    }
+
+    fn has_chr_ram(&self) -> bool
+    {
+        self.chr_banks == 0
+    }
 
-        s.mapper = match s.mapper_id
+    // Palette RAM is inside the PPU, accesses to it never show up on the cartridge's bus
+    fn notify_ppu_address(mapper: &mut dyn MapperTrait, address: u16)
+    {
+        if address < 0x3F00
         {
-            0 => Some(Arc::new(Mutex::new(Mapper000::new(s.prg_banks, s.chr_banks)))),
-            2 => Some(Arc::new(Mutex::new(Mapper002::new(s.prg_banks, s.chr_banks)))),
-            _ => panic!("Invalid mapper type, not supported")
-        };
+            mapper.ppu_address(address);
+        }
+    }
 
-        Ok(s)
+    // Whether a CPU address mapped by the mapper refers to PRG RAM rather than PRG ROM
+    fn is_prg_ram_address(address: u16) -> bool
+    {
+        (0x6000..=0x7FFF).contains(&address)
     }
 
-    pub fn get_mirror_mode(&self) -> MirrorMode
+}
+
+impl Saveable for Cart
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
     {
-        self.mirror_mode
+        // CHR ROM can't change, only CHR RAM needs to be saved
+        if self.has_chr_ram()
+        {
+            writer.write_all(&self.chr_memory)?;
+        }
+
+        writer.write_all(&self.prg_ram)?;
+
+        match &self.mapper
+        {
+            Some(x) => x.lock().unwrap().save_state(writer),
+            None => panic!("No mapper set for cartridge")
+        }
     }
 
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        if self.has_chr_ram()
+        {
+            reader.read_exact(&mut self.chr_memory)?;
+        }
+
+        reader.read_exact(&mut self.prg_ram)?;
+        self.prg_ram_dirty = true;
+
+        match &self.mapper
+        {
+            Some(x) => x.lock().unwrap().load_state(reader),
+            None => panic!("No mapper set for cartridge")
+        }
+    }
 }
 
 impl ReadWrite for Cart
@@ -185,9 +283,22 @@ fn cpu_write(&mut self, address: u16, data: u8) -> bool
             None => panic!("No mapper set for cartridge")
         };
 
-        if handled
+        if handled && mapped_addr != MAPPED_INTERNALLY
         {
-            self.prg_memory[mapped_addr as usize] = data;
+            if Cart::is_prg_ram_address(address)
+            {
+                // Boards without PRG RAM drop the write
+                if !self.prg_ram.is_empty()
+                {
+                    let index = mapped_addr as usize % self.prg_ram.len();
+                    self.prg_ram_dirty |= self.prg_ram[index] != data;
+                    self.prg_ram[index] = data;
+                }
+            }
+            else
+            {
+                self.prg_memory[mapped_addr as usize] = data;
+            }
         }
 
         handled
@@ -202,9 +313,19 @@ fn cpu_read(&mut self, address: u16, data: &mut u8) -> bool
             None => panic!("No mapper set for cartridge")
         };
 
-        if handled
+        if handled && mapped_addr != MAPPED_INTERNALLY
         {
-            *data = self.prg_memory[mapped_addr as usize];
+            if Cart::is_prg_ram_address(address)
+            {
+                if !self.prg_ram.is_empty()
+                {
+                    *data = self.prg_ram[mapped_addr as usize % self.prg_ram.len()];
+                }
+            }
+            else
+            {
+                *data = self.prg_memory[mapped_addr as usize];
+            }
         }
 
         handled
@@ -215,7 +336,12 @@ fn ppu_write(&mut self, address: u16, data: u8) -> bool
         let mut mapped_addr: u32 = 0;
         let handled = match &self.mapper
         {
-            Some(x) => x.lock().unwrap().ppu_map_write(address, &mut mapped_addr, data),
+            Some(x) =>
+            {
+                let mut mapper = x.lock().unwrap();
+                Cart::notify_ppu_address(&mut *mapper, address);
+                mapper.ppu_map_write(address, &mut mapped_addr, data)
+            },
             None => panic!("No mapper set for cartridge")
         };
 
@@ -232,7 +358,12 @@ fn ppu_read(&self, address: u16, data: &mut u8) -> bool
         let mut mapped_addr: u32 = 0;
         let handled = match &self.mapper
         {
-            Some(x) => x.lock().unwrap().ppu_map_read(address, &mut mapped_addr),
+            Some(x) =>
+            {
+                let mut mapper = x.lock().unwrap();
+                Cart::notify_ppu_address(&mut *mapper, address);
+                mapper.ppu_map_read(address, &mut mapped_addr)
+            },
             None => panic!("No mapper set for cartridge")
         };
 
@@ -245,4 +376,21 @@ fn ppu_read(&self, address: u16, data: &mut u8) -> bool
     }
 }
 
-unsafe impl Send for Cart {}
\ No newline at end of file
+impl Resettable for Cart
+{
+    fn reset(&mut self)
+    {
+        match &self.mapper
+        {
+            Some(x) => x.lock().unwrap().reset(),
+            None => panic!("No mapper set for cartridge")
+        }
+    }
+}
+
+unsafe impl Send for Cart {}
+
+fn unsupported(message: String) -> io::Error
+{
+    io::Error::new(io::ErrorKind::Unsupported, message)
+}
@@ -0,0 +1,262 @@
+use std::io::{self, Read};
+
+use crate::cartridge::cart::MirrorMode;
+
+// The 16 byte header at the start of .nes files, based on https://www.nesdev.org/wiki/INES and
+// https://www.nesdev.org/wiki/NES_2.0
+//
+// 0-3   "NES" followed by MS-DOS EOF
+// 4     PRG ROM size, LSB
+// 5     CHR ROM size, LSB
+// 6     flags 6: mirroring, battery, trainer, four screen, mapper D0-D3
+// 7     flags 7: console type, NES 2.0 identifier, mapper D4-D7
+// 8     iNES: PRG RAM size             NES 2.0: mapper D8-D11, submapper
+// 9     iNES: TV system                NES 2.0: PRG/CHR ROM size MSB
+// 10    NES 2.0: PRG RAM/NVRAM shift counts
+// 11    NES 2.0: CHR RAM/NVRAM shift counts
+// 12    NES 2.0: CPU/PPU timing
+// 13    NES 2.0: Vs. System type or extended console type
+// 14    NES 2.0: miscellaneous ROMs
+// 15    NES 2.0: default expansion device
+pub struct InesHeader
+{
+    bytes: [u8; 16]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderFormat
+{
+    Ines,
+    Nes20
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleType
+{
+    Nes,
+    VsSystem { ppu_type: u8, hardware_type: u8 },
+    Playchoice10,
+    Extended(u8)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingMode
+{
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy
+}
+
+// Everything the header says about the cartridge, sizes are in bytes
+#[derive(Debug, Clone)]
+pub struct CartridgeInfo
+{
+    pub format: HeaderFormat,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub mirror_mode: MirrorMode,
+    pub four_screen: bool,
+    pub battery: bool,
+    pub trainer: bool,
+    pub console_type: ConsoleType,
+    pub timing: TimingMode,
+    pub miscellaneous_roms: u8,
+    pub default_expansion_device: u8
+}
+
+impl InesHeader
+{
+    pub const SIZE: usize = 16;
+    pub const TRAINER_SIZE: usize = 512;
+
+    pub fn new<R: Read>(mut reader: R) -> io::Result<Self>
+    {
+        let mut bytes = [0u8; InesHeader::SIZE];
+        reader.read_exact(&mut bytes).map_err(|e| match e.kind()
+        {
+            io::ErrorKind::UnexpectedEof => invalid_data("the file is too short to hold an iNES header"),
+            _ => e
+        })?;
+
+        if bytes[0..4] != *b"NES\x1A"
+        {
+            return Err(invalid_data("not an iNES ROM, the header is missing its NES<EOF> marker"));
+        }
+
+        Ok(InesHeader { bytes })
+    }
+
+    pub fn get_format(&self) -> HeaderFormat
+    {
+        if self.bytes[7] & 0x0C == 0x08
+        {
+            HeaderFormat::Nes20
+        }
+        else
+        {
+            HeaderFormat::Ines
+        }
+    }
+
+    pub fn parse(&self) -> io::Result<CartridgeInfo>
+    {
+        let flags_6 = self.bytes[6];
+        let flags_7 = self.bytes[7];
+        let format = self.get_format();
+
+        let mut info = CartridgeInfo
+        {
+            format,
+            mapper: ((flags_7 & 0xF0) | (flags_6 >> 4)) as u16,
+            submapper: 0,
+            prg_rom_size: 0,
+            chr_rom_size: 0,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            mirror_mode: if flags_6 & 0x01 == 0x01 { MirrorMode::Vertical } else { MirrorMode::Horizontal },
+            battery: flags_6 & 0x02 == 0x02,
+            trainer: flags_6 & 0x04 == 0x04,
+            four_screen: flags_6 & 0x08 == 0x08,
+            console_type: ConsoleType::Nes,
+            timing: TimingMode::Ntsc,
+            miscellaneous_roms: 0,
+            default_expansion_device: 0
+        };
+
+        match format
+        {
+            HeaderFormat::Ines => self.parse_ines(&mut info),
+            HeaderFormat::Nes20 => self.parse_nes20(&mut info)?
+        }
+
+        Ok(info)
+    }
+
+    fn parse_ines(&self, info: &mut CartridgeInfo)
+    {
+        // Old dumping tools wrote their name ("DiskDude!") into bytes 7-15, in which case
+        // everything but the lower half of the mapper number is garbage
+        let archaic = self.bytes[12..16].iter().any(|x| *x != 0);
+        if archaic
+        {
+            info.mapper &= 0x0F;
+        }
+        else
+        {
+            info.console_type = match self.bytes[7] & 0x03
+            {
+                1 => ConsoleType::VsSystem { ppu_type: 0, hardware_type: 0 },
+                2 => ConsoleType::Playchoice10,
+                _ => ConsoleType::Nes
+            };
+
+            if self.bytes[9] & 0x01 == 0x01
+            {
+                info.timing = TimingMode::Pal;
+            }
+        }
+
+        info.prg_rom_size = self.bytes[4] as usize * 0x4000;
+        info.chr_rom_size = self.bytes[5] as usize * 0x2000;
+
+        // Counted in 8KB units, 0 means 8KB for compatibility with older dumps
+        let prg_ram_units = if archaic { 1 } else { self.bytes[8].max(1) };
+        let prg_ram_size = prg_ram_units as usize * 0x2000;
+        if info.battery
+        {
+            info.prg_nvram_size = prg_ram_size;
+        }
+        else
+        {
+            info.prg_ram_size = prg_ram_size;
+        }
+
+        // No CHR ROM means the board has CHR RAM instead
+        if info.chr_rom_size == 0
+        {
+            info.chr_ram_size = 0x2000;
+        }
+    }
+
+    fn parse_nes20(&self, info: &mut CartridgeInfo) -> io::Result<()>
+    {
+        info.mapper |= ((self.bytes[8] & 0x0F) as u16) << 8;
+        info.submapper = self.bytes[8] >> 4;
+
+        info.prg_rom_size = InesHeader::rom_size(self.bytes[4], self.bytes[9] & 0x0F, 0x4000)
+            .ok_or_else(|| invalid_data("the PRG ROM size in the NES 2.0 header is too large"))?;
+        info.chr_rom_size = InesHeader::rom_size(self.bytes[5], self.bytes[9] >> 4, 0x2000)
+            .ok_or_else(|| invalid_data("the CHR ROM size in the NES 2.0 header is too large"))?;
+
+        info.prg_ram_size = InesHeader::shift_size(self.bytes[10] & 0x0F);
+        info.prg_nvram_size = InesHeader::shift_size(self.bytes[10] >> 4);
+        info.chr_ram_size = InesHeader::shift_size(self.bytes[11] & 0x0F);
+        info.chr_nvram_size = InesHeader::shift_size(self.bytes[11] >> 4);
+
+        info.timing = match self.bytes[12] & 0x03
+        {
+            0 => TimingMode::Ntsc,
+            1 => TimingMode::Pal,
+            2 => TimingMode::MultiRegion,
+            3 => TimingMode::Dendy,
+            _ => panic!("Impossible")
+        };
+
+        info.console_type = match self.bytes[7] & 0x03
+        {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem { ppu_type: self.bytes[13] & 0x0F, hardware_type: self.bytes[13] >> 4 },
+            2 => ConsoleType::Playchoice10,
+            3 => ConsoleType::Extended(self.bytes[13] & 0x0F),
+            _ => panic!("Impossible")
+        };
+
+        info.miscellaneous_roms = self.bytes[14] & 0x03;
+        info.default_expansion_device = self.bytes[15] & 0x3F;
+
+        Ok(())
+    }
+
+    // ROM sizes are counted in units, unless the MSB nibble is $F. Then the LSB holds an
+    // exponent (bits 2-7) and a multiplier (bits 0-1) for a size of 2^E * (MM * 2 + 1) bytes.
+    fn rom_size(lsb: u8, msb: u8, unit: usize) -> Option<usize>
+    {
+        if msb == 0x0F
+        {
+            let exponent = (lsb >> 2) as u32;
+            let multiplier = ((lsb & 0x03) * 2 + 1) as usize;
+            1usize.checked_shl(exponent)?.checked_mul(multiplier)
+        }
+        else
+        {
+            (((msb as usize) << 8) | lsb as usize).checked_mul(unit)
+        }
+    }
+
+    // RAM sizes are 64 << n bytes, or no RAM at all when n is 0
+    fn shift_size(shift: u8) -> usize
+    {
+        if shift == 0
+        {
+            0
+        }
+        else
+        {
+            64 << shift
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
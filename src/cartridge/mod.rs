@@ -1 +1,3 @@
-pub mod cart;
\ No newline at end of file
+pub mod cart;
+
+pub mod ines_header;
\ No newline at end of file
@@ -1,8 +1,8 @@
-use crate::bus::main_bus::MainBus;
-use crate::traits::{ReadWrite, Clockable, Resettable};
-use crate::cartridge::cart::Cart;
+use crate::traits::{Clockable, CpuBus, Resettable, Saveable};
+use crate::savestate;
 use std::fmt;
-use std::sync::{Arc, Mutex};
+use std::io::{self, Read, Write};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::collections::BTreeMap;
 
 include!("instructions.rs");
@@ -13,25 +13,202 @@ pub enum Flags6502
     C = (1 << 0), // Carry Bit
     Z = (1 << 1), // Zero
     I = (1 << 2), // Disable Interrupts
-    D = (1 << 3), // Decimal Mode (unused)
+    D = (1 << 3), // Decimal Mode (ignored unless decimal mode is enabled)
     B = (1 << 4), // Break
     U = (1 << 5), // Unused
     V = (1 << 6), // Overflow
     N = (1 << 7), // Negative
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AddressingMode
+{
+    Imp,
+    Imm,
+    Zp0,
+    Zpx,
+    Zpy,
+    Rel,
+    Abs,
+    Abx,
+    Aby,
+    Ind,
+    Izx,
+    Izy
+}
+
+// One per instruction, shared by all of its addressing modes
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operation
+{
+    Adc,
+    Ahx,
+    Alr,
+    Anc,
+    And,
+    Arr,
+    Asl,
+    Axs,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Brk,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    Cpx,
+    Cpy,
+    Dcp,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    Isb,
+    Jmp,
+    Jsr,
+    Kil,
+    Las,
+    Lax,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    Nop,
+    Ora,
+    Pha,
+    Php,
+    Pla,
+    Plp,
+    Rla,
+    Rol,
+    Ror,
+    Rra,
+    Rti,
+    Rts,
+    Sax,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    Shx,
+    Shy,
+    Slo,
+    Sre,
+    Sta,
+    Stx,
+    Sty,
+    Tas,
+    Tax,
+    Tay,
+    Tsx,
+    Txa,
+    Txs,
+    Tya,
+    Xaa
+}
+
+// What an instruction does with the memory its addressing mode points at, which decides the
+// cycles it takes once the address is known
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access
+{
+    Read,
+    Write,
+    ReadModifyWrite
+}
+
+// What the CPU is working through. Interrupts and reset take the place of an instruction.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Sequence
+{
+    Instruction,
+    Interrupt,
+    Reset
+}
+
+// One cycle of work after the opcode fetch. Each micro-op makes exactly one bus access, and the
+// reads the CPU has no use for are still made since registers like $2002 and $2007 react to them
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MicroOp
+{
+    // Dummy read of the byte after the opcode
+    ReadPc,
+    // Same, but the PC moves past it (BRK's padding byte and the end of RTS)
+    ReadPcIncrement,
+    ReadImmediate,
+    // The low byte is all that a zero page address has
+    FetchAddressLo,
+    FetchAddressHi,
+    // The index is added to the low byte alongside the high byte fetch, carrying into the high
+    // byte takes the next cycle
+    FetchAddressHiIndexX,
+    FetchAddressHiIndexY,
+    // Dummy read of the zero page address while the index is added, without leaving the page
+    IndexZeroPageX,
+    IndexZeroPageY,
+    FetchPointerLo,
+    FetchPointerHi,
+    IndexPointerX,
+    // The high byte of an address read through a pointer comes from the pointer's own page, both
+    // for the zero page and for JMP ($xxFF)
+    FetchIndirectLo,
+    FetchIndirectHi,
+    FetchIndirectHiIndexY,
+    // Read from the indexed address before the carry has reached the high byte. If there was
+    // nothing to carry, a read instruction already has its operand and is done.
+    ReadPageCross,
+    ReadOperand,
+    // Read-modify-write instructions write the old value back while they work out the new one
+    WriteDummy,
+    // The instruction makes the write itself
+    Write,
+    // The branch is decided as soon as the offset is in, not taking it ends the instruction
+    FetchBranchOffset,
+    // Dummy read of the next opcode while the low byte of the PC moves, fixing the high byte
+    // takes another cycle
+    BranchTaken,
+    BranchFixPage,
+    // Dummy reads of the stack, where the CPU is moving the stack pointer
+    ReadStack,
+    IncrementStack,
+    // Reset goes through the pushes of an interrupt with the bus held in read mode
+    ReadStackDecrement,
+    PushPcHi,
+    PushPcLo,
+    PushStatus,
+    PullStatus,
+    PullPcLo,
+    PullPcHi,
+    PullOperand,
+    FetchVectorLo,
+    FetchVectorHi
+}
+
+// The op runs on the last cycle of the instruction, after its addressing mode has made the reads
+// for it. Branches return 1 when they are taken, which adds the cycles that move the PC.
 struct Instruction
 {
-    name: String,
-    op: fn(&mut Cpu6502) -> u8,
-    addr_mode: fn(&mut Cpu6502) -> u8,
-    cycles: u8
+    name: &'static str,
+    op: Operation,
+    addr_mode: AddressingMode
 }
 
-pub struct Cpu6502
+// An NMOS 6502 that runs on whatever bus it's given. The NES's 2A03 is the default: it has the
+// decimal flag, but not the circuitry behind it.
+pub struct Cpu6502<B: CpuBus>
 {
-    bus: Option<Arc<Mutex<MainBus>>>,
-    cartridge: Option<Arc<Mutex<Cart>>>,
+    bus: B,
     a: u8,
     x: u8,
     y: u8,
@@ -41,28 +218,54 @@ pub struct Cpu6502
     fetched_data: u8,
     addr_abs: u16,
     addr_rel: u16,
+    // Zero page or 16-bit pointer for the indirect addressing modes
+    pointer: u16,
+    // Whether adding the index to the address carried into the high byte
+    page_crossed: bool,
     opcode: u8,
-    cycles: u8,
+    sequence: Sequence,
+    // Cycles into the current sequence, 0 when the next cycle starts a new one
+    step: u8,
     total_cycles: i64,
-    ins: [Instruction; 256]
+    irq_line: bool,
+    // NMI is edge triggered, so it's latched until the CPU gets to it
+    nmi_pending: bool,
+    // What the interrupt poll at the end of the last cycle found
+    interrupt_polled: bool,
+    // Set by KIL, the CPU stops fetching and ignores interrupts until it is reset
+    jammed: bool,
+    // Whether ADC and SBC honour the D flag, off for the 2A03
+    decimal_mode: bool
 }
 
-// Note: We are only doing address comparisons within this class and they are
-// the easiest way to implement this. I might revisit this, but it isn't causing issues.
-#[allow(clippy::fn_address_comparisons)]
-impl Cpu6502
+impl<B: CpuBus> Cpu6502<B>
 {
     const STACK_START_ADDRESS: u16 = 0x0100;
-    const INTERRUPT_VECTOR: [u16; 2] = [0xFFFE, 0xFFFF];
+    const NMI_VECTOR: u16 = 0xFFFA;
+    const RESET_VECTOR: u16 = 0xFFFC;
+    const IRQ_VECTOR: u16 = 0xFFFE;
 
-    pub fn set_bus(&mut self, bus: Option<Arc<Mutex<MainBus>>>)
+    pub fn get_bus(&self) -> &B
     {
-        self.bus = bus;
+        &self.bus
     }
 
-    pub fn connect_cartridge(&mut self, cartridge: Arc<Mutex<Cart>>)
+    pub fn get_bus_mut(&mut self) -> &mut B
     {
-        self.cartridge = Some(cartridge);
+        &mut self.bus
+    }
+
+    // ADC and SBC work in binary coded decimal while D is set. The 2A03 ignores D, other 6502s
+    // need this turned on.
+    pub fn set_decimal_mode(&mut self, enabled: bool)
+    {
+        self.decimal_mode = enabled;
+    }
+
+    // Used to start execution somewhere other than the reset vector, e.g. nestest's automation mode at $C000
+    pub fn set_pc(&mut self, pc: u16)
+    {
+        self.pc = pc;
     }
 
     pub fn get_flag(&self, f: Flags6502) -> u8
@@ -89,20 +292,6 @@ pub fn set_flag(&mut self, f: Flags6502, b: bool)
         }
     }
 
-    pub fn cpu_read_u16_from_pc(&mut self) -> (bool, u16, u16, u16)
-    {
-        let mut lo: u8 = 0;
-        let mut hi: u8 = 0;
-
-        self.cpu_read(self.pc, &mut lo);
-        self.pc += 1;
-        self.cpu_read(self.pc, &mut hi);
-        self.pc += 1;
-
-        let data: u16 = ((hi as u16) << 8) | (lo as u16);
-        (true, hi as u16, lo as u16, data)
-    }
-
     pub fn get_pc(&self) -> u16
     {
         self.pc
@@ -128,183 +317,25 @@ pub fn get_stkp(&self) -> u8
         self.stkp
     }
 
-    // #region Addressing Modes
-    pub fn imp(&mut self) -> u8
+    pub fn get_status(&self) -> u8
     {
-        // The instruction may operate on the accumulator
-        self.fetched_data = self.a;
-        0
+        self.status
     }
 
-    pub fn imm(&mut self) -> u8
+    pub fn get_total_cycles(&self) -> i64
     {
-        self.addr_abs = self.pc;
-        self.pc += 1;
-        0
+        self.total_cycles
     }
 
-    pub fn zp0(&mut self) -> u8
+    // The opcode of the instruction being executed, or the KIL that jammed the CPU
+    pub fn get_opcode(&self) -> u8
     {
-        // Address in the zero page (plus a byte offset that is read)
-        let mut addr_abs_u8: u8 = 0;
-        self.cpu_read(self.pc, &mut addr_abs_u8);
-        self.addr_abs = addr_abs_u8 as u16;
-        self.pc += 1;
-        self.addr_abs &= 0x00FF;
-        0
-    }
-
-    pub fn zpx(&mut self) -> u8
-    {
-        // Address in the zero page (plus a byte offset that is read and incremented by X register)
-        // This is used for iterating through ranges in zero page
-        let mut addr_abs_u8: u8 = 0;
-        self.cpu_read(self.pc, &mut addr_abs_u8);
-        self.addr_abs = addr_abs_u8 as u16 + self.x as u16;
-        self.pc += 1;
-        self.addr_abs &= 0x00FF;
-        0
+        self.opcode
     }
 
-    pub fn zpy(&mut self) -> u8
+    pub fn is_jammed(&self) -> bool
     {
-        // Address in the zero page (plus a byte offset that is read and incremented by Y register)
-        let mut addr_abs_u8: u8 = 0;
-        self.cpu_read(self.pc, &mut addr_abs_u8);
-        self.addr_abs = addr_abs_u8 as u16 + self.y as u16;
-        self.pc += 1;
-        self.addr_abs &= 0x00FF;
-        0
-    }
-
-    pub fn rel(&mut self) -> u8
-    {
-        let mut addr_rel_u8: u8 = 0;
-        self.cpu_read(self.pc, &mut addr_rel_u8);
-        self.addr_rel = addr_rel_u8 as u16;
-        self.pc += 1;
-
-        // If we have a negative offset, we need to set upper bits to 1
-        if self.addr_rel & 0x80 == 0x80
-        {
-            self.addr_rel |= 0xFF00;
-        }
-
-        0
-    }
-
-    pub fn abs(&mut self) -> u8
-    {
-        // Absolute addressing (full u16 address)
-        (_, _, _, self.addr_abs) = self.cpu_read_u16_from_pc();
-        0 
-    }
-
-    pub fn abx(&mut self) -> u8
-    {
-        // Absolute addressing (full u16 address) with X offset
-        let hi: u16;
-        (_, hi, _, self.addr_abs) = self.cpu_read_u16_from_pc();
-        self.addr_abs = self.addr_abs.wrapping_add(self.x as u16);
-    
-        // If this crosses a page boundary, we need an additional clock cycle for this instruction
-        if (self.addr_abs & 0xFF00) != (hi << 8)
-        {
-            1
-        }
-        else
-        {
-            0	
-        }
-    }
-
-    pub fn aby(&mut self) -> u8
-    {
-        // Absolute addressing (full u16 address) with Y offset
-        let hi: u16;
-        (_, hi, _, self.addr_abs) = self.cpu_read_u16_from_pc();
-        self.addr_abs = self.addr_abs.wrapping_add(self.y as u16);
-
-        // If this crosses a page boundary, we need an additional clock cycle for this instruction
-        if (self.addr_abs & 0xFF00) != (hi << 8)
-        {
-            1
-        }
-        else
-        {
-            0	
-        }
-    }
-
-    pub fn ind(&mut self) -> u8
-    {
-        let ptr: u16;
-        let ptr_lo: u16;
-        (_, _, ptr_lo, ptr) = self.cpu_read_u16_from_pc();
-
-        let mut lo: u8 = 0;
-        let mut hi: u8 = 0;
-        if ptr_lo == 0x00FF
-        {
-            self.cpu_read(ptr, &mut lo);
-            self.cpu_read(ptr & 0xFF00, &mut hi);
-
-            self.addr_abs = ((hi as u16) << 8) | lo as u16;
-        }
-        else
-        {
-            self.cpu_read(ptr, &mut lo);
-            self.cpu_read(ptr + 1, &mut hi);
-
-            self.addr_abs = ((hi as u16) << 8) | lo as u16;
-        }
-
-        0
-    }
-
-    // The read 8-bit address is offset by X and used to read the 16-bit absolute address
-    pub fn izx(&mut self) -> u8
-    {
-        let mut t : u8 = 0;
-        self.cpu_read(self.pc, &mut t);
-        let t_u16 = t as u16;
-	    self.pc += 1;
-
-        let mut lo: u8 = 0;
-        self.cpu_read((t_u16 + self.x as u16) & 0x00FF, &mut lo);
-        let mut hi: u8 = 0;
-        self.cpu_read((t_u16 + self.x as u16 + 1) & 0x00FF, &mut hi);
-
-        self.addr_abs = ((hi as u16) << 8) | lo as u16;
-
-        0
-    }
-
-    // The read 8-bit address is read from the zero page as a 16-bit address, then offset by Y
-    // If the offset changes a page, we need 1 additional clock cycle
-    pub fn izy(&mut self) -> u8
-    {
-        let mut t : u8 = 0;
-        self.cpu_read(self.pc, &mut t);
-        let t_u16 = t as u16;
-	    self.pc += 1;
-
-        let mut lo: u8 = 0;
-        let mut hi: u8 = 0;
-
-        self.cpu_read(t_u16 & 0x00FF, &mut lo);
-        self.cpu_read((t_u16 + 1) & 0x00FF, &mut hi);
-        self.addr_abs = (lo as u16) | ((hi as u16) << 8);
-        self.addr_abs = self.addr_abs.wrapping_add(self.y as u16);
-
-        if (self.addr_abs & 0xFF00) != ((hi as u16) << 8)
-        {
-            1
-        }
-        else
-        {
-            0
-        }
+        self.jammed
     }
 
     // Opcodes
@@ -313,6 +344,12 @@ pub fn izy(&mut self) -> u8
     pub fn adc(&mut self) -> u8 
     {
         self.fetch();
+        if self.is_decimal()
+        {
+            self.add_decimal(self.fetched_data);
+            return 0;
+        }
+
         let temp: u16 = self.a as u16 + self.fetched_data as u16 + self.get_flag(Flags6502::C) as u16;
 
         // If we overflow into 16-bit range, set the carry bit
@@ -351,7 +388,7 @@ pub fn adc(&mut self) -> u8
         // Load the result into the accumulator
         self.a = (temp & 0x00FF) as u8;
 
-        1
+        0
     }
 
     // Instruction: Bitwise Logic AND
@@ -362,7 +399,7 @@ pub fn and(&mut self) -> u8
         self.a &= self.fetched_data;
         self.set_flag(Flags6502::Z, self.a == 0x00); // Zero
         self.set_flag(Flags6502::N, self.a & 0x80 == 0x80); // Negative
-        1
+        0
     }
     
     // Instruction: Arithmetic Shift Left
@@ -375,63 +412,50 @@ pub fn asl(&mut self) -> u8
         self.set_flag(Flags6502::Z, (temp & 0x00FF) == 0x00); // Zero
         self.set_flag(Flags6502::N, temp & 0x80 == 0x80); // Negative
 
-        if self.ins[self.opcode as usize].addr_mode == Cpu6502::imp
+        if INSTRUCTIONS[self.opcode as usize].addr_mode == AddressingMode::Imp
         {
             self.a = (temp & 0x00FF) as u8;
         }
         else
         {
-            self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+            self.write(self.addr_abs, (temp & 0x00FF) as u8);
         }
 
         0
     }
 
-    fn branch(&mut self)
+    // Branches return 1 when they are taken, the micro-ops then spend the cycles moving the PC
+    fn branch(condition: bool) -> u8
     {
-        self.cycles += 1;
-        let overflow_addr: u32 = self.pc as u32 + self.addr_rel as u32;
-        self.addr_abs = overflow_addr as u16;
-
-        if (self.addr_abs & 0xFF00) != (self.pc & 0xFF00)
+        if condition
         {
-            self.cycles += 1;
+            1
+        }
+        else
+        {
+            0
         }
-
-        self.pc = self.addr_abs;
     }
 
     // Instruction: Branch if Carry Clear
     // if (C == 0) then pc = address
     pub fn bcc(&mut self) -> u8
     {
-        if self.get_flag(Flags6502::C) == 0
-        {
-           self.branch();
-        }
-        0
+        Self::branch(self.get_flag(Flags6502::C) == 0)
     }
 
     // Instruction: Branch if Carry Set
     // if (C == 1) then pc = address
     pub fn bcs(&mut self) -> u8
     {
-        if self.get_flag(Flags6502::C) == 1
-        {
-            self.branch();
-        }
-        0
+        Self::branch(self.get_flag(Flags6502::C) == 1)
     }
 
     // Instruction: Branch if Equal
     // if (Z == 1) then pc = address
     pub fn beq(&mut self) -> u8
     {
-        if self.get_flag(Flags6502::Z) == 1
-        {
-            self.branch();
-        }
-        0
+        Self::branch(self.get_flag(Flags6502::Z) == 1)
     }
 
     // Instruction: Bit Test
@@ -451,79 +475,29 @@ pub fn bit(&mut self) -> u8
     // if (N == 1) then pc = address
     pub fn bmi(&mut self) -> u8
     {
-        if self.get_flag(Flags6502::N) == 1
-        {
-            self.branch();
-        }
-        0
+        Self::branch(self.get_flag(Flags6502::N) == 1)
     }
 
     // Instruction: Branch if not equal
     // if (Z == 0) then pc = address
     pub fn bne(&mut self) -> u8
     {
-        if self.get_flag(Flags6502::Z) == 0
-        {
-            self.branch();
-        }
-        0
+        Self::branch(self.get_flag(Flags6502::Z) == 0)
     }
 
     // Instruction: Branch if Positive
     // if (N == 0) then pc = address
     pub fn bpl(&mut self) -> u8
     {
-        if self.get_flag(Flags6502::N) == 0
-        {
-            self.branch();
-        }
-        0
-    }
-
-    fn write_pc_to_stack(&mut self)
-    {
-        self.cpu_write(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, ((self.pc >> 8) & 0x00FF) as u8);
-        self.stkp -= 1;
-        self.cpu_write(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, (self.pc & 0x00FF) as u8);
-        self.stkp -= 1;
-    }
-
-    fn read_pc_from_stack(&mut self)
-    {
-        self.stkp += 1;
-        let mut lo: u8 = 0;
-        self.cpu_read(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, &mut lo);
-        self.pc = lo as u16;
-
-        self.stkp += 1;
-        let mut hi: u8 = 0;
-        self.cpu_read(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, &mut hi);
-
-        self.pc |= (hi as u16) << 8;
+        Self::branch(self.get_flag(Flags6502::N) == 0)
     }
 
     // Instruction: Break
     // Function: Program Sourced Interrupt
     pub fn brk(&mut self) -> u8
     {
-        self.pc += 1;
-
-        self.set_flag(Flags6502::I, true);
-        self.write_pc_to_stack();
-
-        // TODO: Not clear when exactly the B-flag should be set based on the documentation, needs investigation
-        self.cpu_write(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, self.status | Flags6502::B as u8 | Flags6502::U as u8);
-        self.stkp -= 1;
-
-        // TODO: Not clear if we should vary behavior if an NMI happens while BRK is ongoing
-        // Go to the interrupt vector
-        let mut lo: u8 = 0;
-        let mut hi: u8 = 0;
-        self.cpu_read(Cpu6502::INTERRUPT_VECTOR[1], &mut lo);
-        self.cpu_read(Cpu6502::INTERRUPT_VECTOR[0], &mut hi);
-        self.pc = lo as u16 |
-                    ((hi as u16) << 8);
-
+        // Pushing the PC and status and loading the interrupt vector are all micro-ops, the
+        // same ones an IRQ goes through
         0
     }
 
@@ -531,22 +505,14 @@ pub fn brk(&mut self) -> u8
     // Function: if (V == 0) pc = address
     pub fn bvc(&mut self) -> u8
     {
-        if self.get_flag(Flags6502::V) == 0
-        {
-            self.branch();
-        }
-        0
+        Self::branch(self.get_flag(Flags6502::V) == 0)
     }
 
     // Instruction: Branch if Overflow Set
     // Function: if (V == 1) pc = address
     pub fn bvs(&mut self) -> u8
     {
-        if self.get_flag(Flags6502::V) == 1
-        {
-            self.branch();
-        }
-        0
+        Self::branch(self.get_flag(Flags6502::V) == 1)
     }
 
     // Instruction: Clear Carry Flag
@@ -594,7 +560,7 @@ pub fn cmp(&mut self) -> u8
     {
         let temp_a = self.a as u16;
         self.cmp_helper(temp_a);
-        1
+        0
     }
 
     pub fn cpx(&mut self) -> u8 
@@ -615,7 +581,7 @@ pub fn dec(&mut self) -> u8
     {
         self.fetch();
         let temp: u16 = (self.fetched_data as u16).wrapping_sub(1);
-        self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+        self.write(self.addr_abs, (temp & 0x00FF) as u8);
         self.set_flag(Flags6502::Z, (temp & 0x00FF) == 0x0000);
         self.set_flag(Flags6502::N, (temp & 0x0080) == 0x0080);
 
@@ -659,7 +625,7 @@ pub fn inc(&mut self) -> u8
     {
         self.fetch();
         let temp: u16 = (self.fetched_data as u16).wrapping_add(1);
-        self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+        self.write(self.addr_abs, (temp & 0x00FF) as u8);
         self.set_flag(Flags6502::Z, (temp & 0x00FF) == 0x0000);
         self.set_flag(Flags6502::N, (temp & 0x0080) == 0x0080);
         0
@@ -697,20 +663,18 @@ pub fn jmp(&mut self) -> u8
     // Function:    Push current pc to stack, pc = address
     pub fn jsr(&mut self) -> u8 
     {
-        // Decrement PC to get back to current program counter
-        self.pc -= 1;
-        self.write_pc_to_stack();
+        // The micro-ops have pushed the address of the last byte of this instruction by now
         self.pc = self.addr_abs;
         0
     }
 
-    fn load_helper_write<F>(&mut self, write_closure: F) where F: Fn(&mut Cpu6502)
+    fn load_helper_write<F>(&mut self, write_closure: F) where F: Fn(&mut Self)
     {
         self.fetch();
         write_closure(self);
     }
 
-    fn load_helper_update_flags<F>(&mut self, read_closure: F) where F: Fn(&Cpu6502) -> u8
+    fn load_helper_update_flags<F>(&mut self, read_closure: F) where F: Fn(&Self) -> u8
     {
         self.set_flag(Flags6502::Z, read_closure(self) == 0x00);
         self.set_flag(Flags6502::N, (read_closure(self) & 0x80) == 0x80);
@@ -720,21 +684,21 @@ pub fn lda(&mut self) -> u8
     {
         self.load_helper_write(|x| x.a = x.fetched_data);
         self.load_helper_update_flags(|x| x.a);
-        1
+        0
     }
 
     pub fn ldx(&mut self) -> u8 
     {
         self.load_helper_write(|x| x.x = x.fetched_data);
         self.load_helper_update_flags(|x| x.x);
-        1
+        0
     }
 
     pub fn ldy(&mut self) -> u8 
     {
         self.load_helper_write(|x| x.y = x.fetched_data);
         self.load_helper_update_flags(|x| x.y);
-        1
+        0
     }
 
     pub fn lsr(&mut self) -> u8 
@@ -746,13 +710,13 @@ pub fn lsr(&mut self) -> u8
         self.set_flag(Flags6502::Z, (temp & 0x00FF) == 0x0000); // Zero
         self.set_flag(Flags6502::N, temp & 0x0080 == 0x0080); // Negative
 
-        if self.ins[self.opcode as usize].addr_mode == Cpu6502::imp
+        if INSTRUCTIONS[self.opcode as usize].addr_mode == AddressingMode::Imp
         {
             self.a = (temp & 0x00FF) as u8;
         }
         else
         {
-            self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+            self.write(self.addr_abs, (temp & 0x00FF) as u8);
         }
 
         0
@@ -761,17 +725,8 @@ pub fn lsr(&mut self) -> u8
     // Instruction: No-op
     pub fn nop(&mut self) -> u8 
     {
-        // There are different kinds of NOPs depending on the opcode
-        match self.opcode
-        {
-            0x1C => 1,
-            0x3C => 1,
-            0x5C => 1,
-            0x7C => 1,
-            0xDC => 1,
-            0xFC => 1,
-            _ => 0
-        }
+        // The different kinds of NOPs only differ in the reads their addressing modes make
+        0
     }
 
     // Instruction: Bitwise Logic OR
@@ -790,8 +745,7 @@ pub fn ora(&mut self) -> u8
     // Function: Write A to stkp
     pub fn pha(&mut self) -> u8 
     {
-        self.cpu_write(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, self.a);
-        self.stkp -= 1;
+        self.push(self.a);
         0
     }
     
@@ -800,10 +754,9 @@ pub fn pha(&mut self) -> u8
     pub fn php(&mut self) -> u8 
     {
         // Note that Unused flag are both set to 1 when writing
-        self.cpu_write(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, (self.status) | Flags6502::B as u8 | Flags6502::U as u8);
+        self.push(self.status | Flags6502::B as u8 | Flags6502::U as u8);
         // self.set_flag(Flags6502::B, false);
         // self.set_flag(Flags6502::U, false);
-        self.stkp -= 1;
         0
     }
 
@@ -811,11 +764,7 @@ pub fn php(&mut self) -> u8
     // Function: Set A to stack
     pub fn pla(&mut self) -> u8 
     {
-        self.stkp += 1;
-
-        let mut result: u8 = 0;
-        self.cpu_read(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, &mut result);
-        self.a = result;
+        self.a = self.fetched_data;
 
         self.set_flag(Flags6502::Z, self.a == 0x00); // Zero
         self.set_flag(Flags6502::N, self.a & 0x80 == 0x80); // Negative
@@ -826,12 +775,8 @@ pub fn pla(&mut self) -> u8
     // Function: Status <- stack
     pub fn plp(&mut self) -> u8 
     {
-        self.stkp += 1;
+        self.status = self.fetched_data;
 
-        let mut result: u8 = 0;
-        self.cpu_read(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, &mut result);
-        self.status = result;
-        
         self.set_flag(Flags6502::U, true);
         self.set_flag(Flags6502::B, false);
         0
@@ -848,13 +793,13 @@ pub fn rol(&mut self) -> u8
         self.set_flag(Flags6502::N, temp & 0x0080 == 0x0080); // Negative
 
         // TODO: Consolidate this pattern
-        if self.ins[self.opcode as usize].addr_mode == Cpu6502::imp
+        if INSTRUCTIONS[self.opcode as usize].addr_mode == AddressingMode::Imp
         {
             self.a = (temp & 0x00FF) as u8;
         }
         else
         {
-            self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+            self.write(self.addr_abs, (temp & 0x00FF) as u8);
         }
 
         0
@@ -871,13 +816,13 @@ pub fn ror(&mut self) -> u8
         self.set_flag(Flags6502::N, (temp & 0x0080) == 0x0080); // Negative
 
         // TODO: Consolidate this pattern
-        if self.ins[self.opcode as usize].addr_mode == Cpu6502::imp
+        if INSTRUCTIONS[self.opcode as usize].addr_mode == AddressingMode::Imp
         {
             self.a = (temp & 0x00FF) as u8;
         }
         else
         {
-            self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+            self.write(self.addr_abs, (temp & 0x00FF) as u8);
         }
 
         0
@@ -887,17 +832,7 @@ pub fn ror(&mut self) -> u8
     // Function: Read status and pc from stkp
     pub fn rti(&mut self) -> u8 
     {
-        self.stkp += 1;
-
-        let mut result = 0;
-        self.cpu_read(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, &mut result);
-        self.status = result;
-
-        // Zero out break and unused values, TODO: Why?
-        self.status &= !(Flags6502::B as u8);
-        self.status &= !(Flags6502::U as u8);
-
-        self.read_pc_from_stack();
+        // Pulling the status and PC are micro-ops
         0
     }
 
@@ -905,8 +840,7 @@ pub fn rti(&mut self) -> u8
     // Function: Read pc from stack, increment PC to next instruction
     pub fn rts(&mut self) -> u8 
     {
-        self.read_pc_from_stack();
-        self.pc += 1;
+        // Pulling the PC and moving past the JSR are micro-ops
         0
     }
 
@@ -940,6 +874,12 @@ pub fn rts(&mut self) -> u8
     pub fn sbc(&mut self) -> u8 
     {
         self.fetch();
+        if self.is_decimal()
+        {
+            self.subtract_decimal(self.fetched_data);
+            return 0;
+        }
+
 
         // This is an inversion of the bits. Once we invert, the logic just becomes adc()
         let value: u16 = (self.fetched_data as u16) ^ 0x00FF;
@@ -958,7 +898,7 @@ pub fn sbc(&mut self) -> u8
         // Load the result into the accumulator
         self.a = (temp & 0x00FF) as u8;
 
-        1
+        0
     }
 
     // Instruction: Set Carry Flag
@@ -989,7 +929,7 @@ pub fn sei(&mut self) -> u8
     // Function: M = A
     pub fn sta(&mut self) -> u8 
     {
-        self.cpu_write(self.addr_abs, self.a);
+        self.write(self.addr_abs, self.a);
         0
     }
 
@@ -997,7 +937,7 @@ pub fn sta(&mut self) -> u8
     // Function: M = X
     pub fn stx(&mut self) -> u8 
     {
-        self.cpu_write(self.addr_abs, self.x);
+        self.write(self.addr_abs, self.x);
         0
     }
 
@@ -1005,7 +945,7 @@ pub fn stx(&mut self) -> u8
     // Function: M = Y
     pub fn sty(&mut self) -> u8 
     {
-        self.cpu_write(self.addr_abs, self.y);
+        self.write(self.addr_abs, self.y);
         0
     }
 
@@ -1075,14 +1015,14 @@ pub fn lax(&mut self) -> u8
         self.x = self.fetched_data;
         self.set_flag(Flags6502::Z, self.a == 0x00);
         self.set_flag(Flags6502::N, (self.a & 0x80) == 0x80);
-        1
+        0
     }
 
     pub fn sax(&mut self) -> u8
     {
         self.fetch();
         let a_and_x = self.a & self.x;
-        self.cpu_write(self.addr_abs, a_and_x);
+        self.write(self.addr_abs, a_and_x);
         // self.x = and_a_x.wrapping_sub(self.fetched_data);
 
         //self.set_flag(Flags6502::C, self.x >= self.fetched_data);
@@ -1096,7 +1036,7 @@ pub fn dcp(&mut self) -> u8
         self.fetch();
         let result: u16 = (self.fetched_data as u16).wrapping_sub(1);
         let result_u8 = (result & 0x00FF) as u8;
-        self.cpu_write(self.addr_abs, result_u8);
+        self.write(self.addr_abs, result_u8);
 
         let temp: u16 = (self.a as u16).wrapping_sub(result_u8 as u16);
         self.set_flag(Flags6502::C, self.a >= result_u8);
@@ -1110,7 +1050,12 @@ pub fn isb(&mut self) -> u8
     {
         self.fetch();
         let mut temp: u16 = (self.fetched_data as u16).wrapping_add(1);
-        self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+        self.write(self.addr_abs, (temp & 0x00FF) as u8);
+        if self.is_decimal()
+        {
+            self.subtract_decimal((temp & 0x00FF) as u8);
+            return 0;
+        }
         
         // This is an inversion of the bits. Once we invert, the logic just becomes adc()
         let value: u16 = temp ^ 0x00FF;
@@ -1141,7 +1086,7 @@ pub fn slo(&mut self) -> u8
         self.a |= temp as u8;
         self.set_flag(Flags6502::Z, self.a == 0x00); // Zero
         self.set_flag(Flags6502::N, self.a & 0x80 == 0x80); // Negative
-        self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+        self.write(self.addr_abs, (temp & 0x00FF) as u8);
 
         0
     }
@@ -1155,7 +1100,7 @@ pub fn rla(&mut self) -> u8
         self.a &= temp as u8;
         self.set_flag(Flags6502::Z, self.a == 0x00); // Zero
         self.set_flag(Flags6502::N, self.a & 0x80 == 0x80); // Negative
-        self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+        self.write(self.addr_abs, (temp & 0x00FF) as u8);
         0
     }
 
@@ -1179,7 +1124,7 @@ pub fn sre(&mut self) -> u8
         self.a ^= (temp & 0x00FF) as u8;
         self.set_flag(Flags6502::Z, self.a == 0x00);
         self.set_flag(Flags6502::N, (self.a & 0x80) == 0x80);
-        self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+        self.write(self.addr_abs, (temp & 0x00FF) as u8);
         0
     }
 
@@ -1188,6 +1133,12 @@ pub fn rra(&mut self) -> u8
         self.fetch();
         let temp: u16 = (self.fetched_data as u16) >> 1 | ((self.get_flag(Flags6502::C) as u16) << 7);
         self.set_flag(Flags6502::C, (self.fetched_data & 0x01) == 0x01);
+        if self.is_decimal()
+        {
+            self.add_decimal((temp & 0x00FF) as u8);
+            self.write(self.addr_abs, (temp & 0x00FF) as u8);
+            return 0;
+        }
 
         let result = self.a as u16 + temp + self.get_flag(Flags6502::C) as u16;
 
@@ -1201,63 +1152,221 @@ pub fn rra(&mut self) -> u8
 
         // Load the result into the accumulator
         self.a = (result & 0x00FF) as u8;
-        self.cpu_write(self.addr_abs, (temp & 0x00FF) as u8);
+        self.write(self.addr_abs, (temp & 0x00FF) as u8);
 
         0
     }
 
-    pub fn kil(&mut self) -> u8 
+    fn is_decimal(&self) -> bool
+    {
+        self.decimal_mode && self.get_flag(Flags6502::D) == 1
+    }
+
+    // BCD addition the way the NMOS 6502 does it. Z comes from the binary sum, and N and V are
+    // taken after the low digit is adjusted but before the high one is.
+    fn add_decimal(&mut self, value: u8)
+    {
+        let binary: u16 = self.a as u16 + value as u16 + self.get_flag(Flags6502::C) as u16;
+        self.set_flag(Flags6502::Z, (binary & 0x00FF) == 0);
+
+        let mut low: u16 = (self.a as u16 & 0x0F) + (value as u16 & 0x0F) + self.get_flag(Flags6502::C) as u16;
+        if low >= 0x0A
+        {
+            low = ((low + 0x06) & 0x0F) + 0x10;
+        }
+
+        let mut temp: u16 = (self.a as u16 & 0xF0) + (value as u16 & 0xF0) + low;
+        let v: u16 = (!(self.a as u16 ^ value as u16)) & ((self.a as u16 ^ temp) & 0x0080);
+        self.set_flag(Flags6502::V, v != 0);
+        self.set_flag(Flags6502::N, temp & 0x80 == 0x80);
+
+        if temp >= 0xA0
+        {
+            temp += 0x60;
+        }
+
+        self.set_flag(Flags6502::C, temp > 255);
+        self.a = (temp & 0x00FF) as u8;
+    }
+
+    // BCD subtraction the way the NMOS 6502 does it. All of the flags come from the binary
+    // subtraction, only the result is adjusted.
+    fn subtract_decimal(&mut self, value: u8)
+    {
+        let carry = self.get_flag(Flags6502::C) as i16;
+        let binary: u16 = self.a as u16 + (value ^ 0xFF) as u16 + carry as u16;
+        self.set_flag(Flags6502::C, binary > 255);
+        self.set_flag(Flags6502::Z, (binary & 0x00FF) == 0);
+        let v: u16 = (binary ^ self.a as u16) & (binary ^ (value ^ 0xFF) as u16) & 0x0080;
+        self.set_flag(Flags6502::V, v != 0);
+        self.set_flag(Flags6502::N, binary & 0x80 == 0x80);
+
+        let mut low: i16 = (self.a as i16 & 0x0F) - (value as i16 & 0x0F) + carry - 1;
+        if low < 0
+        {
+            low = ((low - 0x06) & 0x0F) - 0x10;
+        }
+
+        let mut temp: i16 = (self.a as i16 & 0xF0) - (value as i16 & 0xF0) + low;
+        if temp < 0
+        {
+            temp -= 0x60;
+        }
+
+        self.a = (temp & 0x00FF) as u8;
+    }
+
+    pub fn kil(&mut self) -> u8
     {
-        // Running this instruction kills the device
-        panic!("Executed KIL instruction");
+        // Running this instruction locks up the CPU until the next reset. The PC is left
+        // pointing at the KIL so that it's clear where the program went wrong.
+        self.pc = self.pc.wrapping_sub(1);
+        self.jammed = true;
+        0
     }
 
+    // The remaining unofficial opcodes, as documented in https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+    // and "No More Secrets" (NMOS 6510 Unintended Opcodes)
+
+    // AND with the operand, then ROR A. C comes from bit 6 of the result, V from bit 6 XOR bit 5.
     pub fn arr(&mut self) -> u8
     {
-        panic!("Executed ARR instruction");
+        self.fetch();
+        let temp: u8 = self.a & self.fetched_data;
+        self.a = (temp >> 1) | (self.get_flag(Flags6502::C) << 7);
+        if self.is_decimal()
+        {
+            self.arr_decimal(temp);
+            return 0;
+        }
+
+        self.set_flag(Flags6502::C, self.a & 0x40 == 0x40);
+        self.set_flag(Flags6502::V, ((self.a >> 6) ^ (self.a >> 5)) & 0x01 == 0x01);
+        self.set_flag(Flags6502::Z, self.a == 0x00);
+        self.set_flag(Flags6502::N, self.a & 0x80 == 0x80);
+        0
+    }
+
+    // In decimal mode N and Z come from the rotated value and V from bit 6 changing, then each
+    // digit of it is fixed up the way ADC would, going by the digits from before the ROR
+    fn arr_decimal(&mut self, temp: u8)
+    {
+        self.set_flag(Flags6502::N, self.a & 0x80 == 0x80);
+        self.set_flag(Flags6502::Z, self.a == 0x00);
+        self.set_flag(Flags6502::V, (temp ^ self.a) & 0x40 == 0x40);
+
+        if (temp & 0x0F) + (temp & 0x01) > 0x05
+        {
+            self.a = (self.a & 0xF0) | (self.a.wrapping_add(0x06) & 0x0F);
+        }
+
+        let high_carry = (temp as u16 & 0xF0) + (temp as u16 & 0x10) > 0x50;
+        if high_carry
+        {
+            self.a = self.a.wrapping_add(0x60);
+        }
+
+        self.set_flag(Flags6502::C, high_carry);
     }
 
+    // Also known as ANE. A = (A | magic) & X & M, where the magic constant depends on the chip
+    // and even its temperature. $EE is what most 2A03s show.
     pub fn xaa(&mut self) -> u8
     {
-        panic!("Executed XAA instruction");
+        self.fetch();
+        self.a = (self.a | 0xEE) & self.x & self.fetched_data;
+        self.set_flag(Flags6502::Z, self.a == 0x00);
+        self.set_flag(Flags6502::N, self.a & 0x80 == 0x80);
+        0
+    }
+
+    // SHX, SHY, AHX and TAS store a value ANDed with the high byte of the base address plus
+    // one. If the index crossed a page, that same value also replaces the high byte of the
+    // address being written.
+    fn store_and_high_byte(&mut self, value: u8, index: u8)
+    {
+        let base_hi = (self.addr_abs.wrapping_sub(index as u16) >> 8) as u8;
+        let data = value & base_hi.wrapping_add(1);
+
+        let mut address = self.addr_abs;
+        if (address >> 8) as u8 != base_hi
+        {
+            address = ((data as u16) << 8) | (address & 0x00FF);
+        }
+
+        self.write(address, data);
     }
 
+    // Also known as SHA. M = A & X & (H + 1)
     pub fn ahx(&mut self) -> u8
     {
-        panic!("Executed AHX instruction");
+        self.store_and_high_byte(self.a & self.x, self.y);
+        0
     }
 
+    // Also known as SHS. SP = A & X, M = SP & (H + 1)
     pub fn tas(&mut self) -> u8
     {
-        panic!("Executed TAS instruction");
+        self.stkp = self.a & self.x;
+        self.store_and_high_byte(self.stkp, self.y);
+        0
     }
 
+    // Also known as SXA. M = X & (H + 1)
     pub fn shx(&mut self) -> u8
     {
-        panic!("Executed SHX instruction");
+        self.store_and_high_byte(self.x, self.y);
+        0
+    }
+
+    // Also known as SYA. M = Y & (H + 1)
+    pub fn shy(&mut self) -> u8
+    {
+        self.store_and_high_byte(self.y, self.x);
+        0
     }
 
+    // A, X and SP = M & SP
     pub fn las(&mut self) -> u8
     {
-        panic!("Executed LAS instruction");
+        self.fetch();
+        self.stkp &= self.fetched_data;
+        self.a = self.stkp;
+        self.x = self.stkp;
+        self.set_flag(Flags6502::Z, self.a == 0x00);
+        self.set_flag(Flags6502::N, self.a & 0x80 == 0x80);
+        0
     }
 
+    // Also known as SBX. X = (A & X) - M, with the flags set like CMP and no borrow in
     pub fn axs(&mut self) -> u8
     {
-        panic!("Executed AXS instruction");
+        self.fetch();
+        let temp: u8 = self.a & self.x;
+        self.x = temp.wrapping_sub(self.fetched_data);
+        self.set_flag(Flags6502::C, temp >= self.fetched_data);
+        self.set_flag(Flags6502::Z, self.x == 0x00);
+        self.set_flag(Flags6502::N, self.x & 0x80 == 0x80);
+        0
     }
 
+    // Also known as ASR. AND with the operand, then LSR A
     pub fn alr(&mut self) -> u8
     {
-        panic!("Executed ALR instruction");
+        self.fetch();
+        let temp: u8 = self.a & self.fetched_data;
+        self.set_flag(Flags6502::C, temp & 0x01 == 0x01);
+        self.a = temp >> 1;
+        self.set_flag(Flags6502::Z, self.a == 0x00);
+        self.set_flag(Flags6502::N, self.a & 0x80 == 0x80);
+        0
     }
 
-    pub fn new() -> Self
+    pub fn new(bus: B) -> Self
     {
         Cpu6502
         {
-            bus: None,
-            cartridge: None,
+            bus,
             a: 0x00,
             x: 0x00,
             y: 0x00,
@@ -1267,19 +1376,33 @@ pub fn new() -> Self
             fetched_data: 0x00,
             addr_abs: 0x0000,
             addr_rel: 0x0000,
+            pointer: 0x0000,
+            page_crossed: false,
             opcode: 0x00,
-            cycles: 0x00,
+            sequence: Sequence::Instruction,
+            step: 0,
             total_cycles: -1,
-            ins: create_instruction_array!()
+            irq_line: false,
+            nmi_pending: false,
+            interrupt_polled: false,
+            jammed: false,
+            decimal_mode: false
         }
     }
 
+    // True between instructions. After a reset the CPU still has its reset sequence to go through.
     pub fn complete(&self) -> bool
     {
-        self.cycles == 0
+        (self.step == 0 && self.sequence != Sequence::Reset) || self.jammed
     }
 
-    pub fn disassemble(&mut self, n_start: u16, n_end: u16, include_state: bool) -> BTreeMap<u16, String>
+    // Reads memory for the disassembler without disturbing the machine
+    fn peek(&mut self, address: u16, data: &mut u8)
+    {
+        *data = self.bus.peek(address);
+    }
+
+    pub fn disassemble(&mut self, n_start: u16, n_end: u16) -> BTreeMap<u16, String>
     {
         let mut map = BTreeMap::new();
 
@@ -1294,22 +1417,15 @@ pub fn disassemble(&mut self, n_start: u16, n_end: u16, include_state: bool) ->
             line_addr = addr as u16;
             let mut instruction: String = format!("{:04X}  ", addr);
             let mut opcode: u8 = 0;
-            match &self.bus
-            {
-                Some(x) =>
-                {
-                    x.lock().unwrap().cpu_read(addr as u16, &mut opcode)
-                },
-                None => panic!("Error, missing bus inside CPU")
-            };
+            self.peek(addr as u16, &mut opcode);
 
             instruction += &format!("{:02X} ", opcode);
-            let name = self.ins[opcode as usize].name.clone();
+            let name = INSTRUCTIONS[opcode as usize].name;
 
-            let pad = |instruction_ref: &mut String, name_ref: &String| 
+            let pad = |instruction_ref: &mut String, name_ref: &str| 
             {
                 let mut sub_1: i32 = 0;
-                if (*name_ref).starts_with('*')
+                if name_ref.starts_with('*')
                 {
                     sub_1 = -1;
                 }
@@ -1337,105 +1453,105 @@ pub fn disassemble(&mut self, n_start: u16, n_end: u16, include_state: bool) ->
 
             addr += 1;
 
-            if self.ins[opcode as usize].addr_mode == Cpu6502::imp
+            if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Imp
             {
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::imm
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Imm
             {
-                self.cpu_read(addr as u16, &mut value);
+                self.peek(addr as u16, &mut value);
                 instruction += &format!("{:02X}", value);
                 addr += 1;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
                 instruction += &format!(" #${:02X}", value);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::zp0
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Zp0
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X}", lo);
                 addr += 1;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
 
                 let mut temp = 0;
-                self.cpu_read((lo as u16) & 0x00FF, &mut temp);
+                self.peek((lo as u16) & 0x00FF, &mut temp);
                 instruction += &format!(" ${:02X} = {:02X}", lo, temp);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::zpx
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Zpx
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X}", lo);
                 addr += 1;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
 
                 let ind_addr = (lo as u16 + self.x as u16) & 0x00FF;
                 let mut data = 0;
-                self.cpu_read(ind_addr, &mut data);
+                self.peek(ind_addr, &mut data);
 
                 instruction += &format!(" ${:02X},X @ {:02X} = {:02X}", lo, ind_addr, data);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::zpy
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Zpy
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X}", lo);
                 addr += 1;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
                 
                 let ind_addr = (lo as u16 + self.y as u16) & 0x00FF;
                 let mut data = 0;
-                self.cpu_read(ind_addr, &mut data);
+                self.peek(ind_addr, &mut data);
 
                 instruction += &format!(" ${:02X},Y @ {:02X} = {:02X}", lo, ind_addr, data);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::izx
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Izx
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X}", lo);
                 addr += 1;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
 
                 let ind: u16 = lo as u16;
 
-                self.cpu_read((ind + self.x as u16) & 0x00FF, &mut lo);
-                self.cpu_read((ind + self.x as u16 + 1) & 0x00FF, &mut hi);
+                self.peek((ind + self.x as u16) & 0x00FF, &mut lo);
+                self.peek((ind + self.x as u16 + 1) & 0x00FF, &mut hi);
 
                 let ind_addr = ((hi as u16) << 8) | lo as u16;
                 let mut ind_data: u8 = 0;
-                self.cpu_read(ind_addr, &mut ind_data);
+                self.peek(ind_addr, &mut ind_data);
 
-                instruction += &format!(" (${:02X},X) @ {:02X} = {:04X} = {:02X}", ind, ind + self.x as u16, ind_addr, ind_data);
+                instruction += &format!(" (${:02X},X) @ {:02X} = {:04X} = {:02X}", ind, (ind + self.x as u16) & 0x00FF, ind_addr, ind_data);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::izy
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Izy
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X}", lo);
                 addr += 1;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
 
                 let ind: u16 = lo as u16;
 
-                self.cpu_read((ind) & 0x00FF, &mut lo);
-                self.cpu_read((ind + 1) & 0x00FF, &mut hi);
+                self.peek((ind) & 0x00FF, &mut lo);
+                self.peek((ind + 1) & 0x00FF, &mut hi);
 
                 let ind_addr = ((hi as u16) << 8) | lo as u16;
                 let ind_addr_y = ind_addr.wrapping_add(self.y as u16);
 
                 let mut ind_data: u8 = 0;
-                self.cpu_read(ind_addr_y, &mut ind_data);
+                self.peek(ind_addr_y, &mut ind_data);
                 instruction += &format!(" (${:02X}),Y = {:04X} @ {:04X} = {:02X}", ind, ind_addr, ind_addr_y, ind_data);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::abs
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Abs
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X} ", lo);
                 addr += 1;
-                self.cpu_read(addr as u16, &mut hi);
+                self.peek(addr as u16, &mut hi);
                 instruction += &format!("{:02X}", hi);
                 addr += 1;
                 let cur_addr = ((hi as u16) << 8) | lo as u16;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
 
-                if self.ins[opcode as usize].name.eq("JMP") ||
-                    self.ins[opcode as usize].name.eq("JSR")
+                if INSTRUCTIONS[opcode as usize].name == "JMP" ||
+                    INSTRUCTIONS[opcode as usize].name == "JSR"
                     
                 {
                     instruction += &format!(" ${:04X}", cur_addr);
@@ -1443,275 +1559,701 @@ pub fn disassemble(&mut self, n_start: u16, n_end: u16, include_state: bool) ->
                 else
                 {
                     let mut temp = 0;
-                    self.cpu_read(cur_addr, &mut temp);
+                    self.peek(cur_addr, &mut temp);
                     instruction += &format!(" ${:04X} = {:02X}", cur_addr, temp);
                 }
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::abx
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Abx
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X} ", lo);
                 addr += 1;
-                self.cpu_read(addr as u16, &mut hi);
+                self.peek(addr as u16, &mut hi);
                 instruction += &format!("{:02X}", hi);
                 addr += 1;
                 let cur_addr = ((hi as u16) << 8) | lo as u16;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
 
                 let ind_addr = cur_addr.wrapping_add(self.x as u16);
                 let mut data = 0;
-                self.cpu_read(ind_addr, &mut data);
+                self.peek(ind_addr, &mut data);
 
                 instruction += &format!(" ${:04X},X @ {:04X} = {:02X}", cur_addr, ind_addr, data);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::aby
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Aby
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X} ", lo);
                 addr += 1;
-                self.cpu_read(addr as u16, &mut hi);
+                self.peek(addr as u16, &mut hi);
                 instruction += &format!("{:02X}", hi);
                 addr += 1;
                 let cur_addr = ((hi as u16) << 8) | lo as u16;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
 
                 let ind_addr = cur_addr.wrapping_add(self.y as u16);
                 let mut data = 0;
-                self.cpu_read(ind_addr, &mut data);
+                self.peek(ind_addr, &mut data);
 
                 instruction += &format!(" ${:04X},Y @ {:04X} = {:02X}", cur_addr, ind_addr, data);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::ind
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Ind
             {
-                self.cpu_read(addr as u16, &mut lo);
+                self.peek(addr as u16, &mut lo);
                 instruction += &format!("{:02X} ", lo);
                 addr += 1;
-                self.cpu_read(addr as u16, &mut hi);
+                self.peek(addr as u16, &mut hi);
                 instruction += &format!("{:02X}", hi);
                 addr += 1;
                 let ptr = ((hi as u16) << 8) | lo as u16;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
         
                 let offset_addr =
                     if lo == 0x00FF
                     {
-                        self.cpu_read(ptr, &mut lo);
-                        self.cpu_read(ptr & 0xFF00, &mut hi);
+                        self.peek(ptr, &mut lo);
+                        self.peek(ptr & 0xFF00, &mut hi);
             
                         ((hi as u16) << 8) | lo as u16
                     }
                     else
                     {
-                        self.cpu_read(ptr, &mut lo);
-                        self.cpu_read(ptr + 1, &mut hi);
+                        self.peek(ptr, &mut lo);
+                        self.peek(ptr + 1, &mut hi);
             
                         ((hi as u16) << 8) | lo as u16
                     };
 
                 instruction += &format!(" (${:04X}) = {:04X}", ptr, offset_addr);
             }
-            else if self.ins[opcode as usize].addr_mode == Cpu6502::rel
+            else if INSTRUCTIONS[opcode as usize].addr_mode == AddressingMode::Rel
             {
-                self.cpu_read(addr as u16, &mut value);
+                self.peek(addr as u16, &mut value);
                 instruction += &format!("{:02X}", value);
                 addr += 1;
-                pad(&mut instruction, &name);
+                pad(&mut instruction, name);
                 instruction += &format!(" ${:04X}", addr as i32 + (value as i8) as i32);
             }
 
-            if include_state
-            {
-                // Pad to 49 characters
-                let num_spaces = 47 - instruction.len();
-                let repeated_blanks = ' '.to_string().repeat(num_spaces);
-                instruction += &repeated_blanks;
-                instruction += &format!(" {:?}", self);
-            }
-
             map.insert(line_addr, instruction);
         }
 
         map
     }
 
-    fn perform_irq(&mut self, pc_read_addr: u16, num_cycles: u8)
+    // The IRQ line is level triggered, whoever holds it keeps it set until it's acknowledged
+    pub fn set_irq_line(&mut self, active: bool)
     {
-        // Save the PC to the stack
-        self.write_pc_to_stack();
-
-        // Save the status register to the stack
-        self.set_flag(Flags6502::B, false);
-        self.set_flag(Flags6502::U, true);
-        self.set_flag(Flags6502::I, true);
-        self.cpu_write(Cpu6502::STACK_START_ADDRESS + self.stkp as u16, self.status);
-        self.stkp -= 1;
-        
-        // Read the new program counter location from a fixed address
-        self.addr_abs = pc_read_addr;
-        let mut lo: u8 = 0;
-        self.cpu_read(self.addr_abs, &mut lo);
-        let mut hi: u8 = 0;
-        self.cpu_read(self.addr_abs + 1, &mut hi);
-
-        self.pc = ((hi as u16) << 8) | (lo as u16);
-
-        // Add cycles for IRQ to complete
-        self.cycles = num_cycles;
+        self.irq_line = active;
     }
 
-    pub fn irq(&mut self)
+    pub fn nmi(&mut self)
     {
-        if self.get_flag(Flags6502::I) == 0
-        {
-            self.perform_irq(0xFFFE, 7);
-        }
+        self.nmi_pending = true;
     }
 
-    pub fn nmi(&mut self)
+    // Helpers
+    fn execute(&mut self) -> u8
     {
-        self.perform_irq(0xFFFA, 8);
+        match INSTRUCTIONS[self.opcode as usize].op
+        {
+            Operation::Adc => self.adc(),
+            Operation::Ahx => self.ahx(),
+            Operation::Alr => self.alr(),
+            Operation::Anc => self.anc(),
+            Operation::And => self.and(),
+            Operation::Arr => self.arr(),
+            Operation::Asl => self.asl(),
+            Operation::Axs => self.axs(),
+            Operation::Bcc => self.bcc(),
+            Operation::Bcs => self.bcs(),
+            Operation::Beq => self.beq(),
+            Operation::Bit => self.bit(),
+            Operation::Bmi => self.bmi(),
+            Operation::Bne => self.bne(),
+            Operation::Bpl => self.bpl(),
+            Operation::Brk => self.brk(),
+            Operation::Bvc => self.bvc(),
+            Operation::Bvs => self.bvs(),
+            Operation::Clc => self.clc(),
+            Operation::Cld => self.cld(),
+            Operation::Cli => self.cli(),
+            Operation::Clv => self.clv(),
+            Operation::Cmp => self.cmp(),
+            Operation::Cpx => self.cpx(),
+            Operation::Cpy => self.cpy(),
+            Operation::Dcp => self.dcp(),
+            Operation::Dec => self.dec(),
+            Operation::Dex => self.dex(),
+            Operation::Dey => self.dey(),
+            Operation::Eor => self.eor(),
+            Operation::Inc => self.inc(),
+            Operation::Inx => self.inx(),
+            Operation::Iny => self.iny(),
+            Operation::Isb => self.isb(),
+            Operation::Jmp => self.jmp(),
+            Operation::Jsr => self.jsr(),
+            Operation::Kil => self.kil(),
+            Operation::Las => self.las(),
+            Operation::Lax => self.lax(),
+            Operation::Lda => self.lda(),
+            Operation::Ldx => self.ldx(),
+            Operation::Ldy => self.ldy(),
+            Operation::Lsr => self.lsr(),
+            Operation::Nop => self.nop(),
+            Operation::Ora => self.ora(),
+            Operation::Pha => self.pha(),
+            Operation::Php => self.php(),
+            Operation::Pla => self.pla(),
+            Operation::Plp => self.plp(),
+            Operation::Rla => self.rla(),
+            Operation::Rol => self.rol(),
+            Operation::Ror => self.ror(),
+            Operation::Rra => self.rra(),
+            Operation::Rti => self.rti(),
+            Operation::Rts => self.rts(),
+            Operation::Sax => self.sax(),
+            Operation::Sbc => self.sbc(),
+            Operation::Sec => self.sec(),
+            Operation::Sed => self.sed(),
+            Operation::Sei => self.sei(),
+            Operation::Shx => self.shx(),
+            Operation::Shy => self.shy(),
+            Operation::Slo => self.slo(),
+            Operation::Sre => self.sre(),
+            Operation::Sta => self.sta(),
+            Operation::Stx => self.stx(),
+            Operation::Sty => self.sty(),
+            Operation::Tas => self.tas(),
+            Operation::Tax => self.tax(),
+            Operation::Tay => self.tay(),
+            Operation::Tsx => self.tsx(),
+            Operation::Txa => self.txa(),
+            Operation::Txs => self.txs(),
+            Operation::Tya => self.tya(),
+            Operation::Xaa => self.xaa()
+        }
     }
 
-    // Helpers
     pub fn fetch(&mut self) -> u8
     {
-        if self.ins[self.opcode as usize].addr_mode != Cpu6502::imp
+        // Implied instructions work on the accumulator, everything else was read by the micro-ops
+        if INSTRUCTIONS[self.opcode as usize].addr_mode == AddressingMode::Imp
         {
-            let mut read_result: u8 = 0;
-            self.cpu_read(self.addr_abs, &mut read_result);
-            self.fetched_data = read_result;
+            self.fetched_data = self.a;
         }
 
         self.fetched_data
     }
 
-}
+    fn read(&mut self, address: u16) -> u8
+    {
+        self.bus.read(address)
+    }
 
-impl Default for Cpu6502 {
-    fn default() -> Self {
-        Self::new()
+    fn write(&mut self, address: u16, data: u8)
+    {
+        self.bus.write(address, data);
     }
-}
 
-impl Resettable for Cpu6502
-{
-    fn reset(&mut self)
+    fn read_pc(&mut self) -> u8
     {
-        self.addr_abs = 0xFFFC;
-        let mut lo: u8 = 0;
-        self.cpu_read(self.addr_abs, &mut lo);
-        let mut hi: u8 = 0;
-        self.cpu_read(self.addr_abs + 1, &mut hi);
-    
-        self.pc = ((hi as u16) << 8) | lo as u16;
+        let data = self.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        data
+    }
 
-        self.a = 0;
-        self.x = 0;
-        self.y = 0;
-        self.stkp = 0xFD;
+    fn get_stack_address(&self) -> u16
+    {
+        Self::STACK_START_ADDRESS + self.stkp as u16
+    }
 
-        self.status = Flags6502::U as u8 | Flags6502::I as u8;
+    fn push(&mut self, data: u8)
+    {
+        self.write(self.get_stack_address(), data);
+        self.stkp = self.stkp.wrapping_sub(1);
+    }
 
-        self.addr_rel = 0x0000;
-        self.addr_abs = 0x0000;
-        self.fetched_data = 0x00;
+    // The low byte has the index added to it now, the high byte is only fixed up a cycle later
+    fn index_address(&mut self, hi: u8, index: u8)
+    {
+        let base = ((hi as u16) << 8) | (self.addr_abs & 0x00FF);
+        self.addr_abs = base.wrapping_add(index as u16);
+        self.page_crossed = (self.addr_abs & 0xFF00) != (base & 0xFF00);
+    }
 
-        self.cycles = 8;
-        self.total_cycles = -1;
+    // Loads and stores are laid out in rows $80-$BF of the opcode matrix. Elsewhere, the opcodes
+    // with bit 1 set that take a memory operand are the shifts, INC/DEC and the illegal opcodes
+    // built on them, all of which are read-modify-write.
+    fn get_access(opcode: u8) -> Access
+    {
+        match opcode >> 5
+        {
+            4 => Access::Write,
+            5 => Access::Read,
+            _ if opcode & 0x02 == 0x02 => Access::ReadModifyWrite,
+            _ => Access::Read
+        }
     }
 
-}
+    // The cycles that work out the address, including the operand fetch for the modes that
+    // don't need one
+    fn get_address_micro_ops(addr_mode: AddressingMode) -> &'static [MicroOp]
+    {
+        match addr_mode
+        {
+            AddressingMode::Imp => &[MicroOp::ReadPc],
+            AddressingMode::Imm => &[MicroOp::ReadImmediate],
+            AddressingMode::Zp0 => &[MicroOp::FetchAddressLo],
+            AddressingMode::Zpx => &[MicroOp::FetchAddressLo, MicroOp::IndexZeroPageX],
+            AddressingMode::Zpy => &[MicroOp::FetchAddressLo, MicroOp::IndexZeroPageY],
+            AddressingMode::Rel => &[MicroOp::FetchBranchOffset, MicroOp::BranchTaken, MicroOp::BranchFixPage],
+            AddressingMode::Abs => &[MicroOp::FetchAddressLo, MicroOp::FetchAddressHi],
+            AddressingMode::Abx => &[MicroOp::FetchAddressLo, MicroOp::FetchAddressHiIndexX, MicroOp::ReadPageCross],
+            AddressingMode::Aby => &[MicroOp::FetchAddressLo, MicroOp::FetchAddressHiIndexY, MicroOp::ReadPageCross],
+            AddressingMode::Ind => &[MicroOp::FetchPointerLo, MicroOp::FetchPointerHi, MicroOp::FetchIndirectLo, MicroOp::FetchIndirectHi],
+            AddressingMode::Izx => &[MicroOp::FetchPointerLo, MicroOp::IndexPointerX, MicroOp::FetchIndirectLo, MicroOp::FetchIndirectHi],
+            AddressingMode::Izy => &[MicroOp::FetchPointerLo, MicroOp::FetchIndirectLo, MicroOp::FetchIndirectHiIndexY, MicroOp::ReadPageCross]
+        }
+    }
 
-impl fmt::Debug for Cpu6502
-{
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    // The cycles that use the address
+    fn get_access_micro_ops(addr_mode: AddressingMode, access: Access) -> &'static [MicroOp]
+    {
+        match (addr_mode, access)
+        {
+            (AddressingMode::Imp | AddressingMode::Imm | AddressingMode::Rel, _) => &[],
+            (_, Access::Read) => &[MicroOp::ReadOperand],
+            (_, Access::Write) => &[MicroOp::Write],
+            (_, Access::ReadModifyWrite) => &[MicroOp::ReadOperand, MicroOp::WriteDummy, MicroOp::Write]
+        }
+    }
+
+    // Everything after the opcode fetch of the current sequence, in two parts that run one after
+    // the other. Instructions that use the stack or jump have their own.
+    fn get_micro_ops(&self) -> (&'static [MicroOp], &'static [MicroOp])
     {
-        let mut cycle = 0;
-        let mut scan_line = 0;
-        match &self.bus
+        match self.sequence
         {
-            Some(x) =>
+            Sequence::Reset =>
             {
-                let mut bus = x.lock().unwrap();
-                cycle = bus.get_ppu().lock().unwrap().get_cycle();
-                scan_line = bus.get_ppu().lock().unwrap().get_scan_line();
-            },
-            None =>
+                (&[MicroOp::ReadPc, MicroOp::ReadStackDecrement, MicroOp::ReadStackDecrement, MicroOp::ReadStackDecrement,
+                    MicroOp::FetchVectorLo, MicroOp::FetchVectorHi], &[])
+            }
+            Sequence::Interrupt =>
             {
+                (&[MicroOp::ReadPc, MicroOp::PushPcHi, MicroOp::PushPcLo, MicroOp::PushStatus, MicroOp::FetchVectorLo,
+                    MicroOp::FetchVectorHi], &[])
+            }
+            Sequence::Instruction =>
+            {
+                let addr_mode = INSTRUCTIONS[self.opcode as usize].addr_mode;
+                match self.opcode
+                {
+                    // BRK
+                    0x00 =>
+                    {
+                        (&[MicroOp::ReadPcIncrement, MicroOp::PushPcHi, MicroOp::PushPcLo, MicroOp::PushStatus,
+                            MicroOp::FetchVectorLo, MicroOp::FetchVectorHi], &[])
+                    }
+                    // JSR
+                    0x20 =>
+                    {
+                        (&[MicroOp::FetchAddressLo, MicroOp::ReadStack, MicroOp::PushPcHi, MicroOp::PushPcLo,
+                            MicroOp::FetchAddressHi], &[])
+                    }
+                    // RTI
+                    0x40 =>
+                    {
+                        (&[MicroOp::ReadPc, MicroOp::IncrementStack, MicroOp::PullStatus, MicroOp::PullPcLo,
+                            MicroOp::PullPcHi], &[])
+                    }
+                    // RTS
+                    0x60 =>
+                    {
+                        (&[MicroOp::ReadPc, MicroOp::IncrementStack, MicroOp::PullPcLo, MicroOp::PullPcHi,
+                            MicroOp::ReadPcIncrement], &[])
+                    }
+                    // PHP, PHA
+                    0x08 | 0x48 => (&[MicroOp::ReadPc], &[MicroOp::Write]),
+                    // PLP, PLA
+                    0x28 | 0x68 => (&[MicroOp::ReadPc, MicroOp::IncrementStack], &[MicroOp::PullOperand]),
+                    // JMP only needs the address
+                    0x4C | 0x6C => (Self::get_address_micro_ops(addr_mode), &[]),
+                    _ =>
+                    {
+                        (Self::get_address_micro_ops(addr_mode),
+                            Self::get_access_micro_ops(addr_mode, Self::get_access(self.opcode)))
+                    }
+                }
             }
         }
+    }
 
-        // TODO: Why is total cycles off by 1
-        // TODO: Why is cycle off by 5
-        write!(f, "A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:3},{:3} CYC:{}", self.a, self.x, self.y, self.status, self.stkp, scan_line, cycle - 5, self.total_cycles)
+    // The first cycle of every sequence fetches an opcode. Interrupts and reset throw it away and
+    // leave the PC where it is, so that it's the address that gets pushed.
+    fn begin_sequence(&mut self)
+    {
+        if self.sequence == Sequence::Instruction
+        {
+            self.opcode = self.read_pc();
+        }
+        else
+        {
+            self.read(self.pc);
+        }
     }
-}
 
-impl ReadWrite for Cpu6502
-{
-    fn cpu_write(&mut self, address: u16, data: u8) -> bool
+    // Runs the micro-op for this cycle, returning true when it ended the sequence
+    fn run_step(&mut self) -> bool
     {
-        match &self.bus
+        let (address_micro_ops, access_micro_ops) = self.get_micro_ops();
+        let index = self.step as usize - 1;
+        let micro_op = match address_micro_ops.get(index)
+        {
+            Some(x) => *x,
+            None => access_micro_ops[index - address_micro_ops.len()]
+        };
+
+        let finished = self.run_micro_op(micro_op) || index + 1 == address_micro_ops.len() + access_micro_ops.len();
+
+        // Branches have already run theirs to decide whether to take the extra cycles
+        if finished && self.sequence == Sequence::Instruction && INSTRUCTIONS[self.opcode as usize].addr_mode != AddressingMode::Rel
         {
-            Some(x) => x.lock().unwrap().cpu_write(address, data),
-            None => panic!("Error, missing bus inside CPU")
+            self.execute();
         }
+
+        finished
     }
 
-    fn cpu_read(&mut self, address: u16, data: &mut u8) -> bool
+    // Makes the bus access for one cycle, returning true if the rest of the sequence is skipped
+    fn run_micro_op(&mut self, micro_op: MicroOp) -> bool
     {
-        match &self.bus
+        match micro_op
         {
-            Some(x) => x.lock().unwrap().cpu_read(address, data),
-            None => panic!("Error, missing bus inside CPU")
+            MicroOp::ReadPc =>
+            {
+                self.read(self.pc);
+            }
+            MicroOp::ReadPcIncrement =>
+            {
+                self.read_pc();
+            }
+            MicroOp::ReadImmediate =>
+            {
+                self.fetched_data = self.read_pc();
+            }
+            MicroOp::FetchAddressLo =>
+            {
+                self.addr_abs = self.read_pc() as u16;
+            }
+            MicroOp::FetchAddressHi =>
+            {
+                self.addr_abs |= (self.read_pc() as u16) << 8;
+            }
+            MicroOp::FetchAddressHiIndexX =>
+            {
+                let hi = self.read_pc();
+                self.index_address(hi, self.x);
+            }
+            MicroOp::FetchAddressHiIndexY =>
+            {
+                let hi = self.read_pc();
+                self.index_address(hi, self.y);
+            }
+            MicroOp::IndexZeroPageX =>
+            {
+                self.read(self.addr_abs);
+                self.addr_abs = (self.addr_abs + self.x as u16) & 0x00FF;
+            }
+            MicroOp::IndexZeroPageY =>
+            {
+                self.read(self.addr_abs);
+                self.addr_abs = (self.addr_abs + self.y as u16) & 0x00FF;
+            }
+            MicroOp::FetchPointerLo =>
+            {
+                self.pointer = self.read_pc() as u16;
+            }
+            MicroOp::FetchPointerHi =>
+            {
+                self.pointer |= (self.read_pc() as u16) << 8;
+            }
+            MicroOp::IndexPointerX =>
+            {
+                self.read(self.pointer);
+                self.pointer = (self.pointer + self.x as u16) & 0x00FF;
+            }
+            MicroOp::FetchIndirectLo =>
+            {
+                self.addr_abs = self.read(self.pointer) as u16;
+            }
+            MicroOp::FetchIndirectHi =>
+            {
+                let hi = self.read((self.pointer & 0xFF00) | (self.pointer.wrapping_add(1) & 0x00FF));
+                self.addr_abs |= (hi as u16) << 8;
+            }
+            MicroOp::FetchIndirectHiIndexY =>
+            {
+                let hi = self.read((self.pointer & 0xFF00) | (self.pointer.wrapping_add(1) & 0x00FF));
+                self.index_address(hi, self.y);
+            }
+            MicroOp::ReadPageCross =>
+            {
+                let address = if self.page_crossed { self.addr_abs.wrapping_sub(0x0100) } else { self.addr_abs };
+                let data = self.read(address);
+                if !self.page_crossed && Self::get_access(self.opcode) == Access::Read
+                {
+                    self.fetched_data = data;
+                    return true;
+                }
+            }
+            MicroOp::ReadOperand =>
+            {
+                self.fetched_data = self.read(self.addr_abs);
+            }
+            MicroOp::WriteDummy =>
+            {
+                self.write(self.addr_abs, self.fetched_data);
+            }
+            MicroOp::Write =>
+            {
+                // The op makes the write once this cycle is over
+            }
+            MicroOp::FetchBranchOffset =>
+            {
+                // The offset is signed
+                self.addr_rel = self.read_pc() as i8 as u16;
+                return self.execute() == 0;
+            }
+            MicroOp::BranchTaken =>
+            {
+                self.read(self.pc);
+                self.addr_abs = self.pc.wrapping_add(self.addr_rel);
+                self.pc = (self.pc & 0xFF00) | (self.addr_abs & 0x00FF);
+                return self.pc == self.addr_abs;
+            }
+            MicroOp::BranchFixPage =>
+            {
+                self.read(self.pc);
+                self.pc = self.addr_abs;
+            }
+            MicroOp::ReadStack =>
+            {
+                self.read(self.get_stack_address());
+            }
+            MicroOp::IncrementStack =>
+            {
+                self.read(self.get_stack_address());
+                self.stkp = self.stkp.wrapping_add(1);
+            }
+            MicroOp::ReadStackDecrement =>
+            {
+                self.read(self.get_stack_address());
+                self.stkp = self.stkp.wrapping_sub(1);
+            }
+            MicroOp::PushPcHi =>
+            {
+                self.push((self.pc >> 8) as u8);
+            }
+            MicroOp::PushPcLo =>
+            {
+                self.push((self.pc & 0x00FF) as u8);
+            }
+            MicroOp::PushStatus =>
+            {
+                // B only exists in the copy that BRK pushes
+                let b = if self.sequence == Sequence::Instruction { Flags6502::B as u8 } else { 0 };
+                self.push(self.status | b | Flags6502::U as u8);
+
+                // An NMI that has arrived by now takes over the vector, even from BRK or an IRQ
+                if self.nmi_pending
+                {
+                    self.nmi_pending = false;
+                    self.addr_abs = Self::NMI_VECTOR;
+                }
+                else
+                {
+                    self.addr_abs = Self::IRQ_VECTOR;
+                }
+            }
+            MicroOp::PullStatus =>
+            {
+                self.status = self.read(self.get_stack_address());
+                self.stkp = self.stkp.wrapping_add(1);
+
+                // B and U don't exist in the register itself
+                self.set_flag(Flags6502::B, false);
+                self.set_flag(Flags6502::U, true);
+            }
+            MicroOp::PullPcLo =>
+            {
+                self.fetched_data = self.read(self.get_stack_address());
+                self.stkp = self.stkp.wrapping_add(1);
+            }
+            MicroOp::PullPcHi =>
+            {
+                let hi = self.read(self.get_stack_address());
+                self.pc = ((hi as u16) << 8) | self.fetched_data as u16;
+            }
+            MicroOp::PullOperand =>
+            {
+                self.fetched_data = self.read(self.get_stack_address());
+            }
+            MicroOp::FetchVectorLo =>
+            {
+                self.fetched_data = self.read(self.addr_abs);
+                self.set_flag(Flags6502::I, true);
+            }
+            MicroOp::FetchVectorHi =>
+            {
+                let hi = self.read(self.addr_abs + 1);
+                self.pc = ((hi as u16) << 8) | self.fetched_data as u16;
+            }
         }
+
+        false
     }
 
-    fn ppu_write(&mut self, _: u16, _: u8) -> bool
+}
+
+impl<B: CpuBus> Resettable for Cpu6502<B>
+{
+    fn reset(&mut self)
     {
-        todo!()
+        self.a = 0;
+        self.x = 0;
+        self.y = 0;
+
+        // The reset sequence brings this down to $FD and loads the PC from the reset vector
+        self.stkp = 0x00;
+        self.addr_abs = Self::RESET_VECTOR;
+
+        self.status = Flags6502::U as u8 | Flags6502::I as u8;
+
+        self.addr_rel = 0x0000;
+        self.fetched_data = 0x00;
+
+        // Reset takes 7 cycles, which are counted towards the total like nestest's log does
+        self.sequence = Sequence::Reset;
+        self.step = 0;
+        self.total_cycles = 0;
+        self.irq_line = false;
+        self.nmi_pending = false;
+        self.interrupt_polled = false;
+        self.jammed = false;
     }
 
-    fn ppu_read(&self, _: u16, _: &mut u8) -> bool
+}
+
+impl<B: CpuBus> Saveable for Cpu6502<B>
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u8(self.a)?;
+        writer.write_u8(self.x)?;
+        writer.write_u8(self.y)?;
+        writer.write_u8(self.stkp)?;
+        writer.write_u16::<LittleEndian>(self.pc)?;
+        writer.write_u8(self.status)?;
+        writer.write_u8(self.fetched_data)?;
+        writer.write_u16::<LittleEndian>(self.addr_abs)?;
+        writer.write_u16::<LittleEndian>(self.addr_rel)?;
+        writer.write_u16::<LittleEndian>(self.pointer)?;
+        savestate::write_bool(writer, self.page_crossed)?;
+        writer.write_u8(self.opcode)?;
+        writer.write_u8(self.sequence as u8)?;
+        writer.write_u8(self.step)?;
+        writer.write_i64::<LittleEndian>(self.total_cycles)?;
+        savestate::write_bool(writer, self.irq_line)?;
+        savestate::write_bool(writer, self.nmi_pending)?;
+        savestate::write_bool(writer, self.interrupt_polled)?;
+        savestate::write_bool(writer, self.jammed)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.a = reader.read_u8()?;
+        self.x = reader.read_u8()?;
+        self.y = reader.read_u8()?;
+        self.stkp = reader.read_u8()?;
+        self.pc = reader.read_u16::<LittleEndian>()?;
+        self.status = reader.read_u8()?;
+        self.fetched_data = reader.read_u8()?;
+        self.addr_abs = reader.read_u16::<LittleEndian>()?;
+        self.addr_rel = reader.read_u16::<LittleEndian>()?;
+        self.pointer = reader.read_u16::<LittleEndian>()?;
+        self.page_crossed = savestate::read_bool(reader)?;
+        self.opcode = reader.read_u8()?;
+        self.sequence = match reader.read_u8()?
+        {
+            0 => Sequence::Instruction,
+            1 => Sequence::Interrupt,
+            2 => Sequence::Reset,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid CPU sequence"))
+        };
+        self.step = reader.read_u8()?;
+        self.total_cycles = reader.read_i64::<LittleEndian>()?;
+        self.irq_line = savestate::read_bool(reader)?;
+        self.nmi_pending = savestate::read_bool(reader)?;
+        self.interrupt_polled = savestate::read_bool(reader)?;
+        self.jammed = savestate::read_bool(reader)?;
+        Ok(())
+    }
+}
+
+impl<B: CpuBus> fmt::Debug for Cpu6502<B>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
     {
-        todo!()
+        write!(f, "A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}", self.a, self.x, self.y, self.status, self.stkp, self.total_cycles)
     }
 }
 
-impl Clockable for Cpu6502
+impl<B: CpuBus> Clockable for Cpu6502<B>
 {
+    // One CPU cycle, with one read or write on the bus
     fn clock_tick(&mut self) -> bool
     {
-        if self.cycles == 0
+        // Time still passes for the rest of the console, but the CPU does nothing on the bus
+        if self.jammed
         {
-            let mut read_result: u8 = 0;
-            self.cpu_read(self.pc, &mut read_result);
-            self.opcode = read_result;
-
-            // TODO: Why?
-            self.set_flag(Flags6502::U, true);
-
-            //let result = self.disassemble(self.pc, self.pc);
+            self.total_cycles += 1;
+            return false;
+        }
 
-            self.cycles = self.ins[self.opcode as usize].cycles;
-            // let last_pc = self.pc;
-            self.pc += 1;
+        let finished = if self.step == 0
+        {
+            self.begin_sequence();
+            false
+        }
+        else
+        {
+            self.run_step()
+        };
 
-            let additional_cycle1: u8 = (self.ins[self.opcode as usize].addr_mode)(self);
-            let additional_cycle2: u8 = (self.ins[self.opcode as usize].op)(self);
-            //println!("{}", result.get(&last_pc).unwrap());
+        // TODO: Why?
+        self.set_flag(Flags6502::U, true);
 
-            // TODO: Why is this a binary AND?
-            self.cycles += additional_cycle1 & additional_cycle2;
+        // Interrupts are polled at the end of every cycle, but it's the poll at the end of the
+        // second to last cycle of an instruction that decides whether one runs after it. So e.g.
+        // the IRQ that CLI lets through only comes after the next instruction.
+        let polled_before = self.interrupt_polled;
+        self.interrupt_polled = self.nmi_pending || (self.irq_line && self.get_flag(Flags6502::I) == 0);
 
-            // TODO: Why?
-            self.set_flag(Flags6502::U, true);
+        if finished
+        {
+            // The first instruction of a handler always runs, even if another interrupt is waiting
+            let handler_next = self.sequence != Sequence::Instruction || self.opcode == 0x00;
+            self.sequence = if polled_before && !handler_next { Sequence::Interrupt } else { Sequence::Instruction };
+            self.step = 0;
+        }
+        else
+        {
+            self.step += 1;
         }
 
-         self.cycles -= 1;
-         self.total_cycles += 1;
-         false
+        self.total_cycles += 1;
+        false
     }
-}
-
-unsafe impl Send for Cpu6502 {}
\ No newline at end of file
+}
\ No newline at end of file
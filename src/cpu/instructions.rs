@@ -1,294 +1,291 @@
-#[macro_export]
-macro_rules! create_instruction_array {
-    () => {
-        [
-        // 00 - 0F
-        Instruction { name: String::from("BRK"), op: Cpu6502::brk, addr_mode: Cpu6502::imm, cycles: 7 },
-        Instruction { name: String::from("ORA"), op: Cpu6502::ora, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*SLO"), op: Cpu6502::slo, addr_mode: Cpu6502::izx, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("ORA"), op: Cpu6502::ora, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("ASL"), op: Cpu6502::asl, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("*SLO"), op: Cpu6502::slo, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("PHP"), op: Cpu6502::php, addr_mode: Cpu6502::imp, cycles: 3 },
-        Instruction { name: String::from("ORA"), op: Cpu6502::ora, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("ASL A"), op: Cpu6502::asl, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*ANC"), op: Cpu6502::anc, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("ORA"), op: Cpu6502::ora, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("ASL"), op: Cpu6502::asl, addr_mode: Cpu6502::abs, cycles: 6 },
-        Instruction { name: String::from("*SLO"), op: Cpu6502::slo, addr_mode: Cpu6502::abs, cycles: 6 },
+// Everything the CPU needs to know about each opcode, indexed by opcode. Mnemonics with a * are
+// unofficial, ** marks the ones that are unstable on real hardware.
+static INSTRUCTIONS: [Instruction; 256] = [
+    // 00 - 0F
+    Instruction { name: "BRK", op: Operation::Brk, addr_mode: AddressingMode::Imm },
+    Instruction { name: "ORA", op: Operation::Ora, addr_mode: AddressingMode::Izx },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*SLO", op: Operation::Slo, addr_mode: AddressingMode::Izx },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "ORA", op: Operation::Ora, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "ASL", op: Operation::Asl, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "*SLO", op: Operation::Slo, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "PHP", op: Operation::Php, addr_mode: AddressingMode::Imp },
+    Instruction { name: "ORA", op: Operation::Ora, addr_mode: AddressingMode::Imm },
+    Instruction { name: "ASL A", op: Operation::Asl, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*ANC", op: Operation::Anc, addr_mode: AddressingMode::Imm },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Abs },
+    Instruction { name: "ORA", op: Operation::Ora, addr_mode: AddressingMode::Abs },
+    Instruction { name: "ASL", op: Operation::Asl, addr_mode: AddressingMode::Abs },
+    Instruction { name: "*SLO", op: Operation::Slo, addr_mode: AddressingMode::Abs },
 
-        // 10 - 1F
-        Instruction { name: String::from("BPL"), op: Cpu6502::bpl, addr_mode: Cpu6502::rel, cycles: 2 },
-        Instruction { name: String::from("ORA"), op: Cpu6502::ora, addr_mode: Cpu6502::izy, cycles: 5 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*SLO"), op: Cpu6502::slo, addr_mode: Cpu6502::izy, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("ORA"), op: Cpu6502::ora, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("ASL"), op: Cpu6502::asl, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("*SLO"), op: Cpu6502::slo, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("CLC"), op: Cpu6502::clc, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("ORA"), op: Cpu6502::ora, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*SLO"), op: Cpu6502::slo, addr_mode: Cpu6502::aby, cycles: 7 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("ORA"), op: Cpu6502::ora, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("ASL"), op: Cpu6502::asl, addr_mode: Cpu6502::abx, cycles: 7 },
-        Instruction { name: String::from("*SLO"), op: Cpu6502::slo, addr_mode: Cpu6502::abx, cycles: 7 },
+    // 10 - 1F
+    Instruction { name: "BPL", op: Operation::Bpl, addr_mode: AddressingMode::Rel },
+    Instruction { name: "ORA", op: Operation::Ora, addr_mode: AddressingMode::Izy },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*SLO", op: Operation::Slo, addr_mode: AddressingMode::Izy },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "ORA", op: Operation::Ora, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "ASL", op: Operation::Asl, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "*SLO", op: Operation::Slo, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "CLC", op: Operation::Clc, addr_mode: AddressingMode::Imp },
+    Instruction { name: "ORA", op: Operation::Ora, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*SLO", op: Operation::Slo, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Abx },
+    Instruction { name: "ORA", op: Operation::Ora, addr_mode: AddressingMode::Abx },
+    Instruction { name: "ASL", op: Operation::Asl, addr_mode: AddressingMode::Abx },
+    Instruction { name: "*SLO", op: Operation::Slo, addr_mode: AddressingMode::Abx },
 
-        // 20 - 2F
-        Instruction { name: String::from("JSR"), op: Cpu6502::jsr, addr_mode: Cpu6502::abs, cycles: 6 },
-        Instruction { name: String::from("AND"), op: Cpu6502::and, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*RLA"), op: Cpu6502::rla, addr_mode: Cpu6502::izx, cycles: 8 },
-        Instruction { name: String::from("BIT"), op: Cpu6502::bit, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("AND"), op: Cpu6502::and, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("ROL"), op: Cpu6502::rol, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("*RLA"), op: Cpu6502::rla, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("PLP"), op: Cpu6502::plp, addr_mode: Cpu6502::imp, cycles: 4 },
-        Instruction { name: String::from("AND"), op: Cpu6502::and, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("ROL A"), op: Cpu6502::rol, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*ANC"), op: Cpu6502::anc, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("BIT"), op: Cpu6502::bit, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("AND"), op: Cpu6502::and, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("ROL"), op: Cpu6502::rol, addr_mode: Cpu6502::abs, cycles: 6 },
-        Instruction { name: String::from("*RLA"), op: Cpu6502::rla, addr_mode: Cpu6502::abs, cycles: 6 },
+    // 20 - 2F
+    Instruction { name: "JSR", op: Operation::Jsr, addr_mode: AddressingMode::Abs },
+    Instruction { name: "AND", op: Operation::And, addr_mode: AddressingMode::Izx },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*RLA", op: Operation::Rla, addr_mode: AddressingMode::Izx },
+    Instruction { name: "BIT", op: Operation::Bit, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "AND", op: Operation::And, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "ROL", op: Operation::Rol, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "*RLA", op: Operation::Rla, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "PLP", op: Operation::Plp, addr_mode: AddressingMode::Imp },
+    Instruction { name: "AND", op: Operation::And, addr_mode: AddressingMode::Imm },
+    Instruction { name: "ROL A", op: Operation::Rol, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*ANC", op: Operation::Anc, addr_mode: AddressingMode::Imm },
+    Instruction { name: "BIT", op: Operation::Bit, addr_mode: AddressingMode::Abs },
+    Instruction { name: "AND", op: Operation::And, addr_mode: AddressingMode::Abs },
+    Instruction { name: "ROL", op: Operation::Rol, addr_mode: AddressingMode::Abs },
+    Instruction { name: "*RLA", op: Operation::Rla, addr_mode: AddressingMode::Abs },
 
-        // 30 - 3F
-        Instruction { name: String::from("BMI"), op: Cpu6502::bmi, addr_mode: Cpu6502::rel, cycles: 2 },
-        Instruction { name: String::from("AND"), op: Cpu6502::and, addr_mode: Cpu6502::izy, cycles: 5 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*RLA"), op: Cpu6502::rla, addr_mode: Cpu6502::izy, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("AND"), op: Cpu6502::and, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("ROL"), op: Cpu6502::rol, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("*RLA"), op: Cpu6502::rla, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("SEC"), op: Cpu6502::sec, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("AND"), op: Cpu6502::and, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*RLA"), op: Cpu6502::rla, addr_mode: Cpu6502::aby, cycles: 7 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("AND"), op: Cpu6502::and, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("ROL"), op: Cpu6502::rol, addr_mode: Cpu6502::abx, cycles: 7 },
-        Instruction { name: String::from("*RLA"), op: Cpu6502::rla, addr_mode: Cpu6502::abx, cycles: 7 },
+    // 30 - 3F
+    Instruction { name: "BMI", op: Operation::Bmi, addr_mode: AddressingMode::Rel },
+    Instruction { name: "AND", op: Operation::And, addr_mode: AddressingMode::Izy },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*RLA", op: Operation::Rla, addr_mode: AddressingMode::Izy },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "AND", op: Operation::And, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "ROL", op: Operation::Rol, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "*RLA", op: Operation::Rla, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "SEC", op: Operation::Sec, addr_mode: AddressingMode::Imp },
+    Instruction { name: "AND", op: Operation::And, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*RLA", op: Operation::Rla, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Abx },
+    Instruction { name: "AND", op: Operation::And, addr_mode: AddressingMode::Abx },
+    Instruction { name: "ROL", op: Operation::Rol, addr_mode: AddressingMode::Abx },
+    Instruction { name: "*RLA", op: Operation::Rla, addr_mode: AddressingMode::Abx },
 
-        // 40 - 4F
-        Instruction { name: String::from("RTI"), op: Cpu6502::rti, addr_mode: Cpu6502::imp, cycles: 6 },
-        Instruction { name: String::from("EOR"), op: Cpu6502::eor, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*SRE"), op: Cpu6502::sre, addr_mode: Cpu6502::izx, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("EOR"), op: Cpu6502::eor, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("LSR"), op: Cpu6502::lsr, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("*SRE"), op: Cpu6502::sre, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("PHA"), op: Cpu6502::pha, addr_mode: Cpu6502::imp, cycles: 3 },
-        Instruction { name: String::from("EOR"), op: Cpu6502::eor, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("LSR A"), op: Cpu6502::lsr, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("ALR"), op: Cpu6502::alr, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("JMP"), op: Cpu6502::jmp, addr_mode: Cpu6502::abs, cycles: 3 },
-        Instruction { name: String::from("EOR"), op: Cpu6502::eor, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("LSR"), op: Cpu6502::lsr, addr_mode: Cpu6502::abs, cycles: 6 },
-        Instruction { name: String::from("*SRE"), op: Cpu6502::sre, addr_mode: Cpu6502::abs, cycles: 6 },
+    // 40 - 4F
+    Instruction { name: "RTI", op: Operation::Rti, addr_mode: AddressingMode::Imp },
+    Instruction { name: "EOR", op: Operation::Eor, addr_mode: AddressingMode::Izx },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*SRE", op: Operation::Sre, addr_mode: AddressingMode::Izx },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "EOR", op: Operation::Eor, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "LSR", op: Operation::Lsr, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "*SRE", op: Operation::Sre, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "PHA", op: Operation::Pha, addr_mode: AddressingMode::Imp },
+    Instruction { name: "EOR", op: Operation::Eor, addr_mode: AddressingMode::Imm },
+    Instruction { name: "LSR A", op: Operation::Lsr, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*ALR", op: Operation::Alr, addr_mode: AddressingMode::Imm },
+    Instruction { name: "JMP", op: Operation::Jmp, addr_mode: AddressingMode::Abs },
+    Instruction { name: "EOR", op: Operation::Eor, addr_mode: AddressingMode::Abs },
+    Instruction { name: "LSR", op: Operation::Lsr, addr_mode: AddressingMode::Abs },
+    Instruction { name: "*SRE", op: Operation::Sre, addr_mode: AddressingMode::Abs },
 
-        // 50 - 5F
-        Instruction { name: String::from("BVC"), op: Cpu6502::bvc, addr_mode: Cpu6502::rel, cycles: 2 },
-        Instruction { name: String::from("EOR"), op: Cpu6502::eor, addr_mode: Cpu6502::izy, cycles: 5 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*SRE"), op: Cpu6502::sre, addr_mode: Cpu6502::izy, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("EOR"), op: Cpu6502::eor, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("LSR"), op: Cpu6502::lsr, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("*SRE"), op: Cpu6502::sre, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("CLI"), op: Cpu6502::cli, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("EOR"), op: Cpu6502::eor, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*SRE"), op: Cpu6502::sre, addr_mode: Cpu6502::aby, cycles: 7 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("EOR"), op: Cpu6502::eor, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("LSR"), op: Cpu6502::lsr, addr_mode: Cpu6502::abx, cycles: 7 },
-        Instruction { name: String::from("*SRE"), op: Cpu6502::sre, addr_mode: Cpu6502::abx, cycles: 7 },
+    // 50 - 5F
+    Instruction { name: "BVC", op: Operation::Bvc, addr_mode: AddressingMode::Rel },
+    Instruction { name: "EOR", op: Operation::Eor, addr_mode: AddressingMode::Izy },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*SRE", op: Operation::Sre, addr_mode: AddressingMode::Izy },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "EOR", op: Operation::Eor, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "LSR", op: Operation::Lsr, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "*SRE", op: Operation::Sre, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "CLI", op: Operation::Cli, addr_mode: AddressingMode::Imp },
+    Instruction { name: "EOR", op: Operation::Eor, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*SRE", op: Operation::Sre, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Abx },
+    Instruction { name: "EOR", op: Operation::Eor, addr_mode: AddressingMode::Abx },
+    Instruction { name: "LSR", op: Operation::Lsr, addr_mode: AddressingMode::Abx },
+    Instruction { name: "*SRE", op: Operation::Sre, addr_mode: AddressingMode::Abx },
 
-        // 60 - 6F
-        Instruction { name: String::from("RTS"), op: Cpu6502::rts, addr_mode: Cpu6502::imp, cycles: 6 },
-        Instruction { name: String::from("ADC"), op: Cpu6502::adc, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*RRA"), op: Cpu6502::rra, addr_mode: Cpu6502::izx, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("ADC"), op: Cpu6502::adc, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("ROR"), op: Cpu6502::ror, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("*RRA"), op: Cpu6502::rra, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("PLA"), op: Cpu6502::pla, addr_mode: Cpu6502::imp, cycles: 4 },
-        Instruction { name: String::from("ADC"), op: Cpu6502::adc, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("ROR A"), op: Cpu6502::ror, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("ARR"), op: Cpu6502::arr, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("JMP"), op: Cpu6502::jmp, addr_mode: Cpu6502::ind, cycles: 5 },
-        Instruction { name: String::from("ADC"), op: Cpu6502::adc, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("ROR"), op: Cpu6502::ror, addr_mode: Cpu6502::abs, cycles: 6 },
-        Instruction { name: String::from("*RRA"), op: Cpu6502::rra, addr_mode: Cpu6502::abs, cycles: 6 },
+    // 60 - 6F
+    Instruction { name: "RTS", op: Operation::Rts, addr_mode: AddressingMode::Imp },
+    Instruction { name: "ADC", op: Operation::Adc, addr_mode: AddressingMode::Izx },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*RRA", op: Operation::Rra, addr_mode: AddressingMode::Izx },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "ADC", op: Operation::Adc, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "ROR", op: Operation::Ror, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "*RRA", op: Operation::Rra, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "PLA", op: Operation::Pla, addr_mode: AddressingMode::Imp },
+    Instruction { name: "ADC", op: Operation::Adc, addr_mode: AddressingMode::Imm },
+    Instruction { name: "ROR A", op: Operation::Ror, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*ARR", op: Operation::Arr, addr_mode: AddressingMode::Imm },
+    Instruction { name: "JMP", op: Operation::Jmp, addr_mode: AddressingMode::Ind },
+    Instruction { name: "ADC", op: Operation::Adc, addr_mode: AddressingMode::Abs },
+    Instruction { name: "ROR", op: Operation::Ror, addr_mode: AddressingMode::Abs },
+    Instruction { name: "*RRA", op: Operation::Rra, addr_mode: AddressingMode::Abs },
 
-        // 70 - 7F
-        Instruction { name: String::from("BVS"), op: Cpu6502::bvs, addr_mode: Cpu6502::rel, cycles: 2 },
-        Instruction { name: String::from("ADC"), op: Cpu6502::adc, addr_mode: Cpu6502::izy, cycles: 5 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*RRA"), op: Cpu6502::rra, addr_mode: Cpu6502::izy, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("ADC"), op: Cpu6502::adc, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("ROR"), op: Cpu6502::ror, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("*RRA"), op: Cpu6502::rra, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("SEI"), op: Cpu6502::sei, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("ADC"), op: Cpu6502::adc, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*RRA"), op: Cpu6502::rra, addr_mode: Cpu6502::aby, cycles: 7 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("ADC"), op: Cpu6502::adc, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("ROR"), op: Cpu6502::ror, addr_mode: Cpu6502::abx, cycles: 7 },
-        Instruction { name: String::from("*RRA"), op: Cpu6502::rra, addr_mode: Cpu6502::abx, cycles: 7 },
+    // 70 - 7F
+    Instruction { name: "BVS", op: Operation::Bvs, addr_mode: AddressingMode::Rel },
+    Instruction { name: "ADC", op: Operation::Adc, addr_mode: AddressingMode::Izy },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*RRA", op: Operation::Rra, addr_mode: AddressingMode::Izy },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "ADC", op: Operation::Adc, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "ROR", op: Operation::Ror, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "*RRA", op: Operation::Rra, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "SEI", op: Operation::Sei, addr_mode: AddressingMode::Imp },
+    Instruction { name: "ADC", op: Operation::Adc, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*RRA", op: Operation::Rra, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Abx },
+    Instruction { name: "ADC", op: Operation::Adc, addr_mode: AddressingMode::Abx },
+    Instruction { name: "ROR", op: Operation::Ror, addr_mode: AddressingMode::Abx },
+    Instruction { name: "*RRA", op: Operation::Rra, addr_mode: AddressingMode::Abx },
 
-        // 80 - 8F
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("STA"), op: Cpu6502::sta, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*SAX"), op: Cpu6502::sax, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("STY"), op: Cpu6502::sty, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("STA"), op: Cpu6502::sta, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("STX"), op: Cpu6502::stx, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("*SAX"), op: Cpu6502::sax, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("DEY"), op: Cpu6502::dey, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("TXA"), op: Cpu6502::txa, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("**XAA"), op: Cpu6502::xaa, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("STY"), op: Cpu6502::sty, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("STA"), op: Cpu6502::sta, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("STX"), op: Cpu6502::stx, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("*SAX"), op: Cpu6502::sax, addr_mode: Cpu6502::abs, cycles: 4 },
+    // 80 - 8F
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imm },
+    Instruction { name: "STA", op: Operation::Sta, addr_mode: AddressingMode::Izx },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*SAX", op: Operation::Sax, addr_mode: AddressingMode::Izx },
+    Instruction { name: "STY", op: Operation::Sty, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "STA", op: Operation::Sta, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "STX", op: Operation::Stx, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "*SAX", op: Operation::Sax, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "DEY", op: Operation::Dey, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "TXA", op: Operation::Txa, addr_mode: AddressingMode::Imp },
+    Instruction { name: "**XAA", op: Operation::Xaa, addr_mode: AddressingMode::Imm },
+    Instruction { name: "STY", op: Operation::Sty, addr_mode: AddressingMode::Abs },
+    Instruction { name: "STA", op: Operation::Sta, addr_mode: AddressingMode::Abs },
+    Instruction { name: "STX", op: Operation::Stx, addr_mode: AddressingMode::Abs },
+    Instruction { name: "*SAX", op: Operation::Sax, addr_mode: AddressingMode::Abs },
 
-        // 90 - 9F
-        Instruction { name: String::from("BCC"), op: Cpu6502::bcc, addr_mode: Cpu6502::rel, cycles: 2 },
-        Instruction { name: String::from("STA"), op: Cpu6502::sta, addr_mode: Cpu6502::izy, cycles: 6 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("**AHX"), op: Cpu6502::ahx, addr_mode: Cpu6502::imp, cycles: 6 },
-        Instruction { name: String::from("STY"), op: Cpu6502::sty, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("STA"), op: Cpu6502::sta, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("STX"), op: Cpu6502::stx, addr_mode: Cpu6502::zpy, cycles: 4 },
-        Instruction { name: String::from("*SAX"), op: Cpu6502::sax, addr_mode: Cpu6502::zpy, cycles: 4 },
-        Instruction { name: String::from("TYA"), op: Cpu6502::tya, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("STA"), op: Cpu6502::sta, addr_mode: Cpu6502::aby, cycles: 5 },
-        Instruction { name: String::from("TXS"), op: Cpu6502::txs, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("**TAS"), op: Cpu6502::tas, addr_mode: Cpu6502::aby, cycles: 5 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::abx, cycles: 5 },
-        Instruction { name: String::from("STA"), op: Cpu6502::sta, addr_mode: Cpu6502::abx, cycles: 5 },
-        Instruction { name: String::from("**SHX"), op: Cpu6502::shx, addr_mode: Cpu6502::aby, cycles: 5 },
-        Instruction { name: String::from("**AHX"), op: Cpu6502::ahx, addr_mode: Cpu6502::aby, cycles: 5 },
+    // 90 - 9F
+    Instruction { name: "BCC", op: Operation::Bcc, addr_mode: AddressingMode::Rel },
+    Instruction { name: "STA", op: Operation::Sta, addr_mode: AddressingMode::Izy },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "**AHX", op: Operation::Ahx, addr_mode: AddressingMode::Izy },
+    Instruction { name: "STY", op: Operation::Sty, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "STA", op: Operation::Sta, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "STX", op: Operation::Stx, addr_mode: AddressingMode::Zpy },
+    Instruction { name: "*SAX", op: Operation::Sax, addr_mode: AddressingMode::Zpy },
+    Instruction { name: "TYA", op: Operation::Tya, addr_mode: AddressingMode::Imp },
+    Instruction { name: "STA", op: Operation::Sta, addr_mode: AddressingMode::Aby },
+    Instruction { name: "TXS", op: Operation::Txs, addr_mode: AddressingMode::Imp },
+    Instruction { name: "**TAS", op: Operation::Tas, addr_mode: AddressingMode::Aby },
+    Instruction { name: "**SHY", op: Operation::Shy, addr_mode: AddressingMode::Abx },
+    Instruction { name: "STA", op: Operation::Sta, addr_mode: AddressingMode::Abx },
+    Instruction { name: "**SHX", op: Operation::Shx, addr_mode: AddressingMode::Aby },
+    Instruction { name: "**AHX", op: Operation::Ahx, addr_mode: AddressingMode::Aby },
 
-        // A0 - AF
-        Instruction { name: String::from("LDY"), op: Cpu6502::ldy, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("LDA"), op: Cpu6502::lda, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("LDX"), op: Cpu6502::ldx, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("*LAX"), op: Cpu6502::lax, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("LDY"), op: Cpu6502::ldy, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("LDA"), op: Cpu6502::lda, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("LDX"), op: Cpu6502::ldx, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("*LAX"), op: Cpu6502::lax, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("TAY"), op: Cpu6502::tay, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("LDA"), op: Cpu6502::lda, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("TAX"), op: Cpu6502::tax, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("**LAX"), op: Cpu6502::lax, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("LDY"), op: Cpu6502::ldy, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("LDA"), op: Cpu6502::lda, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("LDX"), op: Cpu6502::ldx, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("*LAX"), op: Cpu6502::lax, addr_mode: Cpu6502::abs, cycles: 4 },
+    // A0 - AF
+    Instruction { name: "LDY", op: Operation::Ldy, addr_mode: AddressingMode::Imm },
+    Instruction { name: "LDA", op: Operation::Lda, addr_mode: AddressingMode::Izx },
+    Instruction { name: "LDX", op: Operation::Ldx, addr_mode: AddressingMode::Imm },
+    Instruction { name: "*LAX", op: Operation::Lax, addr_mode: AddressingMode::Izx },
+    Instruction { name: "LDY", op: Operation::Ldy, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "LDA", op: Operation::Lda, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "LDX", op: Operation::Ldx, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "*LAX", op: Operation::Lax, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "TAY", op: Operation::Tay, addr_mode: AddressingMode::Imp },
+    Instruction { name: "LDA", op: Operation::Lda, addr_mode: AddressingMode::Imm },
+    Instruction { name: "TAX", op: Operation::Tax, addr_mode: AddressingMode::Imp },
+    Instruction { name: "**LAX", op: Operation::Lax, addr_mode: AddressingMode::Imm },
+    Instruction { name: "LDY", op: Operation::Ldy, addr_mode: AddressingMode::Abs },
+    Instruction { name: "LDA", op: Operation::Lda, addr_mode: AddressingMode::Abs },
+    Instruction { name: "LDX", op: Operation::Ldx, addr_mode: AddressingMode::Abs },
+    Instruction { name: "*LAX", op: Operation::Lax, addr_mode: AddressingMode::Abs },
 
-        // B0 - BF
-        Instruction { name: String::from("BCS"), op: Cpu6502::bcs, addr_mode: Cpu6502::rel, cycles: 2 },
-        Instruction { name: String::from("LDA"), op: Cpu6502::lda, addr_mode: Cpu6502::izy, cycles: 5 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*LAX"), op: Cpu6502::lax, addr_mode: Cpu6502::izy, cycles: 5 },
-        Instruction { name: String::from("LDY"), op: Cpu6502::ldy, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("LDA"), op: Cpu6502::lda, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("LDX"), op: Cpu6502::ldx, addr_mode: Cpu6502::zpy, cycles: 4 },
-        Instruction { name: String::from("*LAX"), op: Cpu6502::lax, addr_mode: Cpu6502::zpy, cycles: 4 },
-        Instruction { name: String::from("CLV"), op: Cpu6502::clv, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("LDA"), op: Cpu6502::lda, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("TSX"), op: Cpu6502::tsx, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*LAS"), op: Cpu6502::las, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("LDY"), op: Cpu6502::ldy, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("LDA"), op: Cpu6502::lda, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("LDX"), op: Cpu6502::ldx, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("*LAX"), op: Cpu6502::lax, addr_mode: Cpu6502::aby, cycles: 4 },
+    // B0 - BF
+    Instruction { name: "BCS", op: Operation::Bcs, addr_mode: AddressingMode::Rel },
+    Instruction { name: "LDA", op: Operation::Lda, addr_mode: AddressingMode::Izy },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*LAX", op: Operation::Lax, addr_mode: AddressingMode::Izy },
+    Instruction { name: "LDY", op: Operation::Ldy, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "LDA", op: Operation::Lda, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "LDX", op: Operation::Ldx, addr_mode: AddressingMode::Zpy },
+    Instruction { name: "*LAX", op: Operation::Lax, addr_mode: AddressingMode::Zpy },
+    Instruction { name: "CLV", op: Operation::Clv, addr_mode: AddressingMode::Imp },
+    Instruction { name: "LDA", op: Operation::Lda, addr_mode: AddressingMode::Aby },
+    Instruction { name: "TSX", op: Operation::Tsx, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*LAS", op: Operation::Las, addr_mode: AddressingMode::Aby },
+    Instruction { name: "LDY", op: Operation::Ldy, addr_mode: AddressingMode::Abx },
+    Instruction { name: "LDA", op: Operation::Lda, addr_mode: AddressingMode::Abx },
+    Instruction { name: "LDX", op: Operation::Ldx, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*LAX", op: Operation::Lax, addr_mode: AddressingMode::Aby },
 
-        // C0 - CF
-        Instruction { name: String::from("CPY"), op: Cpu6502::cpy, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("CMP"), op: Cpu6502::cmp, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*DCP"), op: Cpu6502::dcp, addr_mode: Cpu6502::izx, cycles: 8 },
-        Instruction { name: String::from("CPY"), op: Cpu6502::cpy, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("CMP"), op: Cpu6502::cmp, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("DEC"), op: Cpu6502::dec, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("*DCP"), op: Cpu6502::dcp, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("INY"), op: Cpu6502::iny, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("CMP"), op: Cpu6502::cmp, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("DEX"), op: Cpu6502::dex, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*AXS"), op: Cpu6502::axs, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("CPY"), op: Cpu6502::cpy, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("CMP"), op: Cpu6502::cmp, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("DEC"), op: Cpu6502::dec, addr_mode: Cpu6502::abs, cycles: 6 },
-        Instruction { name: String::from("*DCP"), op: Cpu6502::dcp, addr_mode: Cpu6502::abs, cycles: 6 },
+    // C0 - CF
+    Instruction { name: "CPY", op: Operation::Cpy, addr_mode: AddressingMode::Imm },
+    Instruction { name: "CMP", op: Operation::Cmp, addr_mode: AddressingMode::Izx },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*DCP", op: Operation::Dcp, addr_mode: AddressingMode::Izx },
+    Instruction { name: "CPY", op: Operation::Cpy, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "CMP", op: Operation::Cmp, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "DEC", op: Operation::Dec, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "*DCP", op: Operation::Dcp, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "INY", op: Operation::Iny, addr_mode: AddressingMode::Imp },
+    Instruction { name: "CMP", op: Operation::Cmp, addr_mode: AddressingMode::Imm },
+    Instruction { name: "DEX", op: Operation::Dex, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*AXS", op: Operation::Axs, addr_mode: AddressingMode::Imm },
+    Instruction { name: "CPY", op: Operation::Cpy, addr_mode: AddressingMode::Abs },
+    Instruction { name: "CMP", op: Operation::Cmp, addr_mode: AddressingMode::Abs },
+    Instruction { name: "DEC", op: Operation::Dec, addr_mode: AddressingMode::Abs },
+    Instruction { name: "*DCP", op: Operation::Dcp, addr_mode: AddressingMode::Abs },
 
-        // D0 - DF
-        Instruction { name: String::from("BNE"), op: Cpu6502::bne, addr_mode: Cpu6502::rel, cycles: 2 },
-        Instruction { name: String::from("CMP"), op: Cpu6502::cmp, addr_mode: Cpu6502::izy, cycles: 5 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*DCP"), op: Cpu6502::dcp, addr_mode: Cpu6502::izy, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("CMP"), op: Cpu6502::cmp, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("DEC"), op: Cpu6502::dec, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("*DCP"), op: Cpu6502::dcp, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("CLD"), op: Cpu6502::cld, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("CMP"), op: Cpu6502::cmp, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*DCP"), op: Cpu6502::dcp, addr_mode: Cpu6502::aby, cycles: 7 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("CMP"), op: Cpu6502::cmp, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("DEC"), op: Cpu6502::dec, addr_mode: Cpu6502::abx, cycles: 7 },
-        Instruction { name: String::from("*DCP"), op: Cpu6502::dcp, addr_mode: Cpu6502::abx, cycles: 7 },
+    // D0 - DF
+    Instruction { name: "BNE", op: Operation::Bne, addr_mode: AddressingMode::Rel },
+    Instruction { name: "CMP", op: Operation::Cmp, addr_mode: AddressingMode::Izy },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*DCP", op: Operation::Dcp, addr_mode: AddressingMode::Izy },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "CMP", op: Operation::Cmp, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "DEC", op: Operation::Dec, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "*DCP", op: Operation::Dcp, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "CLD", op: Operation::Cld, addr_mode: AddressingMode::Imp },
+    Instruction { name: "CMP", op: Operation::Cmp, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*DCP", op: Operation::Dcp, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Abx },
+    Instruction { name: "CMP", op: Operation::Cmp, addr_mode: AddressingMode::Abx },
+    Instruction { name: "DEC", op: Operation::Dec, addr_mode: AddressingMode::Abx },
+    Instruction { name: "*DCP", op: Operation::Dcp, addr_mode: AddressingMode::Abx },
 
-        // E0 - EF
-        Instruction { name: String::from("CPX"), op: Cpu6502::cpx, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::izx, cycles: 6 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*ISB"), op: Cpu6502::isb, addr_mode: Cpu6502::izx, cycles: 8 },
-        Instruction { name: String::from("CPX"), op: Cpu6502::cpx, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::zp0, cycles: 3 },
-        Instruction { name: String::from("INC"), op: Cpu6502::inc, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("*ISB"), op: Cpu6502::isb, addr_mode: Cpu6502::zp0, cycles: 5 },
-        Instruction { name: String::from("INX"), op: Cpu6502::inx, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::imm, cycles: 2 },
-        Instruction { name: String::from("CPX"), op: Cpu6502::cpx, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::abs, cycles: 4 },
-        Instruction { name: String::from("INC"), op: Cpu6502::inc, addr_mode: Cpu6502::abs, cycles: 6 },
-        Instruction { name: String::from("*ISB"), op: Cpu6502::isb, addr_mode: Cpu6502::abs, cycles: 6 },
+    // E0 - EF
+    Instruction { name: "CPX", op: Operation::Cpx, addr_mode: AddressingMode::Imm },
+    Instruction { name: "SBC", op: Operation::Sbc, addr_mode: AddressingMode::Izx },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*ISB", op: Operation::Isb, addr_mode: AddressingMode::Izx },
+    Instruction { name: "CPX", op: Operation::Cpx, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "SBC", op: Operation::Sbc, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "INC", op: Operation::Inc, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "*ISB", op: Operation::Isb, addr_mode: AddressingMode::Zp0 },
+    Instruction { name: "INX", op: Operation::Inx, addr_mode: AddressingMode::Imp },
+    Instruction { name: "SBC", op: Operation::Sbc, addr_mode: AddressingMode::Imm },
+    Instruction { name: "NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*SBC", op: Operation::Sbc, addr_mode: AddressingMode::Imm },
+    Instruction { name: "CPX", op: Operation::Cpx, addr_mode: AddressingMode::Abs },
+    Instruction { name: "SBC", op: Operation::Sbc, addr_mode: AddressingMode::Abs },
+    Instruction { name: "INC", op: Operation::Inc, addr_mode: AddressingMode::Abs },
+    Instruction { name: "*ISB", op: Operation::Isb, addr_mode: AddressingMode::Abs },
 
-        // F0 - FF
-        Instruction { name: String::from("BEQ"), op: Cpu6502::beq, addr_mode: Cpu6502::rel, cycles: 2 },
-        Instruction { name: String::from("SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::izy, cycles: 5 },
-        Instruction { name: String::from("KIL"), op: Cpu6502::kil, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*ISB"), op: Cpu6502::isb, addr_mode: Cpu6502::izy, cycles: 8 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::zpx, cycles: 4 },
-        Instruction { name: String::from("INC"), op: Cpu6502::inc, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("*ISB"), op: Cpu6502::isb, addr_mode: Cpu6502::zpx, cycles: 6 },
-        Instruction { name: String::from("SED"), op: Cpu6502::sed, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::aby, cycles: 4 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::imp, cycles: 2 },
-        Instruction { name: String::from("*ISB"), op: Cpu6502::isb, addr_mode: Cpu6502::aby, cycles: 7 },
-        Instruction { name: String::from("*NOP"), op: Cpu6502::nop, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("SBC"), op: Cpu6502::sbc, addr_mode: Cpu6502::abx, cycles: 4 },
-        Instruction { name: String::from("INC"), op: Cpu6502::inc, addr_mode: Cpu6502::abx, cycles: 7 },
-        Instruction { name: String::from("*ISB"), op: Cpu6502::isb, addr_mode: Cpu6502::abx, cycles: 7 }
-        ]
-    };
-}
\ No newline at end of file
+    // F0 - FF
+    Instruction { name: "BEQ", op: Operation::Beq, addr_mode: AddressingMode::Rel },
+    Instruction { name: "SBC", op: Operation::Sbc, addr_mode: AddressingMode::Izy },
+    Instruction { name: "KIL", op: Operation::Kil, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*ISB", op: Operation::Isb, addr_mode: AddressingMode::Izy },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "SBC", op: Operation::Sbc, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "INC", op: Operation::Inc, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "*ISB", op: Operation::Isb, addr_mode: AddressingMode::Zpx },
+    Instruction { name: "SED", op: Operation::Sed, addr_mode: AddressingMode::Imp },
+    Instruction { name: "SBC", op: Operation::Sbc, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Imp },
+    Instruction { name: "*ISB", op: Operation::Isb, addr_mode: AddressingMode::Aby },
+    Instruction { name: "*NOP", op: Operation::Nop, addr_mode: AddressingMode::Abx },
+    Instruction { name: "SBC", op: Operation::Sbc, addr_mode: AddressingMode::Abx },
+    Instruction { name: "INC", op: Operation::Inc, addr_mode: AddressingMode::Abx },
+    Instruction { name: "*ISB", op: Operation::Isb, addr_mode: AddressingMode::Abx }
+];
\ No newline at end of file
@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+
+use ggez::glam::*;
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect, Text};
+use ggez::{Context, GameResult};
+
+use singularity_emu::sound::apu2a03::{Apu2a03, Channel};
+use singularity_emu::sound::debug_info::ChannelDebugInfo;
+
+const NOTE_NAMES: [&str; 12] = [ "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B" ];
+
+// The audio debug panel. Each channel gets a row with its note, envelope, sweep and counter
+// state next to an oscilloscope of its output, and a piano roll of the last few seconds
+// of the pitched channels sits underneath.
+pub struct ApuView
+{
+    // One entry per emulated frame, oldest first. Each pitched channel that is sounding has
+    // its pitch as a fractional MIDI note and its volume from 0 to 1.
+    history: VecDeque<[Option<(f32, f32)>; 5]>
+}
+
+impl ApuView
+{
+    pub const WIDTH: f32 = 750.0;
+    pub const HEIGHT: f32 = ApuView::ROW_HEIGHT * 5.0 + ApuView::ROLL_HEIGHT + 8.0;
+
+    const ROW_HEIGHT: f32 = 30.0;
+    const TEXT_OFFSET_Y: f32 = 14.0;
+
+    const SCOPE_X: f32 = 420.0;
+    const SCOPE_WIDTH: f32 = ApuView::WIDTH - ApuView::SCOPE_X;
+    const SCOPE_HEIGHT: f32 = ApuView::ROW_HEIGHT - 4.0;
+    // How much of the APU's scope history is shown, the rest leaves room to find a trigger
+    const SCOPE_SAMPLES: usize = Apu2a03::SCOPE_LENGTH / 2;
+
+    // 4 seconds, C1 to C8 at 2 pixels a semitone
+    const ROLL_FRAMES: usize = 240;
+    const ROLL_LOWEST_NOTE: f32 = 24.0;
+    const ROLL_HIGHEST_NOTE: f32 = 108.0;
+    const ROLL_HEIGHT: f32 = (ApuView::ROLL_HIGHEST_NOTE - ApuView::ROLL_LOWEST_NOTE) * 2.0;
+
+    pub fn new() -> Self
+    {
+        ApuView { history: VecDeque::with_capacity(ApuView::ROLL_FRAMES) }
+    }
+
+    fn get_color(channel: Channel) -> Color
+    {
+        match channel
+        {
+            Channel::Pulse1 => Color::from_rgb(240, 96, 96),
+            Channel::Pulse2 => Color::from_rgb(240, 176, 64),
+            Channel::Triangle => Color::from_rgb(96, 224, 96),
+            Channel::Noise => Color::from_rgb(96, 160, 240),
+            Channel::Dmc => Color::from_rgb(192, 112, 240)
+        }
+    }
+
+    fn get_midi_note(frequency: f64) -> f32
+    {
+        (69.0 + 12.0 * (frequency / 440.0).log2()) as f32
+    }
+
+    // e.g. "A#4 +12c", octaves numbered so that A4 is 440Hz
+    fn get_note_name(frequency: f64) -> String
+    {
+        let note = ApuView::get_midi_note(frequency);
+        let nearest = note.round();
+        let cents = ((note - nearest) * 100.0).round() as i32;
+        format!("{}{} {:+}c", NOTE_NAMES[(nearest as i32).rem_euclid(12) as usize], nearest as i32 / 12 - 1, cents)
+    }
+
+    // Pitch and volume if the channel is playing a note that can be heard
+    fn get_sounding_note(info: &ChannelDebugInfo) -> Option<(f32, f32)>
+    {
+        let frequency = info.frequency?;
+        if info.length == 0 || info.linear_counter == Some(0) || info.sweep.is_some_and(|x| x.mute)
+        {
+            return None;
+        }
+
+        let volume = info.envelope.map_or(15, |x| x.output);
+        if volume == 0
+        {
+            return None;
+        }
+
+        Some((ApuView::get_midi_note(frequency), volume as f32 / 15.0))
+    }
+
+    // Adds the current state to the piano roll, once per emulated frame
+    pub fn record_frame(&mut self, apu: &Apu2a03)
+    {
+        if self.history.len() == ApuView::ROLL_FRAMES
+        {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(Channel::ALL.map(|x| ApuView::get_sounding_note(&apu.get_channel_debug_info(x))));
+    }
+
+    pub fn draw(&self, apu: &Apu2a03, x: f32, y: f32, ctx: &mut Context, canvas: &mut Canvas) -> GameResult
+    {
+        let mut mesh = MeshBuilder::new();
+        mesh.rectangle(DrawMode::fill(), Rect::new(x, y, ApuView::WIDTH, ApuView::HEIGHT), Color::from_rgb(16, 16, 24))?;
+
+        for (i, channel) in Channel::ALL.iter().enumerate()
+        {
+            let row_y = y + i as f32 * ApuView::ROW_HEIGHT;
+            let info = apu.get_channel_debug_info(*channel);
+            let color = if apu.is_audible(*channel) { ApuView::get_color(*channel) } else { Color::from_rgb(96, 96, 96) };
+
+            let (summary, details) = ApuView::describe(apu, *channel, &info);
+            canvas.draw(&Text::new(summary), DrawParam::new().color(color).dest(Vec2::new(x + 4.0, row_y + 2.0)));
+            canvas.draw(&Text::new(details), DrawParam::new().color(color).dest(Vec2::new(x + 4.0, row_y + 2.0 + ApuView::TEXT_OFFSET_Y)));
+
+            ApuView::build_scope(&mut mesh, &apu.get_scope(*channel), *channel, color, x + ApuView::SCOPE_X, row_y + 2.0)?;
+        }
+
+        self.build_piano_roll(&mut mesh, x, y + ApuView::ROW_HEIGHT * 5.0 + 8.0)?;
+
+        canvas.draw(&Mesh::from_data(ctx, mesh.build()), DrawParam::new());
+
+        // Octave labels go on top of the roll, just above their line. C8 is the top edge.
+        for octave in 1..=7
+        {
+            let note_y = ApuView::get_roll_y(y + ApuView::ROW_HEIGHT * 5.0 + 8.0, (octave + 1) as f32 * 12.0);
+            canvas.draw(&Text::new(format!("C{}", octave)), DrawParam::new().color(Color::from_rgb(128, 128, 128))
+                .dest(Vec2::new(x + 2.0, note_y - ApuView::TEXT_OFFSET_Y)));
+        }
+
+        Ok(())
+    }
+
+    // Two lines of text, what is playing and how the units that shape it are set
+    fn describe(apu: &Apu2a03, channel: Channel, info: &ChannelDebugInfo) -> (String, String)
+    {
+        let flags = match (apu.is_muted(channel), apu.is_soloed(channel))
+        {
+            (true, _) => " [muted]",
+            (false, true) => " [solo]",
+            (false, false) => ""
+        };
+
+        let note = match info.frequency
+        {
+            Some(frequency) => format!("{:<9} {:>7.1}Hz", ApuView::get_note_name(frequency), frequency),
+            None => format!("period {}", info.period)
+        };
+
+        let summary = format!("{:<8} {:>3.0}  {}{}", channel.get_name(), info.output, note, flags);
+
+        let mut details = match channel
+        {
+            Channel::Dmc => format!("bytes {}", info.length),
+            _ => format!("len {}", info.length)
+        };
+
+        if let Some(envelope) = info.envelope
+        {
+            if envelope.constant
+            {
+                details += &format!(" vol {}", envelope.volume);
+            }
+            else
+            {
+                details += &format!(" env {}/{}{}", envelope.decay, envelope.volume, if envelope.looped { " loop" } else { "" });
+            }
+        }
+
+        if let Some(linear_counter) = info.linear_counter
+        {
+            details += &format!(" linear {}", linear_counter);
+        }
+
+        if let Some(sweep) = info.sweep.filter(|x| x.enabled)
+        {
+            details += &format!(" sweep {}{} p{}{}", if sweep.down { "-" } else { "+" }, sweep.shift, sweep.period, if sweep.mute { " mute" } else { "" });
+        }
+
+        (summary, details)
+    }
+
+    // Starts the trace where the signal last rose through its midpoint, so that a steady note
+    // stands still instead of scrolling
+    fn build_scope(mesh: &mut MeshBuilder, samples: &[f32], channel: Channel, color: Color, x: f32, y: f32) -> GameResult
+    {
+        let latest_start = samples.len() - ApuView::SCOPE_SAMPLES;
+        let (low, high) = samples.iter().fold((f32::MAX, f32::MIN), |(low, high), x| (low.min(*x), high.max(*x)));
+        let midpoint = (low + high) / 2.0;
+
+        let start = (1..=latest_start).rev()
+            .find(|i| samples[i - 1] < midpoint && samples[*i] >= midpoint)
+            .unwrap_or(latest_start);
+
+        let full_scale = if channel == Channel::Dmc { 127.0 } else { 15.0 };
+        let points: Vec<Vec2> = samples[start..start + ApuView::SCOPE_SAMPLES].iter().enumerate()
+            .map(|(i, sample)| Vec2::new(
+                x + i as f32 * ApuView::SCOPE_WIDTH / ApuView::SCOPE_SAMPLES as f32,
+                y + ApuView::SCOPE_HEIGHT * (1.0 - sample / full_scale)))
+            .collect();
+
+        mesh.line(&points, 1.0, color)?;
+        Ok(())
+    }
+
+    fn get_roll_y(y: f32, note: f32) -> f32
+    {
+        y + ApuView::ROLL_HEIGHT * (ApuView::ROLL_HIGHEST_NOTE - note) / (ApuView::ROLL_HIGHEST_NOTE - ApuView::ROLL_LOWEST_NOTE)
+    }
+
+    fn build_piano_roll(&self, mesh: &mut MeshBuilder, x: f32, y: f32) -> GameResult
+    {
+        mesh.rectangle(DrawMode::fill(), Rect::new(x, y, ApuView::WIDTH, ApuView::ROLL_HEIGHT), Color::from_rgb(8, 8, 12))?;
+
+        for octave in 1..=8
+        {
+            let note_y = ApuView::get_roll_y(y, (octave + 1) as f32 * 12.0);
+            mesh.line(&[Vec2::new(x, note_y), Vec2::new(x + ApuView::WIDTH, note_y)], 1.0, Color::from_rgb(40, 40, 48))?;
+        }
+
+        // Newest frame at the right edge
+        let frame_width = ApuView::WIDTH / ApuView::ROLL_FRAMES as f32;
+        let first_x = x + ApuView::WIDTH - self.history.len() as f32 * frame_width;
+        for (i, frame) in self.history.iter().enumerate()
+        {
+            for (channel, note) in Channel::ALL.iter().zip(frame.iter())
+            {
+                if let Some((note, volume)) = note.filter(|x| x.0 >= ApuView::ROLL_LOWEST_NOTE && x.0 <= ApuView::ROLL_HIGHEST_NOTE)
+                {
+                    let mut color = ApuView::get_color(*channel);
+                    color.a = 0.25 + 0.75 * volume;
+                    let rect = Rect::new(first_x + i as f32 * frame_width, ApuView::get_roll_y(y, note) - 1.0, frame_width, 2.0);
+                    mesh.rectangle(DrawMode::fill(), rect, color)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ApuView
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
\ No newline at end of file
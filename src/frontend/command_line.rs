@@ -0,0 +1,228 @@
+pub struct LaunchOptions
+{
+    pub rom_path: String,
+    pub scale: f32,
+    pub start_paused: bool,
+    pub audio: bool,
+    pub debug_panels: bool
+}
+
+impl Default for LaunchOptions
+{
+    fn default() -> Self
+    {
+        LaunchOptions
+        {
+            rom_path: String::new(),
+            scale: 3.0,
+            start_paused: false,
+            audio: true,
+            debug_panels: true
+        }
+    }
+}
+
+pub struct NestestOptions
+{
+    pub rom_path: String,
+    pub log_path: String
+}
+
+impl Default for NestestOptions
+{
+    fn default() -> Self
+    {
+        NestestOptions
+        {
+            rom_path: String::from("data/nestest.nes"),
+            log_path: String::from("data/nestest_output.log")
+        }
+    }
+}
+
+pub struct RecordOptions
+{
+    pub rom_path: String,
+    pub wav_path: String,
+    pub frames: u32,
+    pub sample_rate: u32
+}
+
+impl Default for RecordOptions
+{
+    fn default() -> Self
+    {
+        RecordOptions
+        {
+            rom_path: String::new(),
+            wav_path: String::new(),
+            frames: 600,
+            sample_rate: 44100
+        }
+    }
+}
+
+pub enum Command
+{
+    Run(LaunchOptions),
+    Nestest(NestestOptions),
+    Record(RecordOptions),
+    Help
+}
+
+impl Command
+{
+    pub const USAGE: &'static str = "\
+Usage: singularity-emu [OPTIONS] <ROM>
+       singularity-emu nestest [ROM] [LOG]
+       singularity-emu record <ROM> <WAV> [--frames <N>] [--rate <HZ>]
+
+Arguments:
+  <ROM>               Path to an iNES (.nes) ROM file
+
+Commands:
+  nestest             Run nestest in automation mode and compare each instruction against a
+                      reference log, stopping at the first mismatch
+                      (defaults: data/nestest.nes data/nestest_output.log)
+  record              Run a ROM without a window for a number of frames and write its audio
+                      to a .wav file (defaults: --frames 600 --rate 44100)
+
+Options:
+  --scale <FACTOR>    Scale of the emulated screen (default: 3)
+  --paused            Start with emulation paused
+  --no-audio          Disable audio output
+  --no-debug          Hide the debug panels and only show the emulated screen
+  -h, --help          Print this help";
+
+    // Parses the arguments that follow the program name
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Command, String>
+    {
+        let mut args = args.peekable();
+        if args.peek().map(String::as_str) == Some("nestest")
+        {
+            args.next();
+            return Command::parse_nestest(args);
+        }
+
+        if args.peek().map(String::as_str) == Some("record")
+        {
+            args.next();
+            return Command::parse_record(args);
+        }
+
+        let mut options = LaunchOptions::default();
+        let mut rom_path: Option<String> = None;
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "-h" | "--help" => return Ok(Command::Help),
+                "--paused" => options.start_paused = true,
+                "--no-audio" => options.audio = false,
+                "--no-debug" => options.debug_panels = false,
+                "--scale" =>
+                {
+                    let value = args.next().ok_or("--scale requires a value")?;
+                    options.scale = match value.parse::<f32>()
+                    {
+                        Ok(x) if x > 0.0 => x,
+                        _ => return Err(format!("Invalid scale '{}', expected a positive number", value))
+                    };
+                },
+                _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+                _ =>
+                {
+                    if rom_path.is_some()
+                    {
+                        return Err(format!("Unexpected argument '{}', only one ROM can be loaded", arg));
+                    }
+
+                    rom_path = Some(arg);
+                }
+            }
+        }
+
+        options.rom_path = rom_path.ok_or("No ROM path given")?;
+        Ok(Command::Run(options))
+    }
+
+    fn parse_nestest<I: Iterator<Item = String>>(args: I) -> Result<Command, String>
+    {
+        let mut options = NestestOptions::default();
+        let mut positional = 0;
+
+        for arg in args
+        {
+            match arg.as_str()
+            {
+                "-h" | "--help" => return Ok(Command::Help),
+                _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+                _ =>
+                {
+                    match positional
+                    {
+                        0 => options.rom_path = arg,
+                        1 => options.log_path = arg,
+                        _ => return Err(format!("Unexpected argument '{}', nestest takes a ROM and a log", arg))
+                    }
+
+                    positional += 1;
+                }
+            }
+        }
+
+        Ok(Command::Nestest(options))
+    }
+
+    fn parse_record<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String>
+    {
+        let mut options = RecordOptions::default();
+        let mut positional = 0;
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "-h" | "--help" => return Ok(Command::Help),
+                "--frames" =>
+                {
+                    let value = args.next().ok_or("--frames requires a value")?;
+                    options.frames = match value.parse::<u32>()
+                    {
+                        Ok(x) if x > 0 => x,
+                        _ => return Err(format!("Invalid frame count '{}', expected a positive number", value))
+                    };
+                },
+                "--rate" =>
+                {
+                    let value = args.next().ok_or("--rate requires a value")?;
+                    options.sample_rate = match value.parse::<u32>()
+                    {
+                        Ok(x) if x > 0 => x,
+                        _ => return Err(format!("Invalid sample rate '{}', expected a positive number", value))
+                    };
+                },
+                _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+                _ =>
+                {
+                    match positional
+                    {
+                        0 => options.rom_path = arg,
+                        1 => options.wav_path = arg,
+                        _ => return Err(format!("Unexpected argument '{}', record takes a ROM and a .wav path", arg))
+                    }
+
+                    positional += 1;
+                }
+            }
+        }
+
+        if positional < 2
+        {
+            return Err(String::from("record needs a ROM and a .wav path"));
+        }
+
+        Ok(Command::Record(options))
+    }
+}
\ No newline at end of file
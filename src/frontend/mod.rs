@@ -0,0 +1,2 @@
+pub mod command_line;
+pub mod apu_view;
\ No newline at end of file
@@ -1,8 +1,11 @@
+use std::io::{self, Read, Write};
 use std::sync::{Arc, Mutex};
 
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use ggez::{graphics::{self, ImageFormat, Sampler}, Context};
 
-use crate::{traits::{ReadWrite, Clockable, Resettable}, cartridge::cart::Cart, cartridge::cart::MirrorMode};
+use crate::{traits::{ReadWrite, Clockable, Resettable, Saveable}, cartridge::cart::Cart, cartridge::cart::MirrorMode};
+use crate::savestate;
 
 use ggez::glam::*;
 
@@ -74,6 +77,22 @@ struct ObjectAttributeEntry
     x: u8
 }
 
+impl ObjectAttributeEntry
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_all(&[self.y, self.id, self.attribute, self.x])
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        let mut bytes = [0u8; 4];
+        reader.read_exact(&mut bytes)?;
+        [self.y, self.id, self.attribute, self.x] = bytes;
+        Ok(())
+    }
+}
+
 struct BgNextTileInfo
 {
     id: u8,
@@ -106,6 +125,7 @@ pub struct Ppu2c02
     sprite_scanline: [ObjectAttributeEntry; 8],
     sprite_count: u8,
     frame_complete: bool,
+    odd_frame: bool,
     scan_line: i32,
     cycle: i32,
     renderer: Ppu2c02Renderer,
@@ -140,6 +160,7 @@ pub fn new() -> Self
             sprite_scanline: [ObjectAttributeEntry { y: 0, id: 0, attribute: 0, x: 0 }; 8],
             sprite_count: 0,
             frame_complete: false,
+            odd_frame: false,
             scan_line: 0,
             cycle: 0,
             renderer: Ppu2c02Renderer::new(),
@@ -240,6 +261,11 @@ fn clear_scanline_memory(&mut self)
         self.sprite_count = 0;
     }
 
+    pub fn get_screen_pixels(&self) -> &[u8]
+    {
+        self.renderer.screen_pixels.as_slice()
+    }
+
     pub fn render(&self, ctx: &mut Context, canvas: &mut ggez::graphics::Canvas, render_scale: f32)
     {
         // self.prepare_pattern_table(0);
@@ -247,6 +273,11 @@ pub fn render(&self, ctx: &mut Context, canvas: &mut ggez::graphics::Canvas, ren
         self.renderer.render(ctx, canvas, render_scale);
     }
 
+    pub fn render_pattern_tables(&self, ctx: &mut Context, canvas: &mut ggez::graphics::Canvas, x: f32, y: f32, render_scale: f32)
+    {
+        self.renderer.render_pattern_tables(ctx, canvas, x, y, render_scale);
+    }
+
     pub fn get_color_from_palette_ram(&mut self, palette_id: u8, pixel: u8) -> graphics::Color
     {
         const PALETTE_MEMORY_START: u16 = 0x3f00;
@@ -430,6 +461,72 @@ pub fn update_shifters(&mut self)
         }
     }
 
+    // Pattern table address of the low plane of the row of a sprite slot on the current
+    // scanline, the high plane is 8 bytes after it. Empty slots fetch tile $FF like the hardware.
+    fn get_sprite_pattern_address(&self, slot: usize) -> u16
+    {
+        if slot >= self.sprite_count as usize
+        {
+            return if self.ctrl.sprite_size()
+            {
+                0x1FE0
+            }
+            else
+            {
+                ((self.ctrl.pattern_sprite() as u16) << 12) | 0x0FF0
+            };
+        }
+
+        let sprite = &self.sprite_scanline[slot];
+        let flipped_vertically = sprite.attribute & 0x80 == 0x80;
+        let row = (self.scan_line as u16).wrapping_sub(sprite.y as u16);
+
+        if !self.ctrl.sprite_size()
+        {
+            // 8x8 - control register determines pattern table
+            let row = if flipped_vertically { 7 - (row & 0x07) } else { row & 0x07 };
+
+            ((self.ctrl.pattern_sprite() as u16) << 12) |   // Which pattern table (0 or 4kb)
+            ((sprite.id as u16) << 4) |                     // Which cell, Tile ID * 16 (16 bytes per tile)
+            row                                             // Which row in the cell, 0 to 7
+        }
+        else
+        {
+            // 8x16 - sprite attribute determines pattern table, the top half uses the even tile
+            // unless the sprite is flipped vertically
+            let second_tile = (row < 8) == flipped_vertically;
+            let row = if flipped_vertically { 7 - (row & 0x07) } else { row & 0x07 };
+
+            (((sprite.id & 0x01) as u16) << 12) |                       // Which pattern table (0 or 4kb)
+            (((sprite.id & 0xFE) + second_tile as u8) as u16) << 4 |    // Which cell, Tile ID * 16 (16 bytes per tile)
+            row                                                         // Which row in the cell, 0 to 7
+        }
+    }
+
+    // Resolves a nametable address ($2000-$3EFF) to one of the two physical nametables and an
+    // offset into it. The cartridge is asked every time since mappers can switch mirroring.
+    fn get_nametable_location(&self, address: u16) -> (usize, usize)
+    {
+        let mirror_mode = match &self.cartridge
+        {
+            Some(x) => x.lock().unwrap().get_mirror_mode(),
+            None => panic!("No cartridge inserted when querying mirror mode")
+        };
+
+        let table = match mirror_mode
+        {
+            // $2000 and $2800 share a nametable, as do $2400 and $2C00
+            MirrorMode::Vertical => (address >> 10) & 0x01,
+            // $2000 and $2400 share a nametable, as do $2800 and $2C00
+            MirrorMode::Horizontal => (address >> 11) & 0x01,
+            // Every nametable address goes to the same physical nametable
+            MirrorMode::OneScreenLo => 0,
+            MirrorMode::OneScreenHi => 1
+        };
+
+        (table as usize, (address & 0x03FF) as usize)
+    }
+
     pub fn get_name_table(&self) -> Box<[[u8; 1024]; 2]>
     {
         self.nametables.clone()
@@ -452,6 +549,7 @@ fn reset(&mut self)
         self.address_latch = false;
         self.ppu_data_buffer = 0;
         self.frame_complete = false;
+        self.odd_frame = false;
         self.scan_line = 0;
         self.cycle = 0;
         self.fine_x = 0;
@@ -462,6 +560,113 @@ fn reset(&mut self)
     }
 }
 
+// Everything except the renderer, whose screen is redrawn by the next frame anyway
+impl Saveable for Ppu2c02
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        for pattern in self.patterns.iter()
+        {
+            writer.write_all(pattern)?;
+        }
+
+        for nametable in self.nametables.iter()
+        {
+            writer.write_all(nametable)?;
+        }
+
+        writer.write_all(&self.palettes)?;
+
+        for entry in self.oam.iter().chain(self.sprite_scanline.iter())
+        {
+            entry.save_state(writer)?;
+        }
+
+        writer.write_u8(self.sprite_count)?;
+        savestate::write_bool(writer, self.frame_complete)?;
+        savestate::write_bool(writer, self.odd_frame)?;
+        writer.write_i32::<LittleEndian>(self.scan_line)?;
+        writer.write_i32::<LittleEndian>(self.cycle)?;
+        writer.write_u8(self.status.get_field())?;
+        writer.write_u8(self.mask.get_field())?;
+        writer.write_u8(self.ctrl.get_field())?;
+        savestate::write_bool(writer, self.address_latch)?;
+        writer.write_u16::<LittleEndian>(self.vram_addr.get_field())?;
+        writer.write_u16::<LittleEndian>(self.tram_addr.get_field())?;
+        writer.write_u8(self.fine_x)?;
+        writer.write_u8(self.ppu_data_buffer)?;
+        savestate::write_bool(writer, self.nmi)?;
+
+        writer.write_u8(self.bg_next_info.id)?;
+        writer.write_u8(self.bg_next_info.attrib)?;
+        writer.write_u8(self.bg_next_info.lsb)?;
+        writer.write_u8(self.bg_next_info.msb)?;
+
+        writer.write_u16::<LittleEndian>(self.bg_shifter_info.pattern_lo)?;
+        writer.write_u16::<LittleEndian>(self.bg_shifter_info.pattern_hi)?;
+        writer.write_u16::<LittleEndian>(self.bg_shifter_info.attrib_lo)?;
+        writer.write_u16::<LittleEndian>(self.bg_shifter_info.attrib_hi)?;
+
+        writer.write_u8(self.oam_addr)?;
+        writer.write_all(&self.fg_shifter_info.pattern_lo)?;
+        writer.write_all(&self.fg_shifter_info.pattern_hi)?;
+        savestate::write_bool(writer, self.sprite_zero_hit_possible)?;
+        savestate::write_bool(writer, self.sprite_zero_being_rendered)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        for pattern in self.patterns.iter_mut()
+        {
+            reader.read_exact(pattern)?;
+        }
+
+        for nametable in self.nametables.iter_mut()
+        {
+            reader.read_exact(nametable)?;
+        }
+
+        reader.read_exact(&mut self.palettes)?;
+
+        for entry in self.oam.iter_mut().chain(self.sprite_scanline.iter_mut())
+        {
+            entry.load_state(reader)?;
+        }
+
+        self.sprite_count = reader.read_u8()?;
+        self.frame_complete = savestate::read_bool(reader)?;
+        self.odd_frame = savestate::read_bool(reader)?;
+        self.scan_line = reader.read_i32::<LittleEndian>()?;
+        self.cycle = reader.read_i32::<LittleEndian>()?;
+        self.status.set_field(reader.read_u8()?);
+        self.mask.set_field(reader.read_u8()?);
+        self.ctrl.set_field(reader.read_u8()?);
+        self.address_latch = savestate::read_bool(reader)?;
+        self.vram_addr.set_field(reader.read_u16::<LittleEndian>()?);
+        self.tram_addr.set_field(reader.read_u16::<LittleEndian>()?);
+        self.fine_x = reader.read_u8()?;
+        self.ppu_data_buffer = reader.read_u8()?;
+        self.nmi = savestate::read_bool(reader)?;
+
+        self.bg_next_info.id = reader.read_u8()?;
+        self.bg_next_info.attrib = reader.read_u8()?;
+        self.bg_next_info.lsb = reader.read_u8()?;
+        self.bg_next_info.msb = reader.read_u8()?;
+
+        self.bg_shifter_info.pattern_lo = reader.read_u16::<LittleEndian>()?;
+        self.bg_shifter_info.pattern_hi = reader.read_u16::<LittleEndian>()?;
+        self.bg_shifter_info.attrib_lo = reader.read_u16::<LittleEndian>()?;
+        self.bg_shifter_info.attrib_hi = reader.read_u16::<LittleEndian>()?;
+
+        self.oam_addr = reader.read_u8()?;
+        reader.read_exact(&mut self.fg_shifter_info.pattern_lo)?;
+        reader.read_exact(&mut self.fg_shifter_info.pattern_hi)?;
+        self.sprite_zero_hit_possible = savestate::read_bool(reader)?;
+        self.sprite_zero_being_rendered = savestate::read_bool(reader)?;
+        Ok(())
+    }
+}
+
 impl ReadWrite for Ppu2c02
 {
     fn cpu_write(&mut self, address: u16, data: u8) -> bool
@@ -625,7 +830,7 @@ fn cpu_read(&mut self, address: u16, data: &mut u8) -> bool
 
     fn ppu_write(&mut self, address: u16, data: u8) -> bool
     {
-        let mut mut_addr = address & 0x3FFF;
+        let mut_addr = address & 0x3FFF;
         let handled = match &self.cartridge
             {
                 Some(x) =>
@@ -646,60 +851,8 @@ fn ppu_write(&mut self, address: u16, data: u8) -> bool
             }
             else if (0x2000..0x3EFF).contains(&mut_addr)
             {
-                mut_addr &= 0x0FFF;
-
-                match &self.cartridge
-                {
-                    Some(x) =>
-                    {
-                        let mirror_mode = x.lock().unwrap().get_mirror_mode();
-                        match mirror_mode
-                        {
-                            MirrorMode::Vertical =>
-                            {
-                                if mut_addr <= 0x03FF
-                                {
-                                    self.nametables[0][(mut_addr & 0x03FF) as usize] = data;
-                                }
-                                else if (0x0400..=0x07FF).contains(&mut_addr)
-                                {
-                                    self.nametables[1][(mut_addr & 0x03FF) as usize] = data;
-                                }
-                                else if (0x0800..=0x0BFF).contains(&mut_addr)
-                                {
-                                    self.nametables[0][(mut_addr & 0x03FF) as usize] = data;
-                                }
-                                else if (0x0C00..=0x0FFF).contains(&mut_addr)
-                                {
-                                    self.nametables[1][(mut_addr & 0x03FF) as usize] = data;
-                                }
-                            },
-                            MirrorMode::Horizontal =>
-                            {
-                                // This is cleaner to read
-                                #[allow(clippy::if_same_then_else)]
-                                if mut_addr <= 0x03FF
-                                {
-                                    self.nametables[0][(mut_addr & 0x03FF) as usize] = data;
-                                }
-                                else if (0x0400..=0x07FF).contains(&mut_addr)
-                                {
-                                    self.nametables[0][(mut_addr & 0x03FF) as usize] = data;
-                                }
-                                else if (0x0800..=0x0BFF).contains(&mut_addr)
-                                {
-                                    self.nametables[1][(mut_addr & 0x03FF) as usize] = data;
-                                }
-                                else if (0x0C00..=0x0FFF).contains(&mut_addr)
-                                {
-                                    self.nametables[1][(mut_addr & 0x03FF) as usize] = data;
-                                }
-                            },
-                            _ => panic!("Unimplemented mirror mode accessed")
-                        }
-                    },
-                    None => panic!("No cartridge inserted when querying mirror mode")
-                }
+                let (table, index) = self.get_nametable_location(mut_addr);
+                self.nametables[table][index] = data;
             }
             else if (0x3F00..=0x3FFF).contains(&mut_addr)
             {
@@ -724,7 +877,7 @@ fn ppu_write(&mut self, address: u16, data: u8) -> bool
 
     fn ppu_read(&self, address: u16, data: &mut u8) -> bool
     {
-        let mut mut_addr = address & 0x3FFF;
+        let mut_addr = address & 0x3FFF;
         let handled = match &self.cartridge
         {
             Some(x) =>
@@ -749,61 +902,8 @@ fn ppu_read(&self, address: u16, data: &mut u8) -> bool
             }
             else if (0x2000..0x3EFF).contains(&mut_addr)
             {
-                mut_addr &= 0x0FFF;
-
-                match &self.cartridge
-                {
-                    Some(x) =>
-                    {
-                        let mirror_mode = x.lock().unwrap().get_mirror_mode();
-                        match mirror_mode
-                        {
-                            MirrorMode::Vertical =>
-                            {
-                                if mut_addr <= 0x03FF
-                                {
-                                    *data = self.nametables[0][(mut_addr & 0x03FF) as usize];
-                                }
-                                else if (0x0400..=0x07FF).contains(&mut_addr)
-                                {
-                                    *data = self.nametables[1][(mut_addr & 0x03FF) as usize];
-                                }
-                                else if (0x0800..=0x0BFF).contains(&mut_addr)
-                                {
-                                    *data = self.nametables[0][(mut_addr & 0x03FF) as usize];
-                                }
-                                else if (0x0C00..=0x0FFF).contains(&mut_addr)
-                                {
-                                    *data = self.nametables[1][(mut_addr & 0x03FF) as usize];
-                                }
-                            },
-                            MirrorMode::Horizontal =>
-                            {
-                                // This is cleaner to read
-                                #[allow(clippy::if_same_then_else)]
-                                if mut_addr <= 0x03FF
-                                {
-                                    *data = self.nametables[0][(mut_addr & 0x03FF) as usize];
-                                }
-                                else if (0x0400..=0x07FF).contains(&mut_addr)
-                                {
-                                    *data = self.nametables[0][(mut_addr & 0x03FF) as usize];
-                                }
-                                else if (0x0800..=0x0BFF).contains(&mut_addr)
-                                {
-                                    *data = self.nametables[1][(mut_addr & 0x03FF) as usize];
-                                }
-                                else if (0x0C00..=0x0FFF).contains(&mut_addr)
-                                {
-                                    *data = self.nametables[1][(mut_addr & 0x03FF) as usize];
-                                }
-                            },
-                            _ => panic!("Unimplemented mirror mode accessed")
-
-                        }
-                    },
-                    None => panic!("No cartridge inserted when querying mirror mode")
-                }
+                let (table, index) = self.get_nametable_location(mut_addr);
+                *data = self.nametables[table][index];
             }
             else if (0x3F00..=0x3FFF).contains(&mut_addr)
             {
@@ -839,13 +939,19 @@ fn ppu_read(&self, address: u16, data: &mut u8) -> bool
 
 impl Clockable for Ppu2c02
 {
+    // Returns true on the tick that completes a frame
     fn clock_tick(&mut self) -> bool
     {
         // Based on the table from NesDev which details what clock ticks perform what operations
         // at what scanelines and cycles: https://www.nesdev.org/wiki/PPU_rendering
         if self.scan_line >= -1 && self.scan_line < 240
         {
-            if self.scan_line == 0 && self.cycle == 0
+            // The PPU only fetches from its bus while rendering is enabled
+            let rendering_enabled = self.mask.render_background() || self.mask.render_sprites();
+
+            // Odd frames are one dot shorter when rendering is enabled, the idle dot 0 of
+            // the first visible scanline is skipped
+            if self.scan_line == 0 && self.cycle == 0 && self.odd_frame && rendering_enabled
             {
                 self.cycle = 1;
             }
@@ -876,11 +982,14 @@ fn clock_tick(&mut self) -> bool
                     {
                         self.load_background_shifters();
 
-                        let mut id: u8 = 0;
-                        self.ppu_read(0x2000 | (self.vram_addr.get_field() & 0x0FFF), &mut id);
-                        self.bg_next_info.id = id;
+                        if rendering_enabled
+                        {
+                            let mut id: u8 = 0;
+                            self.ppu_read(0x2000 | (self.vram_addr.get_field() & 0x0FFF), &mut id);
+                            self.bg_next_info.id = id;
+                        }
                     },
-                    2 =>
+                    2 if rendering_enabled =>
                     {
                         let mut attrib: u8 = 0;
                         let addr: u16 = 0x23C0 | ((self.vram_addr.name_table_y() as u16) << 11)
@@ -902,7 +1011,7 @@ fn clock_tick(&mut self) -> bool
                         }
                         self.bg_next_info.attrib &= 3;
                     },
-                    4 =>
+                    4 if rendering_enabled =>
                     {
                         let mut lsb: u8 = 0;
                         let addr: u16 = ((self.ctrl.pattern_background() as u16) << 12)
@@ -911,7 +1020,7 @@ fn clock_tick(&mut self) -> bool
                         self.ppu_read(addr, &mut lsb);
                         self.bg_next_info.lsb = lsb;
                     }
-                    6 =>
+                    6 if rendering_enabled =>
                     {
                         let mut msb: u8 = 0;
                         let addr: u16 = ((self.ctrl.pattern_background() as u16) << 12)
@@ -945,13 +1054,19 @@ fn clock_tick(&mut self) -> bool
             }
 
             // These are superfluous, but technically in the implementation
-            if self.cycle == 338 || self.cycle == 340
+            if (self.cycle == 338 || self.cycle == 340) && rendering_enabled
             {
                 let mut id = 0;
                 self.ppu_read(0x2000 | (self.vram_addr.get_field() & 0x0FFF), &mut id);
                 self.bg_next_info.id = id;
             }
 
+            // The pre-render line evaluates no sprites, it only makes the dummy fetches below
+            if self.cycle == 257 && self.scan_line == -1
+            {
+                self.clear_scanline_memory();
+            }
+
             // Foreground rendering
             if self.cycle == 257 && self.scan_line >= 0
             {
@@ -994,98 +1109,40 @@ fn clock_tick(&mut self) -> bool
                 }
             }
 
-            if self.cycle == 340
+            // Sprite pattern fetches, one slot every 8 cycles from 257 to 320. Empty slots still
+            // fetch (tile $FF), mappers like MMC3 count scanlines by watching these accesses.
+            if self.cycle >= 257 && self.cycle <= 320 && rendering_enabled
             {
-                for i in 0..self.sprite_count
+                let slot = ((self.cycle - 257) / 8) as usize;
+                match (self.cycle - 257) % 8
                 {
-                    let index = i as usize;
-                    let mut sprite_pattern_bits_lo: u8 = 0;
-                    let mut sprite_pattern_bits_hi: u8 = 0;
-                    let sprite_pattern_addr_lo: u16;
-
-                    if !self.ctrl.sprite_size()
+                    4 =>
                     {
-                        // 8x8 - control register determines pattern table
-                        if self.sprite_scanline[index].attribute & 0x80 != 0x80
-                        {
-                            // Not flipped vertically
-                            sprite_pattern_addr_lo =
-                                ((self.ctrl.pattern_sprite() as u16) << 12) |                               // Which pattern table (0 or 4kb)
-                                ((self.sprite_scanline[index].id as u16) << 4) |                            // Which cell, Tile ID * 16 (16 bytes per tile)
-                                (self.scan_line as u16 - self.sprite_scanline[index].y as u16);             // Which row in the cell, 0 to 7
-                        }
-                        else
-                        {
-                            // Flipped vertically
-                            sprite_pattern_addr_lo =
-                                ((self.ctrl.pattern_sprite() as u16) << 12) |                               // Which pattern table (0 or 4kb)
-                                ((self.sprite_scanline[index].id as u16) << 4) |                            // Which cell, Tile ID * 16 (16 bytes per tile)
-                                (7 - (self.scan_line as u16 - self.sprite_scanline[index].y as u16));       // Which row in the cell, 7 to 0
-                        }
-                    }
-                    else
+                        let mut sprite_pattern_bits_lo: u8 = 0;
+                        self.ppu_read(self.get_sprite_pattern_address(slot), &mut sprite_pattern_bits_lo);
+                        self.fg_shifter_info.pattern_lo[slot] = sprite_pattern_bits_lo;
+                    },
+                    6 =>
                     {
-                        // 8x16 - sprite attribute determines pattern table 
-                        if self.sprite_scanline[index].attribute & 0x80 != 0x80
-                        {
-                            // Not flipped vertically
-                            if self.scan_line as u8 - self.sprite_scanline[index].y < 8
-                            {
-                                // Top half of the sprite
-                                sprite_pattern_addr_lo =
-                                    (((self.sprite_scanline[index].id & 0x01) as u16) << 12) |              // Which pattern table (0 or 4kb)
-                                    ((self.sprite_scanline[index].id & 0xFE) as u16) << 4 |                 // Which cell, Tile ID * 16 (16 bytes per tile)
-                                    (self.scan_line as u16 - self.sprite_scanline[index].y as u16) & 0x07;  // Which row in the cell, 0 to 7
-                            }
-                            else
-                            {
-                                sprite_pattern_addr_lo =
-                                    (((self.sprite_scanline[index].id & 0x01) as u16) << 12) |              // Which pattern table (0 or 4kb)
-                                    (((self.sprite_scanline[index].id & 0xFE) + 1) as u16) << 4 |           // Which cell, Tile ID * 16 (16 bytes per tile)
-                                    (self.scan_line as u16 - self.sprite_scanline[index].y as u16) & 0x07;  // Which row in the cell, 0 to 7
-                            }
-                        }
-                        else
+                        let mut sprite_pattern_bits_hi: u8 = 0;
+                        self.ppu_read(self.get_sprite_pattern_address(slot) + 8, &mut sprite_pattern_bits_hi);
+                        self.fg_shifter_info.pattern_hi[slot] = sprite_pattern_bits_hi;
+
+                        if slot < self.sprite_count as usize && self.sprite_scanline[slot].attribute & 0x40 == 0x40
                         {
-                            // Flipped vertically
-                            if self.scan_line as u8 - self.sprite_scanline[index].y < 8
-                            {
-                                // Top half of the sprite
-                                sprite_pattern_addr_lo =
-                                    (((self.sprite_scanline[index].id & 0x01) as u16) << 12) |              // Which pattern table (0 or 4kb)
-                                    (((self.sprite_scanline[index].id & 0xFE) + 1) as u16) << 4 |                 // Which cell, Tile ID * 16 (16 bytes per tile)
-                                    (7 - (self.scan_line as u16 - self.sprite_scanline[index].y as u16)) & 0x07;  // Which row in the cell, 0 to 7
-                            }
-                            else
-                            {
-                                sprite_pattern_addr_lo =
-                                    (((self.sprite_scanline[index].id & 0x01) as u16) << 12) |              // Which pattern table (0 or 4kb)
-                                    ((self.sprite_scanline[index].id & 0xFE) as u16) << 4 |                 // Which cell, Tile ID * 16 (16 bytes per tile)
-                                    (7 - (self.scan_line as u16 - self.sprite_scanline[index].y as u16)) & 0x07;  // Which row in the cell, 0 to 7
-                            }
+                            let flip_byte = |b: u8| -> u8 {
+                                let mut r: u8 = b;
+                                r = (r & 0xF0) >> 4 | (r & 0x0F) << 4;
+                                r = (r & 0xCC) >> 2 | (r & 0x33) << 2;
+                                r = (r & 0xAA) >> 1 | (r & 0x55) << 1;
+                                r
+                            };
+
+                            self.fg_shifter_info.pattern_lo[slot] = flip_byte(self.fg_shifter_info.pattern_lo[slot]);
+                            self.fg_shifter_info.pattern_hi[slot] = flip_byte(self.fg_shifter_info.pattern_hi[slot]);
                         }
-                    }
-
-                    let sprite_pattern_addr_hi: u16 = sprite_pattern_addr_lo + 8;
-                    self.ppu_read(sprite_pattern_addr_lo, &mut sprite_pattern_bits_lo);
-                    self.ppu_read(sprite_pattern_addr_hi, &mut sprite_pattern_bits_hi);
-
-                    if self.sprite_scanline[index].attribute & 0x40 == 0x40
-                    {
-                        let flip_byte = |b: u8| -> u8 {
-                            let mut r: u8 = b;
-                            r = (r & 0xF0) >> 4 | (r & 0x0F) << 4;
-                            r = (r & 0xCC) >> 2 | (r & 0x33) << 2;
-                            r = (r & 0xAA) >> 1 | (r & 0x55) << 1;
-                            r
-                        };
-
-                        sprite_pattern_bits_lo = flip_byte(sprite_pattern_bits_lo);
-                        sprite_pattern_bits_hi = flip_byte(sprite_pattern_bits_hi);
-                    }
-
-                    self.fg_shifter_info.pattern_lo[index] = sprite_pattern_bits_lo;
-                    self.fg_shifter_info.pattern_hi[index] = sprite_pattern_bits_hi;
+                    },
+                    _ => ()
                 }
             }
         }
@@ -1244,6 +1301,8 @@ fn clock_tick(&mut self) -> bool
             {
                 self.scan_line = -1;
                 self.frame_complete = true;
+                self.odd_frame = !self.odd_frame;
+                return true;
             }
         }
 
@@ -1251,9 +1310,9 @@ fn clock_tick(&mut self) -> bool
     }
 }
 
-const PIXEL_DEPTH: usize = 4;
-const SCREEN_ROWS: usize = 240;
-const SCREEN_COLS: usize = 256;
+pub const PIXEL_DEPTH: usize = 4;
+pub const SCREEN_ROWS: usize = 240;
+pub const SCREEN_COLS: usize = 256;
 const PATTERN_ROWS: usize = 128;
 const PATTERN_COLS: usize = 128;
 
@@ -1358,24 +1417,30 @@ pub fn render(&self, ctx: &mut Context, canvas: &mut ggez::graphics::Canvas, ren
         canvas.set_sampler(Sampler::from(graphics::FilterMode::Nearest));
 
         let screen_image = graphics::Image::from_pixels(ctx, self.screen_pixels.as_slice(), ImageFormat::Rgba8UnormSrgb, SCREEN_COLS as u32, SCREEN_ROWS as u32);
-        let pattern0_image = graphics::Image::from_pixels(ctx, self.pattern_table[0].as_slice(),
-            ImageFormat::Rgba8UnormSrgb, PATTERN_ROWS as u32, PATTERN_COLS as u32);
-        let pattern1_image = graphics::Image::from_pixels(ctx, self.pattern_table[1].as_slice(),
-            ImageFormat::Rgba8UnormSrgb, PATTERN_ROWS as u32, PATTERN_COLS as u32);
 
         let screen_params = graphics::DrawParam::new()
             .dest(Vec2::new(0.0, 0.0))
             .scale(Vec2::new(render_scale, render_scale));
         
         screen_image.draw(canvas, screen_params);
+    }
+
+    pub fn render_pattern_tables(&self, ctx: &mut Context, canvas: &mut ggez::graphics::Canvas, x: f32, y: f32, render_scale: f32)
+    {
+        canvas.set_sampler(Sampler::from(graphics::FilterMode::Nearest));
+
+        let pattern0_image = graphics::Image::from_pixels(ctx, self.pattern_table[0].as_slice(),
+            ImageFormat::Rgba8UnormSrgb, PATTERN_ROWS as u32, PATTERN_COLS as u32);
+        let pattern1_image = graphics::Image::from_pixels(ctx, self.pattern_table[1].as_slice(),
+            ImageFormat::Rgba8UnormSrgb, PATTERN_ROWS as u32, PATTERN_COLS as u32);
 
         let pattern0_params = graphics::DrawParam::new()
-            .dest(Vec2::new(775.0, 500.0))
+            .dest(Vec2::new(x, y))
             .scale(Vec2::new(render_scale, render_scale));
         pattern0_image.draw(canvas, pattern0_params);
 
         let pattern1_params = graphics::DrawParam::new()
-            .dest(Vec2::new(1050.0, 500.0))
+            .dest(Vec2::new(x + 275.0, y))
             .scale(Vec2::new(render_scale, render_scale));
         pattern1_image.draw(canvas, pattern1_params);
     }
@@ -1,4 +1,8 @@
-use crate::traits::ReadWrite;
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::traits::{ReadWrite, Saveable};
 
 #[repr(u8)]
 pub enum NesKey
@@ -59,6 +63,22 @@ fn default() -> Self {
     }
 }
 
+impl Saveable for NesController
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u8(self.snapshot_state)?;
+        writer.write_u8(self.live_state)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.snapshot_state = reader.read_u8()?;
+        self.live_state = reader.read_u8()?;
+        Ok(())
+    }
+}
+
 impl ReadWrite for NesController
 {
     fn cpu_write(&mut self, address: u16, _: u8) -> bool
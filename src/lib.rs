@@ -0,0 +1,14 @@
+pub mod traits;
+pub mod bus;
+pub mod memory;
+pub mod cpu;
+pub mod mapper;
+pub mod gfx;
+pub mod cartridge;
+pub mod input;
+pub mod sound;
+pub mod nes;
+pub mod nestest;
+pub mod audio_capture;
+pub mod rewind;
+pub mod savestate;
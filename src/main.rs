@@ -1,116 +1,103 @@
-use bus::main_bus::MainBus;
-use cartridge::cart::Cart;
 use cpal::traits::StreamTrait;
-use cpu::cpu6502::Cpu6502;
-use gfx::ppu2c02::Ppu2c02;
-use sound::apu2a03::Apu2a03;
-use input::controller::NesKey;
-use sound::sound_engine::SoundEngine;
-use traits::{ReadWrite, Resettable};
-use crate::cpu::cpu6502::Flags6502;
-use crate::traits::Clockable;
-use std::sync::Once;
-
-use std::sync::{Arc, Mutex};
+use singularity_emu::cartridge::cart::Cart;
+use singularity_emu::cpu::cpu6502::Flags6502;
+use singularity_emu::gfx::ppu2c02::{SCREEN_COLS, SCREEN_ROWS};
+use singularity_emu::input::controller::NesKey;
+use singularity_emu::nes::Nes;
+use singularity_emu::nestest;
+use singularity_emu::audio_capture;
+use singularity_emu::sound::apu2a03::Channel;
+use singularity_emu::sound::sound_engine::{AudioSink, SoundEngine};
+use singularity_emu::sound::wav_writer::WavWriter;
+use singularity_emu::traits::{ReadWrite, Resettable};
+
 use std::ops::Bound;
 use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
 
 // Game engine
 use ggez::event;
 use ggez::graphics::{self};
+use ggez::input::keyboard::{KeyCode, KeyMods};
 use ggez::{Context, GameResult};
 use ggez::glam::*;
 use ggez::graphics::Text;
 
-pub mod traits;
-pub mod bus;
-pub mod memory;
-pub mod cpu;
-pub mod mapper;
-pub mod gfx;
-pub mod cartridge;
-pub mod input;
-pub mod sound;
+mod frontend;
+
+use frontend::apu_view::ApuView;
+use frontend::command_line::{Command, LaunchOptions};
 
 struct MainState
 {
-    bus: Arc<Mutex<MainBus>>,
-    cpu: Option<Arc<Mutex<Cpu6502>>>,
-    ppu: Option<Arc<Mutex<Ppu2c02>>>,
-    apu: Option<Arc<Mutex<Apu2a03>>>,
+    nes: Nes,
+    rom_path: String,
     map_asm: BTreeMap<u16, String>,
-    audio_thread_emulation_tick: bool,
+    audio_enabled: bool,
     emulation_run: bool,
+    battery_flush_time: f32,
     residual_time: f32,
-    sound_engine: Option<Arc<Mutex<SoundEngine>>>,
-    sound_thread: Option<cpal::Stream>
+    render_scale: f32,
+    show_debug_panels: bool,
+    sound_engine: Option<SoundEngine>,
+    sound_thread: Option<cpal::Stream>,
+    recorder: Option<WavWriter<BufWriter<File>>>,
+    vgm_log_path: Option<PathBuf>,
+    apu_view: ApuView,
+    show_audio_panel: bool
 }
 
-static mut INSTANCE: Option<MainState> = None;
-static INIT: Once = Once::new();
-
-
 impl MainState
 {
-    fn initialize(&mut self)
+    fn initialize(&mut self, cart: Cart)
     {
-        self.sound_engine = Some(Arc::new(Mutex::new(SoundEngine::new(MainState::emulator_tick))));
-
-        let mut bus = self.bus.lock().unwrap();
-        self.cpu = Some(Arc::clone(&bus.get_cpu()));
-        self.ppu = Some(Arc::clone(&bus.get_ppu()));
-        self.apu = Some(Arc::clone(&bus.get_apu()));
-
-        // Link the CPU to the BUS
-        self.cpu.as_mut().unwrap().lock().unwrap().set_bus(Some(Arc::clone(&self.bus)));
-
-        let cart = Cart::new("data\\super mario.nes".to_string());
-        match cart
+        if self.audio_enabled
         {
-            Ok(x) =>
-            {
-                let cart_wrapper = Arc::new(Mutex::new(x));
-                bus.insert_cartridge(cart_wrapper);
-            },
-            Err(x) =>
-            {
-                panic!("Failed to load cartridge: {}", x);
-            }
+            self.sound_engine = Some(SoundEngine::new());
         }
 
-        drop(bus);
+        self.nes.insert_cartridge(cart);
+        self.nes.enable_rewind(MainState::REWIND_INTERVAL_FRAMES, MainState::REWIND_CAPACITY);
+        self.load_battery_ram();
 
         // Dissemble code into our main state so we can render it
-        self.map_asm = self.cpu.as_mut().unwrap().lock().unwrap().disassemble(0x0000, 0xFFFF, false);
+        self.map_asm = self.nes.get_cpu().lock().unwrap().disassemble(0x0000, 0xFFFF);
 
         // Reset the CPU
         self.reset();
     }
 
-    fn get_instance() -> &'static mut MainState
+    fn new(options: &LaunchOptions, cart: Cart) -> Self
     {
-        // Required to store the raw mutable pointer
-        unsafe
+        let mut s = MainState
         {
-            INIT.call_once(|| {
-                INSTANCE = Some(MainState
-                {
-                    bus: Arc::new(Mutex::new(MainBus::new())),
-                    cpu: None,
-                    ppu: None,
-                    apu: None,
-                    map_asm: BTreeMap::new(),
-                    audio_thread_emulation_tick: true,
-                    emulation_run: false,
-                    residual_time: 0.0,
-                    sound_engine: None,
-                    sound_thread: None,
-                });
+            nes: Nes::new(),
+            rom_path: options.rom_path.clone(),
+            map_asm: BTreeMap::new(),
+            audio_enabled: options.audio,
+            emulation_run: !options.start_paused,
+            battery_flush_time: 0.0,
+            residual_time: 0.0,
+            render_scale: options.scale,
+            show_debug_panels: options.debug_panels,
+            sound_engine: None,
+            sound_thread: None,
+            recorder: None,
+            vgm_log_path: None,
+            apu_view: ApuView::new(),
+            show_audio_panel: false
+        };
+
+        s.initialize(cart);
+        s
+    }
 
-                (*INSTANCE.as_mut().unwrap()).initialize();
-            });
-            INSTANCE.as_mut().unwrap()
-        }
+    // Where the debug panels start, to the right of and below the emulated screen
+    fn get_panel_origin(&self) -> (f32, f32)
+    {
+        (SCREEN_COLS as f32 * self.render_scale + 7.0, SCREEN_ROWS as f32 * self.render_scale + 30.0)
     }
 
     const OFFSET_X: f32 = 16.0;
@@ -120,7 +107,8 @@ fn draw_cpu_ram(&mut self, x: i32, y: i32, mut n_addr: u16, n_rows: i32, n_cols:
     {
         let n_cpu_ram_x: f32 = x as f32;
         let mut n_cpu_ram_y: f32 = y as f32;
-        let mut bus = self.bus.lock().unwrap();
+        let bus_object = self.nes.get_bus();
+        let mut bus = bus_object.lock().unwrap();
 
         for _ in 0..n_rows
         {
@@ -140,7 +128,8 @@ fn draw_cpu_ram(&mut self, x: i32, y: i32, mut n_addr: u16, n_rows: i32, n_cols:
 
     fn draw_cpu(&mut self, x: f32, y: f32, canvas: &mut ggez::graphics::Canvas)
     {
-        let cpu = self.cpu.as_ref().unwrap().lock().unwrap();
+        let cpu_object = self.nes.get_cpu();
+        let cpu = cpu_object.lock().unwrap();
 
         canvas.draw(&Text::new("Status"), Vec2::new(x, y));
         let mut num_offset: f32 = 0.0;
@@ -194,6 +183,13 @@ fn draw_cpu(&mut self, x: f32, y: f32, canvas: &mut ggez::graphics::Canvas)
         num_offset += 1.0;
 
         canvas.draw(&Text::new(format!("Stack P: ${:04x}", cpu.get_stkp())), Vec2::new(x, y + (MainState::OFFSET_Y * num_offset)));
+        num_offset += 1.0;
+
+        if cpu.is_jammed()
+        {
+            canvas.draw(&Text::new(format!("CPU halted: KIL ${:02x} at ${:04x}", cpu.get_opcode(), cpu.get_pc())),
+                graphics::DrawParam::new().color(graphics::Color::RED).dest(Vec2::new(x, y + (MainState::OFFSET_Y * num_offset))));
+        }
 
         // TODO: Implement disassembly
 
@@ -201,7 +197,8 @@ fn draw_cpu(&mut self, x: f32, y: f32, canvas: &mut ggez::graphics::Canvas)
 
     fn draw_code(&mut self, x: f32, y: f32, n_lines: i32, canvas: &mut ggez::graphics::Canvas)
     {
-        let cpu = self.cpu.as_ref().unwrap().lock().unwrap();
+        let cpu_object = self.nes.get_cpu();
+        let cpu = cpu_object.lock().unwrap();
 
         let mut before_keys: Vec<_> = self.map_asm.range((Bound::Unbounded, Bound::Excluded(cpu.get_pc())))
                                                 .rev()
@@ -237,7 +234,8 @@ fn draw_code(&mut self, x: f32, y: f32, n_lines: i32, canvas: &mut ggez::graphic
 
     fn draw_oam(&mut self, x: f32, y: f32, n_lines: i32, canvas: &mut ggez::graphics::Canvas)
     {
-        let ppu = self.ppu.as_ref().unwrap().lock().unwrap();
+        let ppu_object = self.nes.get_ppu();
+        let ppu = ppu_object.lock().unwrap();
         for i in 0..(n_lines as u8)
         {
             let byte_0 = ppu.get_oam_memory_at_addr(i * 4);
@@ -256,25 +254,73 @@ fn draw_oam(&mut self, x: f32, y: f32, n_lines: i32, canvas: &mut ggez::graphics
         }
     }
 
-    fn draw_notes(&mut self, x: f32, y: f32, canvas: &mut ggez::graphics::Canvas)
+    fn draw_audio(&mut self, x: f32, y: f32, ctx: &mut Context, canvas: &mut ggez::graphics::Canvas) -> GameResult
     {
-        let apu = self.apu.as_ref().unwrap().lock().unwrap();
-        let apu_debug = apu.get_debug_info();
+        let apu_object = self.nes.get_apu();
+        let apu = apu_object.lock().unwrap();
+        self.apu_view.draw(&apu, x, y, ctx, canvas)
+    }
 
-        let s: String = format!("{}, {}, {}, {}, {}", apu_debug.0, apu_debug.1, apu_debug.2, apu_debug.3, apu_debug.4);
-        
-        canvas.draw(&Text::new(s), Vec2::new(x, y));
+    // Keeps the piano roll in step with the emulated frames
+    fn step_frame(&mut self)
+    {
+        let was_jammed = self.nes.get_cpu().lock().unwrap().is_jammed();
+        self.nes.step_frame();
+        self.apu_view.record_frame(&self.nes.get_apu().lock().unwrap());
+
+        // Stop where the CPU jammed so that the debugger shows how the program got there
+        let cpu_object = self.nes.get_cpu();
+        let cpu = cpu_object.lock().unwrap();
+        if cpu.is_jammed() && !was_jammed
+        {
+            eprintln!("CPU halted: KIL ${:02X} at ${:04X}", cpu.get_opcode(), cpu.get_pc());
+            self.emulation_run = false;
+        }
+    }
+
+    const CHANNEL_KEYS: [KeyCode; 5] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5];
+
+    // 1-5 mute pulse 1, pulse 2, triangle, noise and DMC, Shift+1-5 solo them
+    fn process_channel_input(&mut self, ctx: &mut Context)
+    {
+        let apu_object = self.nes.get_apu();
+        let mut apu = apu_object.lock().unwrap();
+        for (channel, key) in Channel::ALL.iter().zip(MainState::CHANNEL_KEYS.iter())
+        {
+            if !ctx.keyboard.is_key_just_pressed(*key)
+            {
+                continue;
+            }
+
+            if ctx.keyboard.is_mod_active(KeyMods::SHIFT)
+            {
+                let soloed = !apu.is_soloed(*channel);
+                apu.set_soloed(*channel, soloed);
+            }
+            else
+            {
+                let muted = !apu.is_muted(*channel);
+                apu.set_muted(*channel, muted);
+            }
+        }
     }
 
     fn draw_perf(&mut self, x: f32, y: f32, ctx: &Context, canvas: &mut ggez::graphics::Canvas)
     {
         let s: String = format!("FPS: {}", ctx.time.fps());
         canvas.draw(&Text::new(s), Vec2::new(x, y));
+
+        if let Some(rewind) = self.nes.get_rewind()
+        {
+            let s: String = format!("Rewind: {} snapshots, {} KB", rewind.len(), rewind.memory_usage() / 1024);
+            canvas.draw(&Text::new(s), Vec2::new(x, y + MainState::OFFSET_Y));
+        }
     }
 
     fn process_controller_input(&mut self, ctx: &mut Context)
     {
-        let mut bus = self.bus.lock().unwrap();
+        let bus_object = self.nes.get_bus();
+        let mut bus = bus_object.lock().unwrap();
         let controller_object = bus.get_controller(0);
         let mut controller = controller_object.lock().unwrap();
         controller.clear_live_state();
@@ -320,82 +366,149 @@ fn process_controller_input(&mut self, ctx: &mut Context)
         }
     }
 
-    // For debugging purposes
-    pub fn emulator_update_without_audio(&mut self, ctx: &mut Context) -> GameResult
+    const SAVE_STATE_KEYS: [KeyCode; 9] = [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5,
+        KeyCode::F6, KeyCode::F7, KeyCode::F8, KeyCode::F9];
+
+    // Save states live next to the ROM, one file per slot (game.nes -> game.ss1 ... game.ss9)
+    fn get_save_state_path(&self, slot: usize) -> PathBuf
+    {
+        PathBuf::from(&self.rom_path).with_extension(format!("ss{}", slot))
+    }
+
+    // Shift+F1-F9 saves to a slot, F1-F9 loads from it
+    fn process_save_state_input(&mut self, ctx: &mut Context)
     {
-        if self.emulation_run
+        for (index, key) in MainState::SAVE_STATE_KEYS.iter().enumerate()
         {
-            if self.residual_time > 0.0
+            if !ctx.keyboard.is_key_just_pressed(*key)
             {
-                // Sleeping
-                self.residual_time -= ctx.time.delta().as_secs_f32();
+                continue;
+            }
+
+            let slot = index + 1;
+            let path = self.get_save_state_path(slot);
+            if ctx.keyboard.is_mod_active(KeyMods::SHIFT)
+            {
+                match self.nes.save_state().and_then(|data| std::fs::write(&path, data))
+                {
+                    Ok(()) => println!("Saved state to slot {} ({})", slot, path.display()),
+                    Err(e) => eprintln!("Failed to save state to slot {}: {}", slot, e)
+                }
             }
             else
             {
-                // Rendering a frame
-                self.residual_time += (1.0 / 60.0) - ctx.time.delta().as_secs_f32();
-                loop
+                match std::fs::read(&path).and_then(|data| self.nes.load_state(&data))
                 {
-                    self.clock_tick();
-                    if self.ppu.as_ref().unwrap().lock().unwrap().frame_complete()
-                    {
-                        break;
-                    }
+                    Ok(()) => println!("Loaded state from slot {}", slot),
+                    Err(e) => eprintln!("Failed to load state from slot {}: {}", slot, e)
                 }
+            }
+        }
+    }
+
+    // Battery-backed PRG RAM lives next to the ROM as well (game.nes -> game.sav)
+    fn get_battery_save_path(&self) -> PathBuf
+    {
+        PathBuf::from(&self.rom_path).with_extension("sav")
+    }
+
+    fn load_battery_ram(&mut self)
+    {
+        let path = self.get_battery_save_path();
+        if !self.nes.has_battery() || !path.exists()
+        {
+            return;
+        }
+
+        match std::fs::read(&path).and_then(|data| self.nes.load_battery_ram(&data))
+        {
+            Ok(()) => println!("Loaded battery save from {}", path.display()),
+            Err(e) => eprintln!("Failed to load battery save {}: {}", path.display(), e)
+        }
+    }
+
+    // Writes the battery-backed PRG RAM out if the game changed it
+    fn flush_battery_ram(&mut self)
+    {
+        if let Some(data) = self.nes.take_battery_ram()
+        {
+            let path = self.get_battery_save_path();
+            if let Err(e) = std::fs::write(&path, data)
+            {
+                eprintln!("Failed to write battery save {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    // Flushing every few seconds keeps progress even if the emulator doesn't exit cleanly
+    const BATTERY_FLUSH_INTERVAL_SECONDS: f32 = 5.0;
+
+    fn process_battery_flush(&mut self, ctx: &mut Context)
+    {
+        self.battery_flush_time += ctx.time.delta().as_secs_f32();
+        if self.battery_flush_time >= MainState::BATTERY_FLUSH_INTERVAL_SECONDS
+        {
+            self.battery_flush_time = 0.0;
+            self.flush_battery_ram();
+        }
+    }
+
+    // A snapshot every 4 frames for 3 minutes
+    const REWIND_INTERVAL_FRAMES: u32 = 4;
+    const REWIND_CAPACITY: usize = 3 * 60 * 60 / 4;
 
-                self.ppu.as_mut().unwrap().lock().unwrap().set_frame_complete(false);
+    // While Backspace is held, every update steps back one snapshot instead of running
+    fn process_rewind_input(&mut self, ctx: &mut Context) -> bool
+    {
+        let rewinding = ctx.keyboard.is_key_pressed(KeyCode::Back);
+        if rewinding
+        {
+            if let Err(e) = self.nes.rewind_step()
+            {
+                eprintln!("Failed to rewind: {}", e);
             }
         }
-        else
+
+        rewinding
+    }
+
+    // The emulator runs here on the frame loop, audio is queued for the sound engine to play
+    pub fn emulator_update(&mut self, ctx: &mut Context) -> GameResult
+    {
+        // Rewinding replaces running or stepping for this update
+        if !self.process_rewind_input(ctx)
         {
-            // Stepping mode
-            if ctx.keyboard.is_key_just_pressed(ggez::input::keyboard::KeyCode::C)
-            {   
-                loop
+            if self.emulation_run
+            {
+                if self.residual_time > 0.0
                 {
-                    self.clock_tick();
-                    if self.cpu.as_mut().unwrap().lock().unwrap().complete()
-                    {
-                        break;
-                    }
+                    // Sleeping
+                    self.residual_time -= ctx.time.delta().as_secs_f32();
                 }
-
-                // Since the CPU runs slower, clear out any leftover instructions
-                loop
+                else
                 {
-                    self.clock_tick();
-                    if !self.cpu.as_mut().unwrap().lock().unwrap().complete()
-                    {
-                        break;
-                    }
+                    // Rendering a frame
+                    self.residual_time += (1.0 / 60.0) - ctx.time.delta().as_secs_f32();
+                    self.step_frame();
                 }
             }
-
-            if ctx.keyboard.is_key_just_pressed(ggez::input::keyboard::KeyCode::F)
-            {   
-                loop
+            else
+            {
+                // Stepping mode
+                if ctx.keyboard.is_key_just_pressed(ggez::input::keyboard::KeyCode::C)
                 {
-                    self.clock_tick();
-                    if self.ppu.as_ref().unwrap().lock().unwrap().frame_complete()
-                    {
-                        break;
-                    }
+                    self.nes.step_instruction();
                 }
 
-                // Since the CPU runs slower, clear out any leftover instructions
-                loop
+                if ctx.keyboard.is_key_just_pressed(ggez::input::keyboard::KeyCode::F)
                 {
-                    self.clock_tick();
-                    if !self.cpu.as_mut().unwrap().lock().unwrap().complete()
-                    {
-                        break;
-                    }
+                    self.step_frame();
                 }
-
-                self.ppu.as_mut().unwrap().lock().unwrap().set_frame_complete(false);
             }
         }
 
+        self.queue_audio();
+
         if ctx.keyboard.is_key_just_pressed(ggez::input::keyboard::KeyCode::R)
         {
             self.reset();
@@ -406,153 +519,154 @@ pub fn emulator_update_without_audio(&mut self, ctx: &mut Context) -> GameResult
             self.emulation_run = !self.emulation_run;
         }
 
+        self.process_save_state_input(ctx);
+        self.process_recording_input(ctx);
+        self.process_vgm_log_input(ctx);
+        self.process_channel_input(ctx);
+
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Tab)
+        {
+            self.show_audio_panel = !self.show_audio_panel;
+        }
         self.process_controller_input(ctx);
+        self.process_battery_flush(ctx);
         
         Ok(())
     }
 
-    pub fn emulator_update_with_audio(&mut self, ctx: &mut Context) -> GameResult
+    // Hands this update's audio to the sound engine and adjusts the rate the next frame is
+    // resampled at, so the queue stays near its target however the frame loop is paced
+    fn queue_audio(&mut self)
     {
-        if ctx.keyboard.is_key_just_pressed(ggez::input::keyboard::KeyCode::R)
+        let samples = self.nes.audio_samples();
+
+        if let Some(recorder) = &mut self.recorder
         {
-            self.reset();
+            if let Err(e) = recorder.write_samples(&samples)
+            {
+                eprintln!("Failed to record audio: {}", e);
+                self.stop_recording();
+            }
         }
 
-        if ctx.keyboard.is_key_just_pressed(ggez::input::keyboard::KeyCode::Space)
+        if let Some(sound_engine) = &mut self.sound_engine
         {
-            self.emulation_run = !self.emulation_run;
+            // Can't fail, a full queue just drops samples
+            let _ = sound_engine.write_samples(&samples);
+            self.nes.set_audio_sample_rate(sound_engine.get_resampling_rate());
         }
-
-        self.process_controller_input(ctx);
-
-        // TODO: For testing, remove eventually
-        // self.sound_engine.lock().unwrap().vary_freq();
-        
-        Ok(())
     }
 
-    pub fn emulator_tick() -> bool
+    // The rate audio is produced at when nothing nudges it
+    fn get_audio_sample_rate(&self) -> u32
     {
-        MainState::get_instance().clock_tick()
+        match &self.sound_engine
+        {
+            Some(x) => x.get_sample_rate(),
+            None => Nes::DEFAULT_AUDIO_SAMPLE_RATE
+        }
     }
 
-}
+    // Recordings live next to the ROM, numbered so that none get overwritten
+    // (game.nes -> game.rec1.wav, game.rec2.wav, ...)
+    fn get_recording_path(&self, extension: &str) -> PathBuf
+    {
+        (1..).map(|n| PathBuf::from(&self.rom_path).with_extension(format!("rec{}.{}", n, extension)))
+            .find(|x| !x.exists())
+            .unwrap()
+    }
 
-impl Clockable for MainState
-{
-    fn clock_tick(&mut self) -> bool
+    // F12 starts recording what is played to a .wav file, and F12 again stops it
+    fn process_recording_input(&mut self, ctx: &mut Context)
     {
-        let clock_counter = self.bus.lock().unwrap().get_clock_counter();
+        if !ctx.keyboard.is_key_just_pressed(KeyCode::F12)
+        {
+            return;
+        }
 
-        self.ppu.as_mut().unwrap().lock().unwrap().clock_tick();
-        self.apu.as_mut().unwrap().lock().unwrap().clock_tick();
+        if self.recorder.is_some()
+        {
+            self.stop_recording();
+            return;
+        }
 
-        if clock_counter % 3 == 0
+        let path = self.get_recording_path("wav");
+        match WavWriter::create(&path, self.get_audio_sample_rate())
         {
-            let mut bus = self.bus.lock().unwrap();
-            if bus.is_dma_transfer_in_progress()
+            Ok(x) =>
             {
-                let dma_info_ptr = bus.get_dma_info();
-                let mut dma_info = dma_info_ptr.lock().unwrap();
+                println!("Recording audio to {}", path.display());
+                self.recorder = Some(x);
+            },
+            Err(e) => eprintln!("Failed to start recording to {}: {}", path.display(), e)
+        }
+    }
 
-                if dma_info.is_sync_needed()
-                {
-                    // Since DMA transfer can only be initiated on an even clock cycle, we synchronize here
-                    if bus.get_clock_counter() % 2 == 1
-                    {
-                        dma_info.set_sync_needed(false);
-                    }
-                }
-                else
-                {
-                    // On even cycles, read from the bus (could be CPU, cart, etc.)
-                    // On odd cycles, write to the PPU
-                    if bus.get_clock_counter() % 2 == 0
-                    {
-                        let mut data: u8 = 0;
-                        bus.cpu_read((dma_info.get_page() as u16) << 8 | (dma_info.get_addr() as u16), &mut data);
-                        dma_info.set_data(data);
-                    }
-                    else
-                    {
-                        self.ppu.as_mut().unwrap().lock().unwrap().set_oam_memory_at_addr(dma_info.get_addr(), dma_info.get_data());
-                        let new_addr = dma_info.get_addr().wrapping_add(1);
-                        dma_info.set_addr(new_addr);
-
-                        if new_addr == 0x00
-                        {
-                            dma_info.set_transfer_in_progress(false);
-                            dma_info.set_sync_needed(true);
-                        }
-                    }
-                }
-            }
-            else
+    fn stop_recording(&mut self)
+    {
+        if let Some(recorder) = self.recorder.take()
+        {
+            let seconds = recorder.get_samples_written() as f32 / recorder.get_sample_rate() as f32;
+            match recorder.finish()
             {
-                drop(bus);
-                self.cpu.as_mut().unwrap().lock().unwrap().clock_tick();
+                Ok(_) => println!("Stopped recording after {:.1} seconds", seconds),
+                Err(e) => eprintln!("Failed to finish recording: {}", e)
             }
         }
+    }
 
-        // Synchronize with audio
-        let result = self.sound_engine.as_mut().unwrap().lock().unwrap().clock_tick();
+    // F11 starts logging the APU register writes to a .vgm file, and F11 again stops it
+    fn process_vgm_log_input(&mut self, ctx: &mut Context)
+    {
+        if !ctx.keyboard.is_key_just_pressed(KeyCode::F11)
+        {
+            return;
+        }
 
-        if self.ppu.as_mut().unwrap().lock().unwrap().get_nmi()
+        if self.vgm_log_path.is_some()
         {
-            self.ppu.as_mut().unwrap().lock().unwrap().set_nmi(false);
-            self.cpu.as_mut().unwrap().lock().unwrap().nmi();
+            self.stop_vgm_log();
+            return;
         }
 
-        self.bus.lock().unwrap().increment_clock_counter();
-        result
+        let path = self.get_recording_path("vgm");
+        println!("Logging APU writes to {}", path.display());
+        self.nes.get_bus().lock().unwrap().start_vgm_log();
+        self.vgm_log_path = Some(path);
     }
-}
 
-impl Resettable for MainState
-{
-    fn reset(&mut self)
+    fn stop_vgm_log(&mut self)
     {
-        // Reset the main bus
-        self.bus.lock().unwrap().reset();
-
-        // Reset the CPU
-        self.cpu.as_mut().unwrap().lock().unwrap().reset();
-
-        // Reset the PPU
-        self.ppu.as_mut().unwrap().lock().unwrap().reset();
+        let path = match self.vgm_log_path.take()
+        {
+            Some(x) => x,
+            None => return
+        };
 
-        // Reset the APU
-        self.apu.as_mut().unwrap().lock().unwrap().reset();
+        if let Some(data) = self.nes.get_bus().lock().unwrap().stop_vgm_log()
+        {
+            match std::fs::write(&path, data)
+            {
+                Ok(_) => println!("Saved VGM log to {}", path.display()),
+                Err(e) => eprintln!("Failed to save VGM log to {}: {}", path.display(), e)
+            }
+        }
     }
 }
 
-struct EventHandlingState
-{
-
-}
-
-impl EventHandlingState
+impl Resettable for MainState
 {
-    pub fn new() -> Self
+    fn reset(&mut self)
     {
-        EventHandlingState {  }
+        self.nes.reset();
     }
 }
 
-impl event::EventHandler<ggez::GameError> for EventHandlingState
+impl event::EventHandler<ggez::GameError> for MainState
 {
     fn update(&mut self, ctx: &mut Context) -> GameResult
     {
-        
-
-        if MainState::get_instance().audio_thread_emulation_tick
-        {
-            MainState::get_instance().emulator_update_with_audio(ctx)
-        }
-        else
-        {
-            MainState::get_instance().emulator_update_without_audio(ctx)
-        }
+        self.emulator_update(ctx)
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult
@@ -562,36 +676,127 @@ fn draw(&mut self, ctx: &mut Context) -> GameResult
             graphics::Color::from([0.0, 0.0, 0.0, 1.0]),
         );
 
-        // Zero page
-        MainState::draw_cpu_ram(MainState::get_instance(), 10, 750, 0x0000, 16, 16, &mut canvas);
-        MainState::draw_notes(MainState::get_instance(), 775.0, 750.0, &mut canvas);
-        MainState::draw_cpu(MainState::get_instance(), 775.0, 2.0, &mut canvas);
-        MainState::draw_code(MainState::get_instance(), 775.0, 100.0, 26, &mut canvas);
-        MainState::draw_oam(MainState::get_instance(), 1175.0, 100.0, 26, &mut canvas);
-        MainState::draw_perf(MainState::get_instance(), 775.0, 800.0, ctx, &mut canvas);
-        MainState::get_instance().ppu.as_ref().unwrap().lock().unwrap().render(ctx, &mut canvas, 3.0);
+        if self.show_debug_panels
+        {
+            let (panel_x, panel_y) = self.get_panel_origin();
+
+            // Zero page, or the audio channels in its place
+            if self.show_audio_panel
+            {
+                self.draw_audio(10.0, panel_y, ctx, &mut canvas)?;
+            }
+            else
+            {
+                self.draw_cpu_ram(10, panel_y as i32, 0x0000, 16, 16, &mut canvas);
+            }
+            self.draw_cpu(panel_x, 2.0, &mut canvas);
+            self.draw_code(panel_x, 100.0, 26, &mut canvas);
+            self.draw_oam(panel_x + 400.0, 100.0, 26, &mut canvas);
+            self.draw_perf(panel_x, panel_y + 50.0, ctx, &mut canvas);
+            self.nes.get_ppu().lock().unwrap().render_pattern_tables(ctx, &mut canvas, panel_x, 500.0, self.render_scale);
+        }
+
+        self.nes.get_ppu().lock().unwrap().render(ctx, &mut canvas, self.render_scale);
         canvas.finish(ctx)?;
         Ok(())
     }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, ggez::GameError>
+    {
+        self.flush_battery_ram();
+        self.stop_recording();
+        self.stop_vgm_log();
+
+        // Carry on quitting
+        Ok(false)
+    }
 }
 
 fn main() -> GameResult
 {
+    let options = match Command::parse(std::env::args().skip(1))
+    {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Nestest(options)) =>
+        {
+            match nestest::run(&options.rom_path, &options.log_path)
+            {
+                Ok(lines) =>
+                {
+                    println!("nestest: all {} lines match {}", lines, options.log_path);
+                    return Ok(());
+                },
+                Err(x) =>
+                {
+                    eprintln!("nestest: {}", x);
+                    std::process::exit(1);
+                }
+            }
+        },
+        Ok(Command::Record(options)) =>
+        {
+            match audio_capture::run(&options.rom_path, &options.wav_path, options.frames, options.sample_rate)
+            {
+                Ok(samples) =>
+                {
+                    println!("record: wrote {} samples to {}", samples, options.wav_path);
+                    return Ok(());
+                },
+                Err(x) =>
+                {
+                    eprintln!("record: {}", x);
+                    std::process::exit(1);
+                }
+            }
+        },
+        Ok(Command::Help) =>
+        {
+            println!("{}", Command::USAGE);
+            return Ok(());
+        },
+        Err(x) =>
+        {
+            eprintln!("error: {}\n\n{}", x, Command::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let cart = match Cart::new(options.rom_path.clone())
+    {
+        Ok(x) => x,
+        Err(x) =>
+        {
+            eprintln!("error: failed to load cartridge '{}': {}", options.rom_path, x);
+            std::process::exit(1);
+        }
+    };
+
+    let mut main_state = MainState::new(&options, cart);
+
+    // The debug panels sit to the right of and below the emulated screen
+    let (mut width, mut height) = (SCREEN_COLS as f32 * options.scale, SCREEN_ROWS as f32 * options.scale);
+    if options.debug_panels
+    {
+        let (panel_x, panel_y) = main_state.get_panel_origin();
+        width = panel_x + 665.0;
+        height = height.max(panel_y + 330.0);
+    }
+
     let (ctx, event_loop) = ggez::ContextBuilder::new("singularity-emu", "jsmrcina")
         .window_setup(ggez::conf::WindowSetup::default().title("Singularity Emu"))
-        .window_mode(ggez::conf::WindowMode::default().dimensions(1440.0, 1080.0))
+        .window_mode(ggez::conf::WindowMode::default().dimensions(width, height))
         .build()?;
 
-    let main_state = MainState::get_instance();
-    let stream = SoundEngine::initialize(main_state.sound_engine.as_mut().unwrap().clone());
+    if let Some(sound_engine) = &mut main_state.sound_engine
+    {
+        let stream = sound_engine.initialize();
 
-    // Tell the APU what the sample rate is
-    main_state.apu.as_mut().unwrap().lock().unwrap().set_oscillator_sample_rate(
-        main_state.sound_engine.as_ref().unwrap().lock().unwrap().get_oscillator_sample_rate());
+        // Produce audio at the rate the output device expects
+        main_state.nes.set_audio_sample_rate(sound_engine.get_sample_rate());
 
-    stream.play().unwrap();
-    main_state.sound_thread = Some(stream);
+        stream.play().unwrap();
+        main_state.sound_thread = Some(stream);
+    }
 
-    let event_handling_state = EventHandlingState::new();
-    event::run(ctx, event_loop, event_handling_state);
+    event::run(ctx, event_loop, main_state);
 }
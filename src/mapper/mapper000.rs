@@ -1,26 +1,52 @@
-use crate::traits::MapperTrait;
+use std::io::{self, Read, Write};
+
+use crate::cartridge::cart::MirrorMode;
+use crate::traits::{MapperTrait, Saveable};
 
 pub struct Mapper000
 {
     prg_banks: u16,
-    _chr_banks: u16
+    _chr_banks: u16,
+    mirror_mode: MirrorMode
 }
 
 impl Mapper000
 {
-    pub fn new(prg_banks: u16, chr_banks: u16) -> Self
+    pub fn new(prg_banks: u16, chr_banks: u16, mirror_mode: MirrorMode) -> Self
     {
         Mapper000 {
             prg_banks,
-            _chr_banks: chr_banks
+            _chr_banks: chr_banks,
+            mirror_mode
         }
     }
 }
 
+impl Saveable for Mapper000
+{
+    fn save_state(&self, _: &mut dyn Write) -> io::Result<()>
+    {
+        // No bank registers
+        Ok(())
+    }
+
+    fn load_state(&mut self, _: &mut dyn Read) -> io::Result<()>
+    {
+        Ok(())
+    }
+}
+
 impl MapperTrait for Mapper000
 {
     fn cpu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool
     {
+        if (0x6000..=0x7FFF).contains(&address)
+        {
+            // PRG RAM (Family Basic), boards without it ignore the access
+            *mapped_addr = address as u32 & 0x1FFF;
+            return true;
+        }
+
         if address >= 0x8000
         {
             let mask: u16 = if self.prg_banks == 1
@@ -41,6 +67,12 @@ fn cpu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool
 
     fn cpu_map_write(&mut self, address: u16, mapped_addr: &mut u32, _: u8) -> bool
     {
+        if (0x6000..=0x7FFF).contains(&address)
+        {
+            *mapped_addr = address as u32 & 0x1FFF;
+            return true;
+        }
+
         if address >= 0x8000
         {
             let mask: u16 = if self.prg_banks == 1
@@ -75,6 +107,12 @@ fn ppu_map_write(&mut self, _: u16, _: &mut u32, _: u8) -> bool
         false
     }
 
+    fn mirror_mode(&self) -> MirrorMode
+    {
+        // Hardwired on the board
+        self.mirror_mode
+    }
+
     fn reset(&mut self)
     {
         // Does nothing
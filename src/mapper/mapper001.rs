@@ -0,0 +1,272 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::cartridge::cart::MirrorMode;
+use crate::traits::{MapperTrait, Saveable, MAPPED_INTERNALLY};
+
+// MMC1, based on https://www.nesdev.org/wiki/MMC1
+//
+// The registers are written one bit at a time through a 5-bit shift register, the fifth
+// write picks the register by the address it was written to:
+// $8000-$9FFF control, $A000-$BFFF CHR bank 0, $C000-$DFFF CHR bank 1, $E000-$FFFF PRG bank
+pub struct Mapper001
+{
+    prg_banks: u16,
+    chr_banks: u16,
+    shift_register: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    // CPU cycles since the last write to the shift register, saturating. A write on the cycle
+    // right after another one is ignored, which is what read-modify-write instructions do.
+    cycles_since_write: u8
+}
+
+impl Mapper001
+{
+    pub fn new(prg_banks: u16, chr_banks: u16) -> Self
+    {
+        let mut s = Mapper001 {
+            prg_banks,
+            chr_banks,
+            shift_register: 0,
+            shift_count: 0,
+            control: 0,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            cycles_since_write: u8::MAX
+        };
+
+        s.reset();
+        s
+    }
+
+    // Boards with 512KB of PRG (SUROM) use bit 4 of the CHR bank register to pick which
+    // 256KB half the PRG bank register switches within
+    fn get_prg_outer_bank(&self) -> u32
+    {
+        if self.prg_banks > 16
+        {
+            (self.chr_bank_0 & 0x10) as u32
+        }
+        else
+        {
+            0
+        }
+    }
+
+    // The 16KB PRG bank mapped at $8000 (upper == false) or $C000 (upper == true)
+    fn get_prg_bank(&self, upper: bool) -> u32
+    {
+        let bank = self.prg_bank as u32 & 0x0F;
+        let outer = self.get_prg_outer_bank();
+        let last = (self.prg_banks as u32 - 1) & 0x0F;
+
+        let selected = match (self.control >> 2) & 0x03
+        {
+            // Switch 32KB at $8000, ignoring the low bit of the bank number
+            0 | 1 => (bank & 0x0E) | upper as u32,
+            // Fix the first bank at $8000 and switch 16KB at $C000
+            2 => if upper { bank } else { 0 },
+            // Fix the last bank at $C000 and switch 16KB at $8000
+            3 => if upper { last } else { bank },
+            _ => panic!("Impossible")
+        };
+
+        (outer | selected) % self.prg_banks as u32
+    }
+
+    // The 4KB CHR bank mapped at $0000 (upper == false) or $1000 (upper == true)
+    fn get_chr_bank(&self, upper: bool) -> u32
+    {
+        let bank = if self.control & 0x10 == 0x10
+        {
+            // Two separate 4KB banks
+            if upper { self.chr_bank_1 } else { self.chr_bank_0 }
+        }
+        else
+        {
+            // One 8KB bank, ignoring the low bit of the bank number
+            (self.chr_bank_0 & 0x1E) | upper as u8
+        };
+
+        // CHR RAM counts as a single 8KB bank
+        let chr_4k_banks = (self.chr_banks.max(1) as u32) * 2;
+        bank as u32 % chr_4k_banks
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool
+    {
+        self.prg_bank & 0x10 == 0
+    }
+
+    fn write_register(&mut self, address: u16, data: u8)
+    {
+        match address
+        {
+            0x8000..=0x9FFF => self.control = data,
+            0xA000..=0xBFFF => self.chr_bank_0 = data,
+            0xC000..=0xDFFF => self.chr_bank_1 = data,
+            0xE000..=0xFFFF => self.prg_bank = data,
+            _ => panic!("Invalid MMC1 register address {:04X}", address)
+        }
+    }
+
+    fn write_shift_register(&mut self, address: u16, data: u8)
+    {
+        if data & 0x80 == 0x80
+        {
+            // Writing a value with bit 7 set clears the shift register and locks
+            // the PRG banking back to mode 3 (last bank fixed at $C000)
+            self.shift_register = 0;
+            self.shift_count = 0;
+            self.control |= 0x0C;
+        }
+        else
+        {
+            self.shift_register = (self.shift_register >> 1) | ((data & 0x01) << 4);
+            self.shift_count += 1;
+
+            if self.shift_count == 5
+            {
+                self.write_register(address, self.shift_register);
+                self.shift_register = 0;
+                self.shift_count = 0;
+            }
+        }
+    }
+
+    fn map_chr(&self, address: u16) -> u32
+    {
+        self.get_chr_bank(address >= 0x1000) * 0x1000 + (address as u32 & 0x0FFF)
+    }
+}
+
+impl Saveable for Mapper001
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u8(self.shift_register)?;
+        writer.write_u8(self.shift_count)?;
+        writer.write_u8(self.control)?;
+        writer.write_u8(self.chr_bank_0)?;
+        writer.write_u8(self.chr_bank_1)?;
+        writer.write_u8(self.prg_bank)?;
+        writer.write_u8(self.cycles_since_write)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.shift_register = reader.read_u8()?;
+        self.shift_count = reader.read_u8()?;
+        self.control = reader.read_u8()?;
+        self.chr_bank_0 = reader.read_u8()?;
+        self.chr_bank_1 = reader.read_u8()?;
+        self.prg_bank = reader.read_u8()?;
+        self.cycles_since_write = reader.read_u8()?;
+        Ok(())
+    }
+}
+
+impl MapperTrait for Mapper001
+{
+    fn cpu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool
+    {
+        if (0x6000..=0x7FFF).contains(&address)
+        {
+            // PRG RAM
+            *mapped_addr = address as u32 & 0x1FFF;
+            return self.is_prg_ram_enabled();
+        }
+
+        if address >= 0x8000
+        {
+            *mapped_addr = self.get_prg_bank(address >= 0xC000) * 0x4000 + (address as u32 & 0x3FFF);
+            return true;
+        }
+
+        false
+    }
+
+    fn cpu_map_write(&mut self, address: u16, mapped_addr: &mut u32, data: u8) -> bool
+    {
+        if (0x6000..=0x7FFF).contains(&address)
+        {
+            // PRG RAM, writes while it is disabled are dropped
+            *mapped_addr = if self.is_prg_ram_enabled() { address as u32 & 0x1FFF } else { MAPPED_INTERNALLY };
+            return true;
+        }
+
+        if address >= 0x8000
+        {
+            // Only the first of two back-to-back writes gets through
+            if self.cycles_since_write >= 2
+            {
+                self.write_shift_register(address, data);
+            }
+
+            self.cycles_since_write = 0;
+
+            // Register writes never reach the ROM
+            *mapped_addr = MAPPED_INTERNALLY;
+            return true;
+        }
+
+        false
+    }
+
+    fn ppu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool
+    {
+        if address <= 0x1FFF
+        {
+            *mapped_addr = self.map_chr(address);
+            return true;
+        }
+
+        false
+    }
+
+    fn ppu_map_write(&mut self, address: u16, mapped_addr: &mut u32, _: u8) -> bool
+    {
+        // Only CHR RAM can be written to
+        if address <= 0x1FFF && self.chr_banks == 0
+        {
+            *mapped_addr = self.map_chr(address);
+            return true;
+        }
+
+        false
+    }
+
+    fn mirror_mode(&self) -> MirrorMode
+    {
+        match self.control & 0x03
+        {
+            0 => MirrorMode::OneScreenLo,
+            1 => MirrorMode::OneScreenHi,
+            2 => MirrorMode::Vertical,
+            3 => MirrorMode::Horizontal,
+            _ => panic!("Impossible")
+        }
+    }
+
+    // cpu_clock runs before the CPU's access on each cycle, so a write on the next cycle sees 1
+    fn cpu_clock(&mut self)
+    {
+        self.cycles_since_write = self.cycles_since_write.saturating_add(1);
+    }
+
+    fn reset(&mut self)
+    {
+        self.shift_register = 0;
+        self.shift_count = 0;
+        self.control = 0x1C;
+        self.chr_bank_0 = 0;
+        self.chr_bank_1 = 0;
+        self.prg_bank = 0;
+    }
+}
@@ -1,30 +1,60 @@
-use crate::traits::MapperTrait;
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::cartridge::cart::MirrorMode;
+use crate::traits::{MapperTrait, Saveable, MAPPED_INTERNALLY};
 
 pub struct Mapper002
 {
     prg_bank_selection_lo: u8,
     prg_bank_selection_hi: u8,
     prg_banks: u16,
-    chr_banks: u16
+    chr_banks: u16,
+    mirror_mode: MirrorMode
 }
 
 impl Mapper002
 {
-    pub fn new(prg_banks: u16, chr_banks: u16) -> Self
+    pub fn new(prg_banks: u16, chr_banks: u16, mirror_mode: MirrorMode) -> Self
     {
         Mapper002 {
             prg_bank_selection_lo: 0,
             prg_bank_selection_hi: 0,
             prg_banks,
-            chr_banks
+            chr_banks,
+            mirror_mode
         }
     }
 }
 
+impl Saveable for Mapper002
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u8(self.prg_bank_selection_lo)?;
+        writer.write_u8(self.prg_bank_selection_hi)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.prg_bank_selection_lo = reader.read_u8()?;
+        self.prg_bank_selection_hi = reader.read_u8()?;
+        Ok(())
+    }
+}
+
 impl MapperTrait for Mapper002
 {
     fn cpu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool
     {
+        if (0x6000..=0x7FFF).contains(&address)
+        {
+            // PRG RAM, boards without it ignore the access
+            *mapped_addr = address as u32 & 0x1FFF;
+            return true;
+        }
+
         if (0x8000..=0xBFFF).contains(&address)
         {
             *mapped_addr = self.prg_bank_selection_lo as u32 * 0x4000 + (address as u32 & 0x3FFF);
@@ -40,11 +70,18 @@ fn cpu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool
         false
     }
 
-    fn cpu_map_write(&mut self, address: u16, _: &mut u32, data: u8) -> bool
+    fn cpu_map_write(&mut self, address: u16, mapped_addr: &mut u32, data: u8) -> bool
     {
+        if (0x6000..=0x7FFF).contains(&address)
+        {
+            *mapped_addr = address as u32 & 0x1FFF;
+            return true;
+        }
+
         if (0x8000..=0xFFFF).contains(&address)
         {
             self.prg_bank_selection_lo = data & 0x0F;
+            *mapped_addr = MAPPED_INTERNALLY;
             return true;
         } 
 
@@ -75,6 +112,12 @@ fn ppu_map_write(&mut self, address: u16, mapped_addr: &mut u32, _: u8) -> bool
         false
     }
 
+    fn mirror_mode(&self) -> MirrorMode
+    {
+        // Hardwired on the board
+        self.mirror_mode
+    }
+
     fn reset(&mut self)
     {
         self.prg_bank_selection_lo = 0;
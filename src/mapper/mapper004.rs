@@ -0,0 +1,313 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::cartridge::cart::MirrorMode;
+use crate::savestate;
+use crate::traits::{MapperTrait, Saveable, MAPPED_INTERNALLY};
+
+// MMC3, based on https://www.nesdev.org/wiki/MMC3
+//
+// Registers come in even/odd address pairs within each 8KB range:
+// $8000 bank select     $8001 bank data
+// $A000 mirroring       $A001 PRG RAM protect
+// $C000 IRQ latch       $C001 IRQ reload
+// $E000 IRQ disable     $E001 IRQ enable
+//
+// The scanline counter is clocked by rising edges of PPU address line A12, which happens
+// once per scanline when backgrounds and sprites use different pattern tables.
+pub struct Mapper004
+{
+    prg_banks: u16,
+    chr_banks: u16,
+    bank_select: u8,
+    bank_registers: [u8; 8],
+    mirroring: u8,
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_active: bool,
+    a12_high: bool,
+    a12_low_cycles: u8
+}
+
+impl Mapper004
+{
+    // A12 has to stay low for this many CPU cycles before a rise clocks the counter. With
+    // backgrounds at $1000 this filters out the rises between tiles and at the start of the
+    // scanline, only the rise after the sprite fetches from $0000 gets counted. The hardware
+    // needs 3, but the PPU here leaves A12 low a little longer around dot 340.
+    const A12_LOW_CYCLES_BEFORE_RISE: u8 = 5;
+
+    pub fn new(prg_banks: u16, chr_banks: u16) -> Self
+    {
+        let mut s = Mapper004 {
+            prg_banks,
+            chr_banks,
+            bank_select: 0,
+            bank_registers: [0; 8],
+            mirroring: 0,
+            prg_ram_protect: 0,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_active: false,
+            a12_high: false,
+            a12_low_cycles: 0
+        };
+
+        s.reset();
+        s
+    }
+
+    // The 8KB PRG bank mapped at $8000, $A000, $C000 or $E000 (slot 0 to 3)
+    fn get_prg_bank(&self, slot: u16) -> u32
+    {
+        let prg_8k_banks = self.prg_banks as u32 * 2;
+        let second_last = prg_8k_banks - 2;
+        let last = prg_8k_banks - 1;
+        let swapped = self.bank_select & 0x40 == 0x40;
+
+        let bank = match slot
+        {
+            0 => if swapped { second_last } else { self.bank_registers[6] as u32 },
+            1 => self.bank_registers[7] as u32,
+            2 => if swapped { self.bank_registers[6] as u32 } else { second_last },
+            3 => last,
+            _ => panic!("Impossible")
+        };
+
+        bank % prg_8k_banks
+    }
+
+    // The 1KB CHR bank mapped at $0000 + slot * $400
+    fn get_chr_bank(&self, slot: u16) -> u32
+    {
+        // With inversion the 2KB banks (R0, R1) go to $1000 and the 1KB banks (R2-R5) to $0000
+        let slot = if self.bank_select & 0x80 == 0x80 { slot ^ 0x04 } else { slot };
+
+        let bank = match slot
+        {
+            0..=3 => (self.bank_registers[(slot / 2) as usize] & 0xFE) | (slot & 0x01) as u8,
+            4..=7 => self.bank_registers[(slot - 2) as usize],
+            _ => panic!("Impossible")
+        };
+
+        // CHR RAM counts as a single 8KB bank
+        let chr_1k_banks = (self.chr_banks.max(1) as u32) * 8;
+        bank as u32 % chr_1k_banks
+    }
+
+    fn map_chr(&self, address: u16) -> u32
+    {
+        self.get_chr_bank(address / 0x0400) * 0x0400 + (address as u32 & 0x03FF)
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool
+    {
+        self.prg_ram_protect & 0x80 == 0x80
+    }
+
+    fn is_prg_ram_writable(&self) -> bool
+    {
+        self.is_prg_ram_enabled() && self.prg_ram_protect & 0x40 == 0
+    }
+
+    fn write_register(&mut self, address: u16, data: u8)
+    {
+        let odd = address & 0x01 == 0x01;
+        match (address, odd)
+        {
+            (0x8000..=0x9FFF, false) => self.bank_select = data,
+            (0x8000..=0x9FFF, true) => self.bank_registers[(self.bank_select & 0x07) as usize] = data,
+            (0xA000..=0xBFFF, false) => self.mirroring = data & 0x01,
+            (0xA000..=0xBFFF, true) => self.prg_ram_protect = data,
+            (0xC000..=0xDFFF, false) => self.irq_latch = data,
+            (0xC000..=0xDFFF, true) =>
+            {
+                // The counter is reloaded from the latch on the next clock
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            (0xE000..=0xFFFF, false) =>
+            {
+                // Disabling also acknowledges any pending interrupt
+                self.irq_enabled = false;
+                self.irq_active = false;
+            },
+            (0xE000..=0xFFFF, true) => self.irq_enabled = true,
+            _ => panic!("Invalid MMC3 register address {:04X}", address)
+        }
+    }
+
+    fn clock_scanline_counter(&mut self)
+    {
+        if self.irq_counter == 0 || self.irq_reload
+        {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        }
+        else
+        {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled
+        {
+            self.irq_active = true;
+        }
+    }
+}
+
+impl Saveable for Mapper004
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u8(self.bank_select)?;
+        writer.write_all(&self.bank_registers)?;
+        writer.write_u8(self.mirroring)?;
+        writer.write_u8(self.prg_ram_protect)?;
+        writer.write_u8(self.irq_latch)?;
+        writer.write_u8(self.irq_counter)?;
+        savestate::write_bool(writer, self.irq_reload)?;
+        savestate::write_bool(writer, self.irq_enabled)?;
+        savestate::write_bool(writer, self.irq_active)?;
+        savestate::write_bool(writer, self.a12_high)?;
+        writer.write_u8(self.a12_low_cycles)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.bank_select = reader.read_u8()?;
+        reader.read_exact(&mut self.bank_registers)?;
+        self.mirroring = reader.read_u8()?;
+        self.prg_ram_protect = reader.read_u8()?;
+        self.irq_latch = reader.read_u8()?;
+        self.irq_counter = reader.read_u8()?;
+        self.irq_reload = savestate::read_bool(reader)?;
+        self.irq_enabled = savestate::read_bool(reader)?;
+        self.irq_active = savestate::read_bool(reader)?;
+        self.a12_high = savestate::read_bool(reader)?;
+        self.a12_low_cycles = reader.read_u8()?;
+        Ok(())
+    }
+}
+
+impl MapperTrait for Mapper004
+{
+    fn cpu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool
+    {
+        if (0x6000..=0x7FFF).contains(&address)
+        {
+            // PRG RAM
+            *mapped_addr = address as u32 & 0x1FFF;
+            return self.is_prg_ram_enabled();
+        }
+
+        if address >= 0x8000
+        {
+            *mapped_addr = self.get_prg_bank((address - 0x8000) / 0x2000) * 0x2000 + (address as u32 & 0x1FFF);
+            return true;
+        }
+
+        false
+    }
+
+    fn cpu_map_write(&mut self, address: u16, mapped_addr: &mut u32, data: u8) -> bool
+    {
+        if (0x6000..=0x7FFF).contains(&address)
+        {
+            // PRG RAM, writes while it is disabled or write protected are dropped
+            *mapped_addr = if self.is_prg_ram_writable() { address as u32 & 0x1FFF } else { MAPPED_INTERNALLY };
+            return true;
+        }
+
+        if address >= 0x8000
+        {
+            self.write_register(address, data);
+
+            // Register writes never reach the ROM
+            *mapped_addr = MAPPED_INTERNALLY;
+            return true;
+        }
+
+        false
+    }
+
+    fn ppu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool
+    {
+        if address <= 0x1FFF
+        {
+            *mapped_addr = self.map_chr(address);
+            return true;
+        }
+
+        false
+    }
+
+    fn ppu_map_write(&mut self, address: u16, mapped_addr: &mut u32, _: u8) -> bool
+    {
+        // Only CHR RAM can be written to
+        if address <= 0x1FFF && self.chr_banks == 0
+        {
+            *mapped_addr = self.map_chr(address);
+            return true;
+        }
+
+        false
+    }
+
+    fn ppu_address(&mut self, address: u16)
+    {
+        let a12_high = address & 0x1000 == 0x1000;
+
+        if a12_high && !self.a12_high && self.a12_low_cycles >= Mapper004::A12_LOW_CYCLES_BEFORE_RISE
+        {
+            self.clock_scanline_counter();
+        }
+
+        if a12_high
+        {
+            self.a12_low_cycles = 0;
+        }
+
+        self.a12_high = a12_high;
+    }
+
+    fn cpu_clock(&mut self)
+    {
+        if !self.a12_high
+        {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+
+    fn irq_active(&self) -> bool
+    {
+        self.irq_active
+    }
+
+    fn mirror_mode(&self) -> MirrorMode
+    {
+        if self.mirroring == 0 { MirrorMode::Vertical } else { MirrorMode::Horizontal }
+    }
+
+    fn reset(&mut self)
+    {
+        self.bank_select = 0;
+        self.bank_registers = [0, 2, 4, 5, 6, 7, 0, 1];
+        self.mirroring = 0;
+        // PRG RAM starts enabled, not every game writes $A001 before using it
+        self.prg_ram_protect = 0x80;
+        self.irq_latch = 0;
+        self.irq_counter = 0;
+        self.irq_reload = false;
+        self.irq_enabled = false;
+        self.irq_active = false;
+        self.a12_high = false;
+        self.a12_low_cycles = 0;
+    }
+}
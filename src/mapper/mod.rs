@@ -1,3 +1,7 @@
 pub mod mapper000;
 
-pub mod mapper002;
\ No newline at end of file
+pub mod mapper001;
+
+pub mod mapper002;
+
+pub mod mapper004;
\ No newline at end of file
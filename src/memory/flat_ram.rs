@@ -0,0 +1,46 @@
+use crate::traits::CpuBus;
+
+// 64KB of RAM with nothing mapped into it, enough to run the CPU on its own
+pub struct FlatRam
+{
+    pub buffer: Vec<u8>
+}
+
+impl FlatRam
+{
+    pub fn new() -> Self
+    {
+        FlatRam
+        {
+            buffer: vec![0; 0x10000]
+        }
+    }
+
+    // Copies data in starting at address, e.g. a program or the vectors
+    pub fn load(&mut self, address: u16, data: &[u8])
+    {
+        let start = address as usize;
+        self.buffer[start..start + data.len()].copy_from_slice(data);
+    }
+}
+
+impl Default for FlatRam
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl CpuBus for FlatRam
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        self.buffer[address as usize]
+    }
+
+    fn write(&mut self, address: u16, data: u8)
+    {
+        self.buffer[address as usize] = data;
+    }
+}
\ No newline at end of file
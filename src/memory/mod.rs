@@ -1 +1,2 @@
+pub mod flat_ram;
 pub mod ram;
\ No newline at end of file
@@ -1,4 +1,6 @@
-use crate::traits::ReadWrite;
+use std::io::{self, Read, Write};
+
+use crate::traits::{ReadWrite, Saveable};
 
 pub struct Ram
 {
@@ -60,4 +62,17 @@ fn ppu_read(&self, _: u16, _: &mut u8) -> bool
     {
         panic!("CPU RAM canot be read from PPU");
     }
+}
+
+impl Saveable for Ram
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_all(&self.buffer)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        reader.read_exact(&mut self.buffer)
+    }
 }
\ No newline at end of file
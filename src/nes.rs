@@ -0,0 +1,455 @@
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::bus::main_bus::MainBus;
+use crate::cartridge::cart::Cart;
+use crate::cpu::cpu6502::Cpu6502;
+use crate::gfx::ppu2c02::Ppu2c02;
+use crate::rewind::Rewind;
+use crate::savestate;
+use crate::sound::apu2a03::Apu2a03;
+use crate::traits::{Clockable, ReadWrite, Resettable, Saveable};
+
+// The 2A03's CPU, on the console's bus
+pub type NesCpu = Cpu6502<Arc<Mutex<MainBus>>>;
+
+// The whole console, wired together without any window or audio device attached.
+// A frontend drives it either one clock at a time (clock_tick) or through the
+// step_* helpers, and pulls the picture and sound back out of it afterwards.
+pub struct Nes
+{
+    bus: Arc<Mutex<MainBus>>,
+    cpu: Arc<Mutex<NesCpu>>,
+    ppu: Arc<Mutex<Ppu2c02>>,
+    apu: Arc<Mutex<Apu2a03>>,
+    cartridge: Option<Arc<Mutex<Cart>>>,
+    audio_samples: Vec<f32>,
+    rewind: Option<Rewind>
+}
+
+impl Nes
+{
+    // PPU Clock Frequency, based on NTSC NES core frequency
+    pub const PPU_CLOCK_FREQUENCY: f64 = 5369318.0;
+    pub const DEFAULT_AUDIO_SAMPLE_RATE: u32 = 44100;
+
+    pub fn new() -> Self
+    {
+        let bus = Arc::new(Mutex::new(MainBus::new()));
+
+        let mut bus_lock = bus.lock().unwrap();
+        let ppu = bus_lock.get_ppu();
+        let apu = bus_lock.get_apu();
+        drop(bus_lock);
+
+        let cpu = Arc::new(Mutex::new(Cpu6502::new(Arc::clone(&bus))));
+
+        let mut s = Nes
+        {
+            bus,
+            cpu,
+            ppu,
+            apu,
+            cartridge: None,
+            audio_samples: Vec::new(),
+            rewind: None
+        };
+
+        s.set_audio_sample_rate(Nes::DEFAULT_AUDIO_SAMPLE_RATE);
+        s
+    }
+
+    pub fn insert_cartridge(&mut self, cartridge: Cart)
+    {
+        let cart_wrapper = Arc::new(Mutex::new(cartridge));
+        self.bus.lock().unwrap().insert_cartridge(Arc::clone(&cart_wrapper));
+        self.cartridge = Some(cart_wrapper);
+    }
+
+    pub fn get_bus(&self) -> Arc<Mutex<MainBus>>
+    {
+        Arc::clone(&self.bus)
+    }
+
+    pub fn get_cpu(&self) -> Arc<Mutex<NesCpu>>
+    {
+        Arc::clone(&self.cpu)
+    }
+
+    pub fn get_ppu(&self) -> Arc<Mutex<Ppu2c02>>
+    {
+        Arc::clone(&self.ppu)
+    }
+
+    pub fn get_apu(&self) -> Arc<Mutex<Apu2a03>>
+    {
+        Arc::clone(&self.apu)
+    }
+
+    pub fn get_cartridge(&self) -> Option<Arc<Mutex<Cart>>>
+    {
+        self.cartridge.as_ref().map(Arc::clone)
+    }
+
+    // Whether the inserted cartridge has battery-backed PRG RAM worth persisting
+    pub fn has_battery(&self) -> bool
+    {
+        self.cartridge.as_ref().is_some_and(|x| x.lock().unwrap().has_battery())
+    }
+
+    // Restores battery-backed PRG RAM, e.g. from a .sav file written earlier
+    pub fn load_battery_ram(&mut self, data: &[u8]) -> io::Result<()>
+    {
+        self.get_inserted_cartridge()?.lock().unwrap().set_prg_ram(data)
+    }
+
+    // The battery-backed PRG RAM if the game changed it since the last call, so that
+    // frontends only write it out when there is something new to save
+    pub fn take_battery_ram(&mut self) -> Option<Vec<u8>>
+    {
+        let cartridge = self.cartridge.as_ref()?;
+        let mut cartridge = cartridge.lock().unwrap();
+        if cartridge.has_battery() && cartridge.take_prg_ram_dirty()
+        {
+            Some(cartridge.get_prg_ram().to_vec())
+        }
+        else
+        {
+            None
+        }
+    }
+
+    // Audio is produced at this rate and collected until audio_samples() is called. It can be
+    // adjusted while running, e.g. to keep an audio queue from over or underflowing.
+    pub fn set_audio_sample_rate(&mut self, sample_rate: u32)
+    {
+        self.apu.lock().unwrap().set_sample_rate(sample_rate as f64);
+    }
+
+    // Runs until the CPU has finished an instruction: the one it's in the middle of, or the next
+    // one if it's between instructions. A jammed CPU never starts another instruction, so this
+    // returns straight away.
+    pub fn step_instruction(&mut self)
+    {
+        // Since the CPU runs slower, it can take a few clocks until it starts the next one
+        loop
+        {
+            let cpu = self.cpu.lock().unwrap();
+            if !cpu.complete() || cpu.is_jammed()
+            {
+                break;
+            }
+
+            drop(cpu);
+            self.clock_tick();
+        }
+
+        while !self.cpu.lock().unwrap().complete()
+        {
+            self.clock_tick();
+        }
+    }
+
+    // Runs until the PPU has produced a whole frame
+    pub fn step_frame(&mut self)
+    {
+        // The flag may still be set from a frame that nobody waited on, e.g. in a loaded state
+        self.ppu.lock().unwrap().set_frame_complete(false);
+
+        loop
+        {
+            self.clock_tick();
+            if self.ppu.lock().unwrap().frame_complete()
+            {
+                break;
+            }
+        }
+
+        self.ppu.lock().unwrap().set_frame_complete(false);
+    }
+
+    // RGBA8 pixels of the last rendered frame, SCREEN_COLS x SCREEN_ROWS
+    pub fn framebuffer(&self) -> Vec<u8>
+    {
+        self.ppu.lock().unwrap().get_screen_pixels().to_vec()
+    }
+
+    // Drains the audio produced since the last call, mono at the configured sample rate
+    pub fn audio_samples(&mut self) -> Vec<f32>
+    {
+        std::mem::take(&mut self.audio_samples)
+    }
+
+    // Snapshot of the whole machine in the format described in savestate.rs
+    pub fn save_state(&self) -> io::Result<Vec<u8>>
+    {
+        let cartridge = self.get_inserted_cartridge()?;
+        let cartridge = cartridge.lock().unwrap();
+
+        let mut data: Vec<u8> = Vec::new();
+        savestate::write_header(&mut data, cartridge.get_rom_hash())?;
+        self.write_components(&mut data, &cartridge)?;
+        Ok(data)
+    }
+
+    // Restores a snapshot made by save_state. If the snapshot turns out to be unusable
+    // part way through, the machine is put back the way it was before the call.
+    pub fn load_state(&mut self, data: &[u8]) -> io::Result<()>
+    {
+        let cartridge = self.get_inserted_cartridge()?;
+        let mut cartridge = cartridge.lock().unwrap();
+
+        let mut reader = data;
+        savestate::read_header(&mut reader, cartridge.get_rom_hash())?;
+
+        let mut backup: Vec<u8> = Vec::new();
+        self.write_components(&mut backup, &cartridge)?;
+
+        let result = match self.read_components(&mut reader, &mut cartridge)
+        {
+            Ok(()) if !reader.is_empty() => Err(io::Error::new(io::ErrorKind::InvalidData, "save state is longer than expected")),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(io::Error::new(io::ErrorKind::InvalidData, "save state is truncated")),
+            x => x
+        };
+
+        if let Err(e) = result
+        {
+            self.read_components(&mut backup.as_slice(), &mut cartridge)?;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    // Starts recording a snapshot every interval_frames frames, keeping the newest capacity of them
+    pub fn enable_rewind(&mut self, interval_frames: u32, capacity: usize)
+    {
+        self.rewind = Some(Rewind::new(interval_frames, capacity));
+    }
+
+    pub fn get_rewind(&self) -> Option<&Rewind>
+    {
+        self.rewind.as_ref()
+    }
+
+    // Goes back to the newest recorded snapshot and removes it from the history, then runs
+    // a frame from it so there is a picture to show. Returns false if there is no history.
+    pub fn rewind_step(&mut self) -> io::Result<bool>
+    {
+        // Taking the history out also keeps the frame below from being recorded
+        let mut rewind = match self.rewind.take()
+        {
+            Some(x) => x,
+            None => return Ok(false)
+        };
+
+        let result = match rewind.pop()
+        {
+            Some(snapshot) => self.load_state(&snapshot).map(|_|
+            {
+                self.step_frame();
+                self.audio_samples.clear();
+                true
+            }),
+            None => Ok(false)
+        };
+
+        self.rewind = Some(rewind);
+        result
+    }
+
+    fn record_rewind_snapshot(&mut self)
+    {
+        let due = match &mut self.rewind
+        {
+            Some(x) => x.frame_completed(),
+            None => false
+        };
+
+        if due
+        {
+            // Without a cartridge there is nothing worth recording
+            if let Ok(snapshot) = self.save_state()
+            {
+                self.rewind.as_mut().unwrap().push(snapshot);
+            }
+        }
+    }
+
+    fn get_inserted_cartridge(&self) -> io::Result<Arc<Mutex<Cart>>>
+    {
+        self.get_cartridge().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cartridge inserted"))
+    }
+
+    fn write_components(&self, writer: &mut dyn Write, cartridge: &Cart) -> io::Result<()>
+    {
+        self.bus.lock().unwrap().save_state(writer)?;
+        self.cpu.lock().unwrap().save_state(writer)?;
+        self.ppu.lock().unwrap().save_state(writer)?;
+        self.apu.lock().unwrap().save_state(writer)?;
+        cartridge.save_state(writer)
+    }
+
+    fn read_components(&mut self, reader: &mut dyn Read, cartridge: &mut Cart) -> io::Result<()>
+    {
+        self.bus.lock().unwrap().load_state(reader)?;
+        self.cpu.lock().unwrap().load_state(reader)?;
+        self.ppu.lock().unwrap().load_state(reader)?;
+        self.apu.lock().unwrap().load_state(reader)?;
+        cartridge.load_state(reader)
+    }
+}
+
+impl Default for Nes
+{
+    fn default() -> Self
+    {
+        Nes::new()
+    }
+}
+
+impl Clockable for Nes
+{
+    // Returns true when this tick produced a new audio sample
+    fn clock_tick(&mut self) -> bool
+    {
+        let clock_counter = self.bus.lock().unwrap().get_clock_counter();
+
+        let frame_completed = self.ppu.lock().unwrap().clock_tick();
+        let sample_ready = self.apu.lock().unwrap().clock_tick();
+
+        if clock_counter.is_multiple_of(3)
+        {
+            if let Some(cartridge) = &self.cartridge
+            {
+                cartridge.lock().unwrap().cpu_clock();
+            }
+
+            let mut bus = self.bus.lock().unwrap();
+            let dma_info_ptr = bus.get_dma_info();
+            let mut dma_info = dma_info_ptr.lock().unwrap();
+
+            // The DMC reads its samples over the CPU bus. That halts the CPU for 4 cycles, or 2
+            // when an OAM DMA is already holding it.
+            if !dma_info.is_dmc_fetch_in_progress()
+            {
+                if let Some(addr) = self.apu.lock().unwrap().get_dmc_sample_request()
+                {
+                    let stall_cycles = if dma_info.is_transfer_in_progress() { 2 } else { 4 };
+                    dma_info.start_dmc_fetch(addr, stall_cycles);
+                }
+            }
+
+            if dma_info.is_dmc_fetch_in_progress()
+            {
+                if dma_info.clock_dmc_stall()
+                {
+                    let mut data: u8 = 0;
+                    bus.cpu_read(dma_info.get_dmc_addr(), &mut data);
+                    self.apu.lock().unwrap().fill_dmc_sample_buffer(data);
+                }
+            }
+            else if dma_info.is_transfer_in_progress()
+            {
+                if dma_info.is_sync_needed()
+                {
+                    // Since DMA transfer can only be initiated on an even clock cycle, we synchronize here
+                    if bus.get_clock_counter() % 2 == 1
+                    {
+                        dma_info.set_sync_needed(false);
+                    }
+                }
+                else
+                {
+                    // On even cycles, read from the bus (could be CPU, cart, etc.)
+                    // On odd cycles, write to the PPU
+                    if bus.get_clock_counter().is_multiple_of(2)
+                    {
+                        let mut data: u8 = 0;
+                        bus.cpu_read((dma_info.get_page() as u16) << 8 | (dma_info.get_addr() as u16), &mut data);
+                        dma_info.set_data(data);
+                    }
+                    else
+                    {
+                        self.ppu.lock().unwrap().set_oam_memory_at_addr(dma_info.get_addr(), dma_info.get_data());
+                        let new_addr = dma_info.get_addr().wrapping_add(1);
+                        dma_info.set_addr(new_addr);
+
+                        if new_addr == 0x00
+                        {
+                            dma_info.set_transfer_in_progress(false);
+                            dma_info.set_sync_needed(true);
+                        }
+                    }
+                }
+            }
+            else
+            {
+                // The CPU goes back to the bus, which also owns the DMA registers
+                drop(dma_info);
+                drop(bus);
+
+                // The IRQ line is level triggered, it stays active for as long as the mapper or
+                // the APU holds it
+                let irq_active = self.cartridge.as_ref().is_some_and(|x| x.lock().unwrap().is_irq_active()) ||
+                    self.apu.lock().unwrap().is_irq_active();
+                let mut cpu = self.cpu.lock().unwrap();
+                cpu.set_irq_line(irq_active);
+                cpu.clock_tick();
+            }
+        }
+
+        // The APU resamples to the requested rate itself
+        if sample_ready
+        {
+            let final_mix = self.apu.lock().unwrap().get_final_mix();
+            self.audio_samples.push(final_mix as f32);
+        }
+
+        if self.ppu.lock().unwrap().get_nmi()
+        {
+            self.ppu.lock().unwrap().set_nmi(false);
+            self.cpu.lock().unwrap().nmi();
+        }
+
+        self.bus.lock().unwrap().increment_clock_counter();
+
+        if frame_completed
+        {
+            self.record_rewind_snapshot();
+        }
+
+        sample_ready
+    }
+}
+
+impl Resettable for Nes
+{
+    fn reset(&mut self)
+    {
+        // Reset the main bus
+        self.bus.lock().unwrap().reset();
+
+        // Reset the cartridge first, the CPU reads the reset vector through its mapper
+        if let Some(cartridge) = &self.cartridge
+        {
+            cartridge.lock().unwrap().reset();
+        }
+
+        // Reset the CPU
+        self.cpu.lock().unwrap().reset();
+
+        // Reset the PPU
+        self.ppu.lock().unwrap().reset();
+
+        // Reset the APU
+        self.apu.lock().unwrap().reset();
+
+        self.audio_samples.clear();
+
+        if let Some(rewind) = &mut self.rewind
+        {
+            rewind.clear();
+        }
+    }
+}
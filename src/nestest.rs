@@ -0,0 +1,264 @@
+use std::fmt;
+use std::fs;
+use std::io;
+
+use crate::cartridge::cart::Cart;
+use crate::nes::Nes;
+use crate::traits::{Clockable, Resettable};
+
+// Runs nestest in its automation mode (execution starting at $C000, no PPU needed) and compares
+// the state before every instruction against a known-good log in the Nintendulator format:
+//
+// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+//
+// The run stops at the first line that differs and reports which field went wrong first.
+
+pub const AUTOMATION_START_ADDRESS: u16 = 0xC000;
+
+// Column at which the register state starts, everything before it is the disassembly
+const STATE_COLUMN: usize = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceField
+{
+    Pc,
+    A,
+    X,
+    Y,
+    P,
+    Sp,
+    Ppu,
+    Cyc,
+    Disassembly,
+    Missing
+}
+
+impl fmt::Display for TraceField
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let name = match self
+        {
+            TraceField::Pc => "PC",
+            TraceField::A => "A",
+            TraceField::X => "X",
+            TraceField::Y => "Y",
+            TraceField::P => "P",
+            TraceField::Sp => "SP",
+            TraceField::Ppu => "PPU dot",
+            TraceField::Cyc => "CYC",
+            TraceField::Disassembly => "disassembly",
+            TraceField::Missing => "line"
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug)]
+pub struct TraceMismatch
+{
+    // 1-based, as shown by an editor
+    pub line: usize,
+    pub field: TraceField,
+    pub expected: String,
+    pub actual: String
+}
+
+impl fmt::Display for TraceMismatch
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        writeln!(f, "line {}: {} differs", self.line, self.field)?;
+        writeln!(f, "  expected: {}", self.expected)?;
+        write!(f, "  actual:   {}", self.actual)
+    }
+}
+
+#[derive(Debug)]
+pub enum NestestError
+{
+    Io(io::Error),
+    Mismatch(TraceMismatch)
+}
+
+impl fmt::Display for NestestError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            NestestError::Io(e) => write!(f, "{}", e),
+            NestestError::Mismatch(m) => write!(f, "{}", m)
+        }
+    }
+}
+
+impl From<io::Error> for NestestError
+{
+    fn from(e: io::Error) -> Self
+    {
+        NestestError::Io(e)
+    }
+}
+
+pub struct NestestRunner
+{
+    nes: Nes
+}
+
+impl NestestRunner
+{
+    pub fn new(rom_path: &str) -> io::Result<Self>
+    {
+        let mut nes = Nes::new();
+        nes.insert_cartridge(Cart::new(rom_path.to_string())?);
+        nes.reset();
+
+        // The reset sequence loads the PC from the reset vector, so it has to finish before the
+        // PC is moved
+        let mut runner = NestestRunner { nes };
+        while !runner.at_instruction_boundary()
+        {
+            runner.nes.clock_tick();
+        }
+
+        runner.nes.get_cpu().lock().unwrap().set_pc(AUTOMATION_START_ADDRESS);
+        Ok(runner)
+    }
+
+    // Runs the console up to the point where the CPU is about to fetch its next opcode
+    // and returns the trace line for that instruction
+    pub fn next_trace_line(&mut self) -> String
+    {
+        while !self.at_instruction_boundary()
+        {
+            self.nes.clock_tick();
+        }
+
+        let cpu = self.nes.get_cpu();
+        let mut cpu = cpu.lock().unwrap();
+        let pc = cpu.get_pc();
+        let disassembly = cpu.disassemble(pc, pc).remove(&pc).unwrap_or_default();
+        let ppu = self.nes.get_ppu();
+        let ppu = ppu.lock().unwrap();
+        let line = format!("{:<47} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:3},{:3} CYC:{}",
+            disassembly, cpu.get_a(), cpu.get_x(), cpu.get_y(), cpu.get_status(), cpu.get_stkp(),
+            ppu.get_scan_line(), ppu.get_cycle(), cpu.get_total_cycles());
+        drop(ppu);
+        drop(cpu);
+
+        // Step past the fetch so the next call moves on to the following instruction
+        self.nes.clock_tick();
+        line
+    }
+
+    // Compares against every line of the expected log, returning how many lines matched
+    pub fn compare(&mut self, expected_log: &str) -> Result<usize, TraceMismatch>
+    {
+        let mut count = 0;
+        for (index, expected) in expected_log.lines().enumerate()
+        {
+            let actual = self.next_trace_line();
+            if let Some(field) = first_mismatch(expected, &actual)
+            {
+                return Err(TraceMismatch
+                {
+                    line: index + 1,
+                    field,
+                    expected: expected.to_string(),
+                    actual
+                });
+            }
+
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn at_instruction_boundary(&self) -> bool
+    {
+        let bus = self.nes.get_bus();
+        let bus = bus.lock().unwrap();
+        bus.get_clock_counter().is_multiple_of(3) &&
+            !bus.is_dma_transfer_in_progress() &&
+            !bus.is_dmc_fetch_in_progress() &&
+            self.nes.get_cpu().lock().unwrap().complete()
+    }
+}
+
+// Loads the ROM and the expected log from disk and compares the two
+pub fn run(rom_path: &str, log_path: &str) -> Result<usize, NestestError>
+{
+    let expected_log = fs::read_to_string(log_path)?;
+    let mut runner = NestestRunner::new(rom_path)?;
+    runner.compare(&expected_log).map_err(NestestError::Mismatch)
+}
+
+// Finds the first field that differs between two trace lines. Fields are checked in order of
+// how useful they are for tracking down a bug: the PC first, then registers, then timing
+fn first_mismatch(expected: &str, actual: &str) -> Option<TraceField>
+{
+    let fields = [
+        (TraceField::Pc, field_pc as fn(&str) -> Option<&str>),
+        (TraceField::A, |l| field_value(l, "A:")),
+        (TraceField::X, |l| field_value(l, "X:")),
+        (TraceField::Y, |l| field_value(l, "Y:")),
+        (TraceField::P, |l| field_value(l, "P:")),
+        (TraceField::Sp, |l| field_value(l, "SP:")),
+        (TraceField::Ppu, field_ppu),
+        (TraceField::Cyc, |l| field_value(l, "CYC:")),
+        (TraceField::Disassembly, field_disassembly)
+    ];
+
+    for (field, extract) in fields
+    {
+        match (extract(expected), extract(actual))
+        {
+            (Some(e), Some(a)) if e == a => (),
+            (Some(_), _) => return Some(field),
+            // The expected line doesn't have this field, nothing to compare against
+            (None, _) => ()
+        }
+    }
+
+    if actual.is_empty()
+    {
+        return Some(TraceField::Missing);
+    }
+
+    None
+}
+
+fn field_pc(line: &str) -> Option<&str>
+{
+    line.get(0..4)
+}
+
+fn field_disassembly(line: &str) -> Option<&str>
+{
+    line.get(0..STATE_COLUMN.min(line.len())).map(str::trim_end)
+}
+
+fn state(line: &str) -> Option<&str>
+{
+    line.get(STATE_COLUMN..)
+}
+
+// Value of a "NAME:value" pair in the register state part of the line
+fn field_value<'a>(line: &'a str, name: &str) -> Option<&'a str>
+{
+    state(line)?
+        .split_whitespace()
+        .find_map(|token| token.strip_prefix(name))
+}
+
+// The PPU position is the only field with a space inside it ("PPU:  0, 21")
+fn field_ppu(line: &str) -> Option<&str>
+{
+    let state = state(line)?;
+    let start = state.find("PPU:")? + "PPU:".len();
+    let end = state.find(" CYC:")?;
+    state.get(start..end)
+}
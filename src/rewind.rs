@@ -0,0 +1,300 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// History of save states for rewinding. Only the newest snapshot is kept whole, every older
+// one is stored as the difference to the snapshot after it. Consecutive snapshots are mostly
+// identical, so each difference is the XOR of the two run-length encoded as:
+//
+// zero_run u16, literal_count u16, literal bytes... repeated until the snapshot is covered
+//
+// Going back one step XORs the newest snapshot with its difference, which yields the one
+// before it. Dropping the oldest snapshot is just dropping the oldest difference.
+pub struct Rewind
+{
+    interval_frames: u32,
+    frames_until_snapshot: u32,
+    capacity: usize,
+    latest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>
+}
+
+impl Rewind
+{
+    // Records a snapshot every interval_frames frames, keeping at most capacity of them
+    pub fn new(interval_frames: u32, capacity: usize) -> Self
+    {
+        Rewind
+        {
+            interval_frames: interval_frames.max(1),
+            frames_until_snapshot: 0,
+            capacity: capacity.max(1),
+            latest: None,
+            deltas: VecDeque::new()
+        }
+    }
+
+    // Called once per frame, returns true when a snapshot should be pushed for this frame
+    pub fn frame_completed(&mut self) -> bool
+    {
+        if self.frames_until_snapshot == 0
+        {
+            self.frames_until_snapshot = self.interval_frames - 1;
+            true
+        }
+        else
+        {
+            self.frames_until_snapshot -= 1;
+            false
+        }
+    }
+
+    pub fn push(&mut self, snapshot: Vec<u8>)
+    {
+        if let Some(latest) = self.latest.take()
+        {
+            if latest.len() == snapshot.len()
+            {
+                self.deltas.push_back(Rewind::encode_delta(&latest, &snapshot));
+            }
+            else
+            {
+                // The layout changed (e.g. another cartridge), the history is of no use anymore
+                self.deltas.clear();
+            }
+        }
+
+        self.latest = Some(snapshot);
+
+        while self.deltas.len() >= self.capacity
+        {
+            self.deltas.pop_front();
+        }
+    }
+
+    // Takes the newest snapshot out of the history. The oldest one is never removed, so
+    // holding rewind at the start of the history keeps returning it.
+    pub fn pop(&mut self) -> Option<Vec<u8>>
+    {
+        let latest = self.latest.as_ref()?;
+
+        match self.deltas.pop_back()
+        {
+            Some(delta) =>
+            {
+                let mut previous = latest.clone();
+                Rewind::apply_delta(&mut previous, &delta).expect("Rewind history is corrupt");
+                self.latest.replace(previous)
+            },
+            None => Some(latest.clone())
+        }
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.latest = None;
+        self.deltas.clear();
+        self.frames_until_snapshot = 0;
+    }
+
+    // Number of snapshots that can be stepped back through
+    pub fn len(&self) -> usize
+    {
+        match self.latest
+        {
+            Some(_) => self.deltas.len() + 1,
+            None => 0
+        }
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.latest.is_none()
+    }
+
+    // Bytes held by the history, for showing in the debug panels
+    pub fn memory_usage(&self) -> usize
+    {
+        self.latest.as_ref().map_or(0, |x| x.len()) + self.deltas.iter().map(|x| x.len()).sum::<usize>()
+    }
+
+    fn encode_delta(previous: &[u8], current: &[u8]) -> Vec<u8>
+    {
+        let mut delta: Vec<u8> = Vec::new();
+        let mut i = 0;
+
+        while i < current.len()
+        {
+            let zero_start = i;
+            while i < current.len() && i - zero_start < u16::MAX as usize && previous[i] == current[i]
+            {
+                i += 1;
+            }
+
+            let literal_start = i;
+            while i < current.len() && i - literal_start < u16::MAX as usize && previous[i] != current[i]
+            {
+                i += 1;
+            }
+
+            delta.write_u16::<LittleEndian>((literal_start - zero_start) as u16).unwrap();
+            delta.write_u16::<LittleEndian>((i - literal_start) as u16).unwrap();
+            delta.extend(previous[literal_start..i].iter().zip(&current[literal_start..i]).map(|(a, b)| a ^ b));
+        }
+
+        delta
+    }
+
+    fn apply_delta(snapshot: &mut [u8], mut delta: &[u8]) -> io::Result<()>
+    {
+        let mut i = 0;
+
+        while !delta.is_empty()
+        {
+            i += delta.read_u16::<LittleEndian>()? as usize;
+            let literal_count = delta.read_u16::<LittleEndian>()? as usize;
+
+            let mut literals = vec![0u8; literal_count];
+            delta.read_exact(&mut literals)?;
+
+            let target = snapshot.get_mut(i..i + literal_count)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "rewind delta is larger than the snapshot"))?;
+
+            for (byte, literal) in target.iter_mut().zip(literals)
+            {
+                *byte ^= literal;
+            }
+
+            i += literal_count;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // Each snapshot is the one before with a few scattered bytes and one block changed, the way
+    // consecutive save states mostly look
+    fn make_snapshots(count: usize, len: usize) -> Vec<Vec<u8>>
+    {
+        let mut snapshots = Vec::new();
+        let mut snapshot: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+        for n in 0..count
+        {
+            for i in (n % 13..len).step_by(97 + n)
+            {
+                snapshot[i] = snapshot[i].wrapping_add(n as u8 + 1);
+            }
+
+            let block = (n * 31) % (len / 2);
+            for byte in &mut snapshot[block..block + len / 8]
+            {
+                *byte ^= 0x5A;
+            }
+
+            snapshots.push(snapshot.clone());
+        }
+
+        snapshots
+    }
+
+    fn round_trip(previous: &[u8], current: &[u8])
+    {
+        let delta = Rewind::encode_delta(previous, current);
+        let mut restored = current.to_vec();
+        Rewind::apply_delta(&mut restored, &delta).unwrap();
+        assert!(restored == previous);
+    }
+
+    #[test]
+    fn delta_round_trips()
+    {
+        let snapshots = make_snapshots(8, 4096);
+        for pair in snapshots.windows(2)
+        {
+            round_trip(&pair[0], &pair[1]);
+        }
+
+        round_trip(&snapshots[0], &snapshots[0]);
+        round_trip(&[], &[]);
+    }
+
+    #[test]
+    fn delta_splits_runs_longer_than_u16()
+    {
+        let len = 200_000;
+        let previous = vec![0u8; len];
+
+        // Identical apart from the last byte, so the zero run has to be split
+        let mut current = previous.clone();
+        current[len - 1] = 1;
+        round_trip(&previous, &current);
+
+        // Every byte differs, so the literal run has to be split
+        let current = vec![0xFFu8; len];
+        round_trip(&previous, &current);
+    }
+
+    #[test]
+    fn pop_returns_each_snapshot_in_reverse()
+    {
+        let snapshots = make_snapshots(10, 4096);
+        let mut rewind = Rewind::new(1, 16);
+        for snapshot in &snapshots
+        {
+            rewind.push(snapshot.clone());
+        }
+
+        assert_eq!(rewind.len(), snapshots.len());
+        for snapshot in snapshots.iter().rev()
+        {
+            assert!(rewind.pop().unwrap() == *snapshot);
+        }
+
+        // The oldest one stays behind
+        assert_eq!(rewind.len(), 1);
+        assert!(rewind.pop().unwrap() == snapshots[0]);
+        assert!(rewind.pop().unwrap() == snapshots[0]);
+    }
+
+    #[test]
+    fn push_evicts_the_oldest_past_capacity()
+    {
+        let snapshots = make_snapshots(20, 2048);
+        let mut rewind = Rewind::new(1, 5);
+        for snapshot in &snapshots
+        {
+            rewind.push(snapshot.clone());
+        }
+
+        assert_eq!(rewind.len(), 5);
+        for snapshot in snapshots[15..].iter().rev()
+        {
+            assert!(rewind.pop().unwrap() == *snapshot);
+        }
+
+        assert!(rewind.pop().unwrap() == snapshots[15]);
+    }
+
+    #[test]
+    fn layout_change_resets_the_history()
+    {
+        let mut rewind = Rewind::new(1, 16);
+        rewind.push(vec![1; 64]);
+        rewind.push(vec![2; 64]);
+        rewind.push(vec![3; 128]);
+
+        assert_eq!(rewind.len(), 1);
+        assert_eq!(rewind.pop(), Some(vec![3; 128]));
+        assert_eq!(rewind.pop(), Some(vec![3; 128]));
+
+        rewind.clear();
+        assert!(rewind.is_empty());
+        assert_eq!(rewind.pop(), None);
+    }
+}
\ No newline at end of file
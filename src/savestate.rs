@@ -0,0 +1,70 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+// Save state layout (all values little endian):
+//
+// magic    [u8; 4]  "SNGS"
+// version  u32      VERSION below, states with any other version are rejected
+// rom_hash u64      fingerprint of the PRG/CHR ROM the state was taken with
+// body              each component's Saveable::save_state, in the order Nes writes them
+//
+// The body has no per-field tags, so VERSION must be bumped whenever any component adds,
+// removes or reorders the fields it saves.
+
+pub const MAGIC: [u8; 4] = *b"SNGS";
+pub const VERSION: u32 = 12;
+
+pub fn write_header(writer: &mut dyn Write, rom_hash: u64) -> io::Result<()>
+{
+    writer.write_all(&MAGIC)?;
+    writer.write_u32::<LittleEndian>(VERSION)?;
+    writer.write_u64::<LittleEndian>(rom_hash)
+}
+
+pub fn read_header(reader: &mut dyn Read, rom_hash: u64) -> io::Result<()>
+{
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC
+    {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a save state"));
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != VERSION
+    {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("save state version {} is not supported, expected version {}", version, VERSION)));
+    }
+
+    if reader.read_u64::<LittleEndian>()? != rom_hash
+    {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "save state was made with a different ROM"));
+    }
+
+    Ok(())
+}
+
+// 64-bit FNV-1a, stable across builds unlike std's DefaultHasher
+pub fn hash_rom(data: &[&[u8]]) -> u64
+{
+    let mut hash: u64 = 0xCBF29CE484222325;
+    for byte in data.iter().flat_map(|x| x.iter())
+    {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001B3);
+    }
+
+    hash
+}
+
+pub fn write_bool(writer: &mut dyn Write, value: bool) -> io::Result<()>
+{
+    writer.write_u8(value as u8)
+}
+
+pub fn read_bool(reader: &mut dyn Read) -> io::Result<bool>
+{
+    Ok(reader.read_u8()? != 0)
+}
@@ -1,114 +1,365 @@
-use crate::traits::{ReadWrite, Clockable, Resettable};
+use std::io::{self, Read, Write};
 
-use super::{sequencer::Sequencer, envelope::Envelope, oscillator::Oscillator, sound_length_counter::{SoundLengthCounter, self}, sweeper::Sweeper};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{ReadWrite, Clockable, Resettable, Saveable};
+
+use super::pulse::Pulse;
+use super::triangle::Triangle;
+use super::noise::Noise;
+use super::dmc::Dmc;
+use super::filter::{Filter, FilterType};
+use super::blip_buffer::BlipBuffer;
+use super::debug_info::ChannelDebugInfo;
+
+pub const CPU_CLOCK_FREQUENCY: f64 = 1789773.0;
+
+// Output rate until set_sample_rate says otherwise
+const DEFAULT_SAMPLE_RATE: f64 = 44100.0;
+
+// Frame counter steps in CPU cycles since the sequence started, from
+// https://www.nesdev.org/wiki/APU_Frame_Counter. The hardware steps fall between CPU
+// cycles (3728.5 etc.), these are rounded up to the cycle they take effect on.
+const FRAME_STEP_1: u32 = 7457;
+const FRAME_STEP_2: u32 = 14913;
+const FRAME_STEP_3: u32 = 22371;
+const FRAME_STEP_4: u32 = 29829;
+const FRAME_STEP_5: u32 = 37281;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel
+{
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc
+}
+
+impl Channel
+{
+    pub const ALL: [Channel; 5] = [Channel::Pulse1, Channel::Pulse2, Channel::Triangle, Channel::Noise, Channel::Dmc];
+
+    pub fn get_name(&self) -> &'static str
+    {
+        match self
+        {
+            Channel::Pulse1 => "Pulse 1",
+            Channel::Pulse2 => "Pulse 2",
+            Channel::Triangle => "Triangle",
+            Channel::Noise => "Noise",
+            Channel::Dmc => "DMC"
+        }
+    }
+}
 
 pub struct Apu2a03
 {
-    pulse_1_sample: f64,
-    pulse_1_halt: bool,
-    pulse_1_seq: Sequencer,
-    pulse_1_osc: Oscillator,
-    pulse_1_env: Envelope,
-    pulse_1_lc: SoundLengthCounter,
-    pulse_1_sweep: Sweeper,
-    pulse_1_freq: f64,
-    pulse_1_sp: f64,
-
-    pulse_2_sample: f64,
-    pulse_2_halt: bool,
-    pulse_2_seq: Sequencer,
-    pulse_2_osc: Oscillator,
-    pulse_2_env: Envelope,
-    pulse_2_lc: SoundLengthCounter,
-    pulse_2_sweep: Sweeper,
-    pulse_2_freq: f64,
-    pulse_2_sp: f64,
-
-    noise_sample: f64,
-    noise_halt: bool,
-    noise_seq: Sequencer,
-    noise_env: Envelope,
-    noise_lc: SoundLengthCounter,
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+
+    // Recent channel levels for debug views, a ring buffer written at the output rate
+    scope: Vec<[f32; 5]>,
+    scope_position: usize,
+
+    // Listening aids rather than machine state, so neither is saved. A muted channel is left
+    // out of the mix, and while any channel is soloed only soloed channels are heard.
+    muted: [bool; 5],
+    soloed: [bool; 5],
+
+    // The mixed channels go through the band-limited resampler, then the console's output
+    // stage at the host rate
+    blip: BlipBuffer,
+    high_pass_90: Filter,
+    high_pass_440: Filter,
+    low_pass_14k: Filter,
+    final_mix: f64,
 
     frame_clock_counter: u32,
+    frame_five_step: bool,
+    frame_irq_inhibit: bool,
+    frame_irq_active: bool,
+    frame_reset_delay: u8,
     clock_counter: u32
 }
 
 impl Apu2a03
 {
+    pub const SCOPE_LENGTH: usize = 1024;
+
     pub fn new() -> Self
     {
-        let mut s = Apu2a03
+        Apu2a03
         {
-            pulse_1_sample: 0.0,
-            pulse_1_halt: false,
-            pulse_1_seq: Sequencer::new(),
-            pulse_1_osc: Oscillator::new(),
-            pulse_1_env: Envelope::new(),
-            pulse_1_lc: SoundLengthCounter::new(),
-            pulse_1_sweep: Sweeper::new(),
-            pulse_1_freq: 0.0,
-            pulse_1_sp: 0.0,
-
-            pulse_2_sample: 0.0,
-            pulse_2_halt: false,
-            pulse_2_seq: Sequencer::new(),
-            pulse_2_osc: Oscillator::new(),
-            pulse_2_env: Envelope::new(),
-            pulse_2_lc: SoundLengthCounter::new(),
-            pulse_2_sweep: Sweeper::new(),
-            pulse_2_freq: 0.0,
-            pulse_2_sp: 0.0,
-
-            noise_sample: 0.0,
-            noise_halt: false,
-            noise_seq: Sequencer::new(),
-            noise_env: Envelope::new(),
-            noise_lc: SoundLengthCounter::new(),
+            pulse_1: Pulse::new(false),
+            pulse_2: Pulse::new(true),
+            triangle: Triangle::new(),
+            noise: Noise::new(),
+            dmc: Dmc::new(),
+
+            scope: vec![[0.0; 5]; Apu2a03::SCOPE_LENGTH],
+            scope_position: 0,
+
+            muted: [false; 5],
+            soloed: [false; 5],
+
+            blip: BlipBuffer::new(CPU_CLOCK_FREQUENCY, DEFAULT_SAMPLE_RATE),
+            high_pass_90: Filter::new(FilterType::HighPass, DEFAULT_SAMPLE_RATE, 90.0),
+            high_pass_440: Filter::new(FilterType::HighPass, DEFAULT_SAMPLE_RATE, 440.0),
+            low_pass_14k: Filter::new(FilterType::LowPass, DEFAULT_SAMPLE_RATE, 14000.0),
+            final_mix: 0.0,
 
             frame_clock_counter: 0,
+            frame_five_step: false,
+            frame_irq_inhibit: false,
+            frame_irq_active: false,
+            frame_reset_delay: 0,
             clock_counter: 0
-        };
-
-        // Initialize noise sequence
-        s.noise_seq.set_sequence(0x1);
-        s
+        }
     }
 
+    // The newest output sample, at the rate given to set_sample_rate. clock_tick returns
+    // true whenever there is a new one.
     pub fn get_final_mix(&mut self) -> f64
     {
-        (self.pulse_1_sample - 0.8) * 0.3 +
-			(self.pulse_2_sample - 0.8) * 0.3 +
-			2.0 * (self.noise_sample - 0.5) * 0.3
+        self.final_mix
     }
 
-    pub fn get_debug_info(&self) -> (f64, f64, f64, f64, f64)
+    // The channels run at the CPU rate no matter what, this only decides how often the
+    // resampler hands out samples. It can be changed at any time without a click.
+    pub fn set_sample_rate(&mut self, sample_rate: f64)
     {
-        (self.pulse_1_freq, self.pulse_1_sp, self.pulse_2_freq, self.pulse_2_sp, self.noise_sample)
+        self.blip.set_rates(CPU_CLOCK_FREQUENCY, sample_rate);
+        self.high_pass_90.set_sample_rate(sample_rate);
+        self.high_pass_440.set_sample_rate(sample_rate);
+        self.low_pass_14k.set_sample_rate(sample_rate);
+    }
+
+    // A channel's raw level before mixing, 0-15 for all but the DMC which is 0-127. Muting
+    // doesn't affect it.
+    pub fn get_channel_output(&self, channel: Channel) -> f64
+    {
+        match channel
+        {
+            Channel::Pulse1 => self.pulse_1.get_output(),
+            Channel::Pulse2 => self.pulse_2.get_output(),
+            Channel::Triangle => self.triangle.get_output(),
+            Channel::Noise => self.noise.get_output(),
+            Channel::Dmc => self.dmc.get_output() as f64
+        }
     }
 
-    pub fn set_oscillator_sample_rate(&mut self, osc_sample_rate: f64)
+    pub fn set_muted(&mut self, channel: Channel, muted: bool)
     {
-        self.pulse_1_osc.set_oscillator_sample_rate(osc_sample_rate);
-        self.pulse_2_osc.set_oscillator_sample_rate(osc_sample_rate);
+        self.muted[channel as usize] = muted;
     }
 
-    fn rotate_sequence(s: &mut u32, _: bool)
+    pub fn is_muted(&self, channel: Channel) -> bool
     {
-        *s = ((*s & 0x0001) << 7) | ((*s & 0x00FE) >> 1);
+        self.muted[channel as usize]
     }
 
-    fn noise_seq_update(s: &mut u32, mode: bool)
+    pub fn set_soloed(&mut self, channel: Channel, soloed: bool)
     {
-        if !mode
+        self.soloed[channel as usize] = soloed;
+    }
+
+    pub fn is_soloed(&self, channel: Channel) -> bool
+    {
+        self.soloed[channel as usize]
+    }
+
+    // Whether the channel currently makes it into the mix
+    pub fn is_audible(&self, channel: Channel) -> bool
+    {
+        let any_soloed = self.soloed.iter().any(|x| *x);
+        !self.muted[channel as usize] && (!any_soloed || self.soloed[channel as usize])
+    }
+
+    // The nonlinear DAC mix from https://www.nesdev.org/wiki/APU_Mixer. The pulses share one
+    // resistor network and the triangle, noise and DMC another, so neither group adds linearly.
+    // Inputs are the raw channel levels, 0-15 for all but the DMC which is 0-127.
+    fn mix(pulse_1: f64, pulse_2: f64, triangle: f64, noise: f64, dmc: f64) -> f64
+    {
+        let pulse_sum = pulse_1 + pulse_2;
+        let pulse_out = if pulse_sum > 0.0
         {
-            *s = (((*s & 0x0001) ^ ((*s & 0x0002) >> 1)) << 14) | ((*s & 0x7FFF) >> 1);
+            95.88 / (8128.0 / pulse_sum + 100.0)
         }
         else
         {
-            *s = (((*s & 0x0001) ^ ((*s & 0x0007) >> 1)) << 14) | ((*s & 0x7FFF) >> 1);
+            0.0
+        };
+
+        let tnd_sum = triangle / 8227.0 + noise / 12241.0 + dmc / 22638.0;
+        let tnd_out = if tnd_sum > 0.0
+        {
+            159.79 / (1.0 / tnd_sum + 100.0)
+        }
+        else
+        {
+            0.0
+        };
+
+        pulse_out + tnd_out
+    }
+
+    // Feeds this CPU cycle's level to the resampler, returns true if that finished a sample
+    fn update_final_mix(&mut self) -> bool
+    {
+        let levels = Channel::ALL.map(|x| self.get_channel_output(x));
+        let audible = Channel::ALL.map(|x| if self.is_audible(x) { levels[x as usize] } else { 0.0 });
+        let mix = Apu2a03::mix(audible[0], audible[1], audible[2], audible[3], audible[4]);
+        self.blip.set_amplitude(mix);
+
+        match self.blip.clock()
+        {
+            Some(sample) =>
+            {
+                self.scope[self.scope_position] = levels.map(|x| x as f32);
+                self.scope_position = (self.scope_position + 1) % Apu2a03::SCOPE_LENGTH;
+
+                let mut output = self.high_pass_90.process(sample);
+                output = self.high_pass_440.process(output);
+                self.final_mix = self.low_pass_14k.process(output);
+                true
+            },
+            None => false
+        }
+    }
+
+    // The DMC's memory reader can't reach the CPU bus by itself. Whoever drives the bus asks
+    // for the address here, stalls the CPU while reading it and passes the byte back.
+    pub fn get_dmc_sample_request(&self) -> Option<u16>
+    {
+        self.dmc.get_sample_request()
+    }
+
+    pub fn fill_dmc_sample_buffer(&mut self, data: u8)
+    {
+        self.dmc.fill_sample_buffer(data);
+    }
+
+    pub fn get_dmc_sample_range(&self) -> (u16, u16)
+    {
+        self.dmc.get_sample_range()
+    }
+
+    pub fn is_irq_active(&self) -> bool
+    {
+        self.frame_irq_active || self.dmc.is_irq_active()
+    }
+
+    pub fn get_channel_debug_info(&self, channel: Channel) -> ChannelDebugInfo
+    {
+        match channel
+        {
+            Channel::Pulse1 => self.pulse_1.get_debug_info(),
+            Channel::Pulse2 => self.pulse_2.get_debug_info(),
+            Channel::Triangle => self.triangle.get_debug_info(),
+            Channel::Noise => self.noise.get_debug_info(),
+            Channel::Dmc => self.dmc.get_debug_info()
+        }
+    }
+
+    // Each channel's raw level at the last SCOPE_LENGTH output samples, oldest first. Like
+    // get_channel_output, muting doesn't affect it.
+    pub fn get_scope(&self, channel: Channel) -> Vec<f32>
+    {
+        let (newer, older) = self.scope.split_at(self.scope_position);
+        older.iter().chain(newer.iter()).map(|x| x[channel as usize]).collect()
+    }
+
+    // Runs once per CPU cycle
+    fn clock_frame_counter(&mut self)
+    {
+        // A $4017 write restarts the sequence, and 5-step mode clocks everything right away
+        if self.frame_reset_delay > 0
+        {
+            self.frame_reset_delay -= 1;
+            if self.frame_reset_delay == 0
+            {
+                self.frame_clock_counter = 0;
+                if self.frame_five_step
+                {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+                return;
+            }
+        }
+
+        self.frame_clock_counter += 1;
+
+        match (self.frame_clock_counter, self.frame_five_step)
+        {
+            (FRAME_STEP_1, _) | (FRAME_STEP_3, _) =>
+            {
+                self.clock_quarter_frame();
+            },
+            (FRAME_STEP_2, _) =>
+            {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            },
+            (FRAME_STEP_4, false) =>
+            {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+                self.set_frame_irq();
+            },
+            (FRAME_STEP_5, true) =>
+            {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            },
+            _ => ()
+        }
+
+        // In 4-step mode the interrupt flag is asserted for the last step and the cycles either
+        // side of it, then the sequence wraps around
+        if !self.frame_five_step && self.frame_clock_counter >= FRAME_STEP_4 - 1
+        {
+            self.set_frame_irq();
+            if self.frame_clock_counter == FRAME_STEP_4 + 1
+            {
+                self.frame_clock_counter = 0;
+            }
+        }
+        else if self.frame_five_step && self.frame_clock_counter == FRAME_STEP_5 + 1
+        {
+            self.frame_clock_counter = 0;
+        }
+    }
+
+    fn set_frame_irq(&mut self)
+    {
+        if !self.frame_irq_inhibit
+        {
+            self.frame_irq_active = true;
         }
+    }
 
+    // Quarter frame beats adjust volume envelope
+    fn clock_quarter_frame(&mut self)
+    {
+        self.pulse_1.clock_quarter_frame();
+        self.pulse_2.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+    }
+
+    // Half frame beats adjust the note length and frequency sweepers
+    fn clock_half_frame(&mut self)
+    {
+        self.pulse_1.clock_half_frame();
+        self.pulse_2.clock_half_frame();
+        self.noise.clock_half_frame();
+        self.triangle.clock_half_frame();
     }
 }
 
@@ -126,115 +377,47 @@ fn cpu_write(&mut self, address: u16, data: u8) -> bool
         {
             0x4000 =>
             {
-                match (data & 0xC0) >> 6
-                {
-                    0x00 =>
-                    {
-                        self.pulse_1_seq.set_sequence(0b00000001);
-                        self.pulse_1_osc.set_duty_cycle(0.125);
-                    },
-                    0x01 =>
-                    {
-                        self.pulse_1_seq.set_sequence(0b00000011);
-                        self.pulse_1_osc.set_duty_cycle(0.250);
-                    },
-                    0x02 =>
-                    {
-                        self.pulse_1_seq.set_sequence(0b00001111);
-                        self.pulse_1_osc.set_duty_cycle(0.500);
-                    },
-                    0x03 =>
-                    {
-                        self.pulse_1_seq.set_sequence(0b11111100);
-                        self.pulse_1_osc.set_duty_cycle(0.750);
-                    },
-                    _ => panic!("Impossible")
-                }
-
-                self.pulse_1_halt = data & 0x20 == 0x20;
-                self.pulse_1_env.set_volume(data as u16 & 0x0F);
-                self.pulse_1_env.set_disable(data as u16 & 0x10 == 0x10);
-
+                self.pulse_1.set_control(data);
                 true
             },
             0x4001 =>
             {
-                self.pulse_1_sweep.set_enabled(data & 0x80 == 0x80);
-                self.pulse_1_sweep.set_period((data & 0x70) >> 4);
-                self.pulse_1_sweep.set_down(data & 0x08 == 0x08);
-                self.pulse_1_sweep.set_shift(data & 0x07);
-                self.pulse_1_sweep.set_reload(true);
+                self.pulse_1.set_sweep(data);
                 true
             },
             0x4002 =>
             {
-                self.pulse_1_seq.set_reload(self.pulse_1_seq.get_reload() & 0xFF00 | data as u16);
+                self.pulse_1.set_timer_low(data);
                 true
             },
             0x4003 =>
             {
-                self.pulse_1_seq.set_reload(((data as u16 & 0x07) << 8) | self.pulse_1_seq.get_reload() & 0x00FF);
-                self.pulse_1_seq.set_timer(self.pulse_1_seq.get_reload());
-                self.pulse_1_lc.set_counter(sound_length_counter::LENGTH_TABLE[((data & 0xF8) >> 3) as usize]);
-                self.pulse_1_env.set_start(true);
+                self.pulse_1.set_timer_high(data);
                 true
             },
             0x4004 =>
             {
-                match (data & 0xC0) >> 6
-                {
-                    0x00 =>
-                    {
-                        self.pulse_2_seq.set_sequence(0b00000001);
-                        self.pulse_2_osc.set_duty_cycle(0.125);
-                    },
-                    0x01 =>
-                    {
-                        self.pulse_2_seq.set_sequence(0b00000011);
-                        self.pulse_2_osc.set_duty_cycle(0.250);
-                    },
-                    0x02 =>
-                    {
-                        self.pulse_2_seq.set_sequence(0b00001111);
-                        self.pulse_2_osc.set_duty_cycle(0.500);
-                    },
-                    0x03 =>
-                    {
-                        self.pulse_2_seq.set_sequence(0b11111100);
-                        self.pulse_2_osc.set_duty_cycle(0.750);
-                    },
-                    _ => panic!("Impossible")
-                }
-
-                self.pulse_2_halt = data & 0x20 == 0x20;
-                self.pulse_2_env.set_volume(data as u16 & 0x0F);
-                self.pulse_2_env.set_disable(data as u16 & 0x10 == 0x10);
+                self.pulse_2.set_control(data);
                 true
             },
             0x4005 =>
             {
-                self.pulse_2_sweep.set_enabled(data & 0x80 == 0x80);
-                self.pulse_2_sweep.set_period((data & 0x70) >> 4);
-                self.pulse_2_sweep.set_down(data & 0x08 == 0x08);
-                self.pulse_2_sweep.set_shift(data & 0x07);
-                self.pulse_2_sweep.set_reload(true);
+                self.pulse_2.set_sweep(data);
                 true
             },
             0x4006 =>
             {
-                self.pulse_2_seq.set_reload(self.pulse_2_seq.get_reload() & 0xFF00 | data as u16);
+                self.pulse_2.set_timer_low(data);
                 true
             },
             0x4007 =>
             {
-                self.pulse_2_seq.set_reload(((data as u16 & 0x07) << 8) | self.pulse_2_seq.get_reload() & 0x00FF);
-                self.pulse_2_seq.set_timer(self.pulse_2_seq.get_reload());
-                self.pulse_2_lc.set_counter(sound_length_counter::LENGTH_TABLE[((data & 0xF8) >> 3) as usize]);
-                self.pulse_2_env.set_start(true);
+                self.pulse_2.set_timer_high(data);
                 true
             },
             0x4008 =>
             {
+                self.triangle.set_control(data);
                 true
             },
             0x4009 =>
@@ -243,17 +426,17 @@ fn cpu_write(&mut self, address: u16, data: u8) -> bool
             },
             0x400A =>
             {
+                self.triangle.set_timer_low(data);
                 true
             },
             0x400B =>
             {
+                self.triangle.set_timer_high(data);
                 true
             },
             0x400C =>
             {
-                self.noise_env.set_volume((data & 0x0F) as u16);
-                self.noise_env.set_disable(data & 0x10 == 0x10);
-                self.noise_halt = data & 0x20 == 0x20;
+                self.noise.set_control(data);
                 true
             },
             0x400D =>
@@ -262,63 +445,58 @@ fn cpu_write(&mut self, address: u16, data: u8) -> bool
             },
             0x400E =>
             {
-                self.noise_seq.set_mode(data & 0xF0 == 0xF0);
-
-                match data & 0x0F
-                {
-                    0x00 => self.noise_seq.set_reload(0),
-                    0x01 => self.noise_seq.set_reload(4),
-                    0x02 => self.noise_seq.set_reload(8),
-                    0x03 => self.noise_seq.set_reload(16),
-                    0x04 => self.noise_seq.set_reload(32),
-                    0x05 => self.noise_seq.set_reload(64),
-                    0x06 => self.noise_seq.set_reload(96),
-                    0x07 => self.noise_seq.set_reload(128),
-                    0x08 => self.noise_seq.set_reload(160),
-                    0x09 => self.noise_seq.set_reload(202),
-                    0x0A => self.noise_seq.set_reload(254),
-                    0x0B => self.noise_seq.set_reload(380),
-                    0x0C => self.noise_seq.set_reload(508),
-                    0x0D => self.noise_seq.set_reload(1016),
-                    0x0E => self.noise_seq.set_reload(2034),
-                    0x0F => self.noise_seq.set_reload(4068),
-                    _ => panic!("Invalid reload value for noise channel")
-                }
+                self.noise.set_period(data);
                 true
             },
             0x400F =>
             {
-                self.pulse_1_env.set_start(true);
-                self.pulse_2_env.set_start(true);
-                self.noise_env.set_start(true);
-                self.noise_lc.set_counter(sound_length_counter::LENGTH_TABLE[((data & 0xF8) >> 3) as usize]);
+                self.noise.set_length(data);
                 true
             },
             0x4010 =>
             {
+                self.dmc.set_control(data);
                 true
             },
             0x4011 =>
             {
+                self.dmc.set_output_level(data);
                 true
             },
             0x4012 =>
             {
+                self.dmc.set_sample_address(data);
                 true
             },
             0x4013 =>
             {
+                self.dmc.set_sample_length(data);
                 true
             },
             0x4015 =>
             {
-                self.pulse_1_seq.set_enable(data & 0x01 == 0x01);
-                self.pulse_2_seq.set_enable(data & 0x02 == 0x02);
-                self.noise_seq.set_enable(data & 0x04 == 0x04);
+                self.pulse_1.set_enable(data & 0x01 == 0x01);
+                self.pulse_2.set_enable(data & 0x02 == 0x02);
+                self.triangle.set_enable(data & 0x04 == 0x04);
+                self.noise.set_enable(data & 0x08 == 0x08);
+
+                // Any write acknowledges the DMC interrupt
+                self.dmc.set_enable(data & 0x10 == 0x10);
+                self.dmc.clear_irq();
                 true
             },
             0x4017 =>
             {
+                self.frame_five_step = data & 0x80 == 0x80;
+                self.frame_irq_inhibit = data & 0x40 == 0x40;
+                if self.frame_irq_inhibit
+                {
+                    self.frame_irq_active = false;
+                }
+
+                // The sequence restarts 3 CPU cycles later if the write lands on an APU cycle,
+                // 4 if it lands between two
+                self.frame_reset_delay = if (self.clock_counter.wrapping_sub(1)).is_multiple_of(6) { 3 } else { 4 };
                 true
             },
             _ =>
@@ -328,9 +506,55 @@ fn cpu_write(&mut self, address: u16, data: u8) -> bool
         }
     }
 
-    fn cpu_read(&mut self, _: u16, data: &mut u8) -> bool
+    // Only $4015 can be read, everything else is left to whoever shares the address ($4017
+    // belongs to the second controller when reading)
+    fn cpu_read(&mut self, address: u16, data: &mut u8) -> bool
     {
+        if address != 0x4015
+        {
+            return false;
+        }
+
         *data = 0x00;
+
+        if self.pulse_1.is_active()
+        {
+            *data |= 0x01;
+        }
+
+        if self.pulse_2.is_active()
+        {
+            *data |= 0x02;
+        }
+
+        if self.triangle.is_active()
+        {
+            *data |= 0x04;
+        }
+
+        if self.noise.is_active()
+        {
+            *data |= 0x08;
+        }
+
+        if self.dmc.is_active()
+        {
+            *data |= 0x10;
+        }
+
+        if self.frame_irq_active
+        {
+            *data |= 0x40;
+        }
+
+        if self.dmc.is_irq_active()
+        {
+            *data |= 0x80;
+        }
+
+        // Reading acknowledges the frame interrupt, but not the DMC one
+        self.frame_irq_active = false;
+
         true
     }
 
@@ -349,163 +573,94 @@ impl Clockable for Apu2a03
 {
     fn clock_tick(&mut self) -> bool
     {
-        let mut quarter_frame_clock: bool = false;
-        let mut half_frame_clock: bool = false;
-
-        if self.clock_counter % 6 == 0
+        if self.clock_counter.is_multiple_of(3)
         {
-            self.frame_clock_counter += 1;
-
-            // 4-Step sequence mode
-            if self.frame_clock_counter == 3729
-            {
-                quarter_frame_clock = true;
-            }
-
-            if self.frame_clock_counter == 7457
-            {
-                quarter_frame_clock = true;
-                half_frame_clock = true;
-            }
-
-            if self.frame_clock_counter == 11186
-            {
-                quarter_frame_clock = true;
-            }
-
-            if self.frame_clock_counter == 14916
-            {
-                quarter_frame_clock = true;
-                half_frame_clock = true;
-                self.frame_clock_counter = 0;
-            }
-
-            // Quarter frame beats adjust volume envelope
-            if quarter_frame_clock
-            {
-                self.pulse_1_env.set_looped(self.pulse_1_halt);
-                self.pulse_1_env.clock_tick();
-
-                self.pulse_2_env.set_looped(self.pulse_2_halt);
-                self.pulse_2_env.clock_tick();
-
-                self.noise_env.set_looped(self.noise_halt);
-                self.noise_env.clock_tick();
-            }
-
-            // Half frame beats adjust the note length and frequency sweepers
-            if half_frame_clock
-            {
-                self.pulse_1_lc.set_enable(self.pulse_1_seq.get_enable());
-                self.pulse_1_lc.set_halt(self.pulse_1_halt);
-                self.pulse_1_lc.clock_tick();
-
-                self.pulse_2_lc.set_enable(self.pulse_1_seq.get_enable());
-                self.pulse_2_lc.set_halt(self.pulse_1_halt);
-                self.pulse_2_lc.clock_tick();
-
-                self.noise_lc.set_enable(self.noise_seq.get_enable());
-                self.noise_lc.set_halt(self.noise_halt);
-                self.noise_lc.clock_tick();
-
-                self.pulse_1_sweep.set_target(self.pulse_1_seq.get_reload());
-                self.pulse_1_sweep.set_channel(false);
-                self.pulse_1_sweep.clock_tick();
-                self.pulse_1_seq.set_reload(self.pulse_1_sweep.get_target());
-
-                self.pulse_2_sweep.set_target(self.pulse_2_seq.get_reload());
-                self.pulse_2_sweep.set_channel(true);
-                self.pulse_2_sweep.clock_tick();
-                self.pulse_2_seq.set_reload(self.pulse_2_sweep.get_target());
-            }
-
-            // Pulse 1
-            {
-                self.pulse_1_seq.set_callback(Apu2a03::rotate_sequence);
-                self.pulse_1_seq.clock_tick();
-                
-                self.pulse_1_sp = self.pulse_1_seq.get_reload() as f64 + 1.0;
-                self.pulse_1_freq = 1789773.0 / (16.0 * self.pulse_1_sp);
-                self.pulse_1_osc.set_base_frequency(self.pulse_1_freq);
-                self.pulse_1_osc.set_amplitude((self.pulse_1_env.get_output() as f64 - 1.0) / 16.0);
-                let new_pulse_1_sample = self.pulse_1_osc.get_output();
-
-                if self.pulse_1_lc.get_counter() > 0 && self.pulse_1_seq.get_timer() >= 8 && !self.pulse_1_sweep.get_mute() && self.pulse_1_env.get_output() > 2
-                {
-                    self.pulse_1_sample += (new_pulse_1_sample - self.pulse_1_sample) * 0.5;
-                }
-                else
-                {
-                    self.pulse_1_sample = 0.0;
-                }
-
-                if !self.pulse_1_seq.get_enable()
-                {
-                    self.pulse_1_sample = 0.0;
-                }
-            }
-
-            // Pulse 2
-            {
-                self.pulse_2_seq.set_callback(Apu2a03::rotate_sequence);
-                self.pulse_2_seq.clock_tick();
-
-                self.pulse_2_sp = self.pulse_2_seq.get_reload() as f64 + 1.0;
-                self.pulse_2_freq = 1789773.0 / (16.0 * self.pulse_2_sp);
-                self.pulse_2_osc.set_base_frequency(self.pulse_2_freq);
-                self.pulse_2_osc.set_amplitude((self.pulse_2_env.get_output() as f64 - 1.0) / 16.0);
-                let new_pulse_2_sample = self.pulse_2_osc.get_output();
-
-                if self.pulse_2_lc.get_counter() > 0 && self.pulse_2_seq.get_timer() >= 8 && !self.pulse_2_sweep.get_mute() && self.pulse_2_env.get_output() > 2
-                {
-                    self.pulse_2_sample += (new_pulse_2_sample - self.pulse_2_sample) * 0.5;
-                }
-                else
-                {
-                    self.pulse_2_sample = 0.0;
-                }
-
-                if !self.pulse_2_seq.get_enable()
-                {
-                    self.pulse_2_sample = 0.0;
-                }
-            }
-            
-            // Noise
-            {
-                self.noise_seq.set_callback(Apu2a03::noise_seq_update);
-                self.noise_seq.clock_tick();
-
-                if self.noise_lc.get_counter() > 0
-                {
-                    self.noise_sample = self.noise_seq.get_output() as f64 * (self.noise_env.get_output().saturating_sub(1)) as f64 / 16.0;
-                }
+            self.clock_frame_counter();
+            self.triangle.clock_tick();
+            self.dmc.clock_tick();
+        }
 
-                if !self.noise_seq.get_enable()
-                {
-                    self.noise_sample = 0.0;
-                }
-            }
+        if self.clock_counter.is_multiple_of(6)
+        {
+            self.pulse_1.clock_tick();
+            self.pulse_2.clock_tick();
+            self.noise.clock_tick();
         }
 
         // Frequency sweepers change at high frequency
-        self.pulse_1_sweep.set_target(self.pulse_1_seq.get_reload());
-        self.pulse_1_sweep.track();
-        self.pulse_1_seq.set_reload(self.pulse_1_sweep.get_target());
+        self.pulse_1.track_sweep();
+        self.pulse_2.track_sweep();
 
-        self.pulse_2_sweep.set_target(self.pulse_2_seq.get_reload());
-        self.pulse_2_sweep.track();
-        self.pulse_2_seq.set_reload(self.pulse_2_sweep.get_target());
+        let mut sample_ready = false;
+        if self.clock_counter.is_multiple_of(3)
+        {
+            sample_ready = self.update_final_mix();
+        }
 
         self.clock_counter += 1;
 
-        false
+        sample_ready
+    }
+}
+
+impl Saveable for Apu2a03
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        self.pulse_1.save_state(writer)?;
+        self.pulse_2.save_state(writer)?;
+        self.triangle.save_state(writer)?;
+        self.noise.save_state(writer)?;
+        self.dmc.save_state(writer)?;
+
+        self.blip.save_state(writer)?;
+        self.high_pass_90.save_state(writer)?;
+        self.high_pass_440.save_state(writer)?;
+        self.low_pass_14k.save_state(writer)?;
+        writer.write_f64::<LittleEndian>(self.final_mix)?;
+
+        writer.write_u32::<LittleEndian>(self.frame_clock_counter)?;
+        savestate::write_bool(writer, self.frame_five_step)?;
+        savestate::write_bool(writer, self.frame_irq_inhibit)?;
+        savestate::write_bool(writer, self.frame_irq_active)?;
+        writer.write_u8(self.frame_reset_delay)?;
+        writer.write_u32::<LittleEndian>(self.clock_counter)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.pulse_1.load_state(reader)?;
+        self.pulse_2.load_state(reader)?;
+        self.triangle.load_state(reader)?;
+        self.noise.load_state(reader)?;
+        self.dmc.load_state(reader)?;
+
+        self.blip.load_state(reader)?;
+        self.high_pass_90.load_state(reader)?;
+        self.high_pass_440.load_state(reader)?;
+        self.low_pass_14k.load_state(reader)?;
+        self.final_mix = reader.read_f64::<LittleEndian>()?;
+
+        self.frame_clock_counter = reader.read_u32::<LittleEndian>()?;
+        self.frame_five_step = savestate::read_bool(reader)?;
+        self.frame_irq_inhibit = savestate::read_bool(reader)?;
+        self.frame_irq_active = savestate::read_bool(reader)?;
+        self.frame_reset_delay = reader.read_u8()?;
+        self.clock_counter = reader.read_u32::<LittleEndian>()?;
+        Ok(())
     }
 }
 
 impl Resettable for Apu2a03
 {
+    // Reset silences the APU the same way a $00 write to $4015 does. The frame counter keeps
+    // its mode but starts its sequence again, and drops any interrupt it was holding.
     fn reset(&mut self)
     {
+        self.cpu_write(0x4015, 0x00);
+
+        self.frame_irq_active = false;
+        self.frame_clock_counter = 0;
+        self.frame_reset_delay = 0;
     }
 }
\ No newline at end of file
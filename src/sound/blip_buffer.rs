@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::traits::Saveable;
+
+// Band-limited step synthesis, the approach blargg's Blip_Buffer takes.
+//
+// The APU's output is a stepped signal that changes at most once per CPU clock. Sampling
+// that directly at the host rate aliases badly, so instead every change (delta) is drawn
+// into the output as a band-limited step: the delta is spread over the output samples
+// around it with a windowed sinc kernel, and the samples are summed back up as they are
+// read out. The kernel is precomputed for PHASES sub-sample positions.
+//
+// Output samples lag the input by HALF_WIDTH samples, since a step also affects the
+// samples just before it.
+pub struct BlipBuffer
+{
+    samples_per_clock: f64,
+    // Position of the current clock in output samples, relative to the next sample out
+    time: f64,
+    deltas: VecDeque<f64>,
+    integrator: f64,
+    amplitude: f64,
+    kernel: Vec<[f64; BlipBuffer::WIDTH]>
+}
+
+impl BlipBuffer
+{
+    const HALF_WIDTH: usize = 8;
+    const WIDTH: usize = BlipBuffer::HALF_WIDTH * 2;
+    const PHASES: usize = 32;
+
+    // Cutoff as a fraction of the output sample rate, a little under Nyquist so the
+    // short kernel still rejects most of what would fold back
+    const CUTOFF: f64 = 0.45;
+
+    pub fn new(clock_rate: f64, sample_rate: f64) -> Self
+    {
+        BlipBuffer
+        {
+            samples_per_clock: sample_rate / clock_rate,
+            time: 0.0,
+            deltas: VecDeque::from(vec![0.0; BlipBuffer::WIDTH + 1]),
+            integrator: 0.0,
+            amplitude: 0.0,
+            kernel: BlipBuffer::build_kernel()
+        }
+    }
+
+    // Keeps whatever is in flight, so the ratio can be nudged while audio is playing
+    pub fn set_rates(&mut self, clock_rate: f64, sample_rate: f64)
+    {
+        self.samples_per_clock = sample_rate / clock_rate;
+    }
+
+    // Each phase holds the impulse for a step at that fraction of a sample, normalized so
+    // that a whole step always adds up to exactly its delta
+    fn build_kernel() -> Vec<[f64; BlipBuffer::WIDTH]>
+    {
+        let half_width = BlipBuffer::HALF_WIDTH as f64;
+        let mut kernel = Vec::with_capacity(BlipBuffer::PHASES);
+
+        for phase in 0..BlipBuffer::PHASES
+        {
+            let offset = phase as f64 / BlipBuffer::PHASES as f64;
+            let mut taps = [0.0; BlipBuffer::WIDTH];
+
+            for (i, tap) in taps.iter_mut().enumerate()
+            {
+                let x = i as f64 + 1.0 - half_width - offset;
+                let sinc = if x == 0.0 { 1.0 } else { (PI * 2.0 * BlipBuffer::CUTOFF * x).sin() / (PI * 2.0 * BlipBuffer::CUTOFF * x) };
+                let window = 0.42 + 0.5 * (PI * x / half_width).cos() + 0.08 * (2.0 * PI * x / half_width).cos();
+                *tap = sinc * window;
+            }
+
+            let sum: f64 = taps.iter().sum();
+            for tap in taps.iter_mut()
+            {
+                *tap /= sum;
+            }
+
+            kernel.push(taps);
+        }
+
+        kernel
+    }
+
+    // Moves the output to a new level at the current clock
+    pub fn set_amplitude(&mut self, amplitude: f64)
+    {
+        let delta = amplitude - self.amplitude;
+        if delta == 0.0
+        {
+            return;
+        }
+
+        self.amplitude = amplitude;
+
+        let phase = ((self.time * BlipBuffer::PHASES as f64) as usize).min(BlipBuffer::PHASES - 1);
+        for (i, tap) in self.kernel[phase].iter().enumerate()
+        {
+            self.deltas[i + 1] += delta * tap;
+        }
+    }
+
+    // Advances one input clock, returns an output sample whenever one is finished
+    pub fn clock(&mut self) -> Option<f64>
+    {
+        self.time += self.samples_per_clock;
+        if self.time < 1.0
+        {
+            return None;
+        }
+
+        self.time -= 1.0;
+        self.integrator += self.deltas.pop_front().unwrap();
+        self.deltas.push_back(0.0);
+        Some(self.integrator)
+    }
+}
+
+// The rates belong to whoever drives the buffer, only the signal in flight is saved
+impl Saveable for BlipBuffer
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_f64::<LittleEndian>(self.time)?;
+        writer.write_f64::<LittleEndian>(self.integrator)?;
+        writer.write_f64::<LittleEndian>(self.amplitude)?;
+        for delta in self.deltas.iter()
+        {
+            writer.write_f64::<LittleEndian>(*delta)?;
+        }
+        Ok(())
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        // A state saved at another host rate may sit past the next sample, which is harmless
+        self.time = reader.read_f64::<LittleEndian>()?.clamp(0.0, 1.0);
+        self.integrator = reader.read_f64::<LittleEndian>()?;
+        self.amplitude = reader.read_f64::<LittleEndian>()?;
+        for delta in self.deltas.iter_mut()
+        {
+            *delta = reader.read_f64::<LittleEndian>()?;
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
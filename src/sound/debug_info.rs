@@ -0,0 +1,39 @@
+// Snapshots of a channel's internals for debug views, see Apu2a03::get_channel_debug_info.
+// Fields a channel doesn't have are None.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChannelDebugInfo
+{
+    // Raw level, 0-15 for all but the DMC which is 0-127
+    pub output: f64,
+    // Timer reload value in the channel's own units, CPU cycles per output bit for the DMC
+    pub period: u16,
+    // Pitch of the note being played, for the pulses and triangle when the period is audible
+    pub frequency: Option<f64>,
+    // Length counter, bytes left of the sample for the DMC
+    pub length: u16,
+    pub linear_counter: Option<u8>,
+    pub envelope: Option<EnvelopeDebugInfo>,
+    pub sweep: Option<SweepDebugInfo>
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EnvelopeDebugInfo
+{
+    // The volume in the register, either the constant volume or the decay period
+    pub volume: u16,
+    pub decay: u16,
+    pub constant: bool,
+    pub looped: bool,
+    pub output: u16
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SweepDebugInfo
+{
+    pub enabled: bool,
+    pub down: bool,
+    pub period: u8,
+    pub shift: u8,
+    pub mute: bool
+}
\ No newline at end of file
@@ -0,0 +1,295 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
+
+use super::debug_info::ChannelDebugInfo;
+
+// NTSC output periods in CPU cycles, indexed by the rate in $4010
+const RATE_TABLE: [u16; 16] = [ 428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54 ];
+
+// The delta modulation channel, based on https://www.nesdev.org/wiki/APU_DMC
+//
+// A memory reader fills a one byte sample buffer from $C000-$FFFF. The CPU bus belongs to
+// the CPU though, so the channel can only ask for the byte (get_sample_request) and whoever
+// owns the bus hands it back with fill_sample_buffer. An output unit shifts the bits of the
+// buffer out one at a time, each one moving the 7-bit output level up or down by 2.
+pub struct Dmc
+{
+    irq_enabled: bool,
+    irq_active: bool,
+    looped: bool,
+    rate: u8,
+    timer: u16,
+    output_level: u8,
+
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: u8,
+    sample_buffer_empty: bool,
+
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool
+}
+
+impl Dmc
+{
+    pub fn new() -> Self
+    {
+        Dmc
+        {
+            irq_enabled: false,
+            irq_active: false,
+            looped: false,
+            rate: 0,
+            timer: 0,
+            output_level: 0,
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: 0,
+            sample_buffer_empty: true,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true
+        }
+    }
+
+    // $4010: IRQ enable, loop and rate
+    pub fn set_control(&mut self, data: u8)
+    {
+        self.irq_enabled = data & 0x80 == 0x80;
+        self.looped = data & 0x40 == 0x40;
+        self.rate = data & 0x0F;
+
+        if !self.irq_enabled
+        {
+            self.irq_active = false;
+        }
+    }
+
+    // $4011: loads the output level directly
+    pub fn set_output_level(&mut self, data: u8)
+    {
+        self.output_level = data & 0x7F;
+    }
+
+    // $4012: the sample starts at $C000 + A * 64
+    pub fn set_sample_address(&mut self, data: u8)
+    {
+        self.sample_address = 0xC000 | ((data as u16) << 6);
+    }
+
+    // $4013: the sample is L * 16 + 1 bytes long
+    pub fn set_sample_length(&mut self, data: u8)
+    {
+        self.sample_length = ((data as u16) << 4) | 0x0001;
+    }
+
+    // Where the sample set by $4012 and $4013 starts, and how many bytes long it is
+    pub fn get_sample_range(&self) -> (u16, u16)
+    {
+        (self.sample_address, self.sample_length)
+    }
+
+    // Bit 4 of $4015. Disabling drops whatever is left of the sample, enabling restarts
+    // it only if it already finished.
+    pub fn set_enable(&mut self, enable: bool)
+    {
+        if !enable
+        {
+            self.bytes_remaining = 0;
+        }
+        else if self.bytes_remaining == 0
+        {
+            self.restart();
+        }
+    }
+
+    // Whether there are still sample bytes left to fetch, reported in bit 4 of $4015
+    pub fn is_active(&self) -> bool
+    {
+        self.bytes_remaining > 0
+    }
+
+    pub fn is_irq_active(&self) -> bool
+    {
+        self.irq_active
+    }
+
+    pub fn clear_irq(&mut self)
+    {
+        self.irq_active = false;
+    }
+
+    pub fn get_output(&self) -> u8
+    {
+        self.output_level
+    }
+
+    pub fn get_debug_info(&self) -> ChannelDebugInfo
+    {
+        ChannelDebugInfo
+        {
+            output: self.output_level as f64,
+            period: RATE_TABLE[self.rate as usize],
+            frequency: None,
+            length: self.bytes_remaining,
+            linear_counter: None,
+            envelope: None,
+            sweep: None
+        }
+    }
+
+    // The address the memory reader wants read, if the sample buffer needs filling
+    pub fn get_sample_request(&self) -> Option<u16>
+    {
+        if self.sample_buffer_empty && self.bytes_remaining > 0
+        {
+            Some(self.current_address)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    // Completes a fetch asked for by get_sample_request
+    pub fn fill_sample_buffer(&mut self, data: u8)
+    {
+        self.sample_buffer = data;
+        self.sample_buffer_empty = false;
+
+        // The address wraps around to $8000, not $C000
+        self.current_address = if self.current_address == 0xFFFF { 0x8000 } else { self.current_address + 1 };
+
+        self.bytes_remaining = self.bytes_remaining.saturating_sub(1);
+        if self.bytes_remaining == 0
+        {
+            if self.looped
+            {
+                self.restart();
+            }
+            else if self.irq_enabled
+            {
+                self.irq_active = true;
+            }
+        }
+    }
+
+    fn restart(&mut self)
+    {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    fn clock_output(&mut self)
+    {
+        if !self.silence
+        {
+            // The level saturates instead of wrapping
+            if self.shift_register & 0x01 == 0x01
+            {
+                if self.output_level <= 125
+                {
+                    self.output_level += 2;
+                }
+            }
+            else if self.output_level >= 2
+            {
+                self.output_level -= 2;
+            }
+        }
+
+        self.shift_register >>= 1;
+        self.bits_remaining -= 1;
+
+        // Start a new output cycle with whatever is in the sample buffer
+        if self.bits_remaining == 0
+        {
+            self.bits_remaining = 8;
+            self.silence = self.sample_buffer_empty;
+            if !self.sample_buffer_empty
+            {
+                self.shift_register = self.sample_buffer;
+                self.sample_buffer_empty = true;
+            }
+        }
+    }
+}
+
+impl Default for Dmc
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Saveable for Dmc
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        savestate::write_bool(writer, self.irq_enabled)?;
+        savestate::write_bool(writer, self.irq_active)?;
+        savestate::write_bool(writer, self.looped)?;
+        writer.write_u8(self.rate)?;
+        writer.write_u16::<LittleEndian>(self.timer)?;
+        writer.write_u8(self.output_level)?;
+        writer.write_u16::<LittleEndian>(self.sample_address)?;
+        writer.write_u16::<LittleEndian>(self.sample_length)?;
+        writer.write_u16::<LittleEndian>(self.current_address)?;
+        writer.write_u16::<LittleEndian>(self.bytes_remaining)?;
+        writer.write_u8(self.sample_buffer)?;
+        savestate::write_bool(writer, self.sample_buffer_empty)?;
+        writer.write_u8(self.shift_register)?;
+        writer.write_u8(self.bits_remaining)?;
+        savestate::write_bool(writer, self.silence)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.irq_enabled = savestate::read_bool(reader)?;
+        self.irq_active = savestate::read_bool(reader)?;
+        self.looped = savestate::read_bool(reader)?;
+        self.rate = reader.read_u8()? & 0x0F;
+        self.timer = reader.read_u16::<LittleEndian>()?;
+        self.output_level = reader.read_u8()? & 0x7F;
+        self.sample_address = reader.read_u16::<LittleEndian>()?;
+        self.sample_length = reader.read_u16::<LittleEndian>()?;
+        self.current_address = reader.read_u16::<LittleEndian>()?;
+        self.bytes_remaining = reader.read_u16::<LittleEndian>()?;
+        self.sample_buffer = reader.read_u8()?;
+        self.sample_buffer_empty = savestate::read_bool(reader)?;
+        self.shift_register = reader.read_u8()?;
+        // Zero would underflow on the next output clock
+        self.bits_remaining = reader.read_u8()?.clamp(1, 8);
+        self.silence = savestate::read_bool(reader)?;
+        Ok(())
+    }
+}
+
+// Clocked at the CPU rate, returns true when the output unit was clocked
+impl Clockable for Dmc
+{
+    fn clock_tick(&mut self) -> bool
+    {
+        if self.timer == 0
+        {
+            self.timer = RATE_TABLE[self.rate as usize] - 1;
+            self.clock_output();
+            true
+        }
+        else
+        {
+            self.timer -= 1;
+            false
+        }
+    }
+}
\ No newline at end of file
@@ -1,4 +1,11 @@
-use crate::traits::Clockable;
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
+
+use super::debug_info::EnvelopeDebugInfo;
 
 pub struct Envelope
 {
@@ -84,6 +91,18 @@ pub fn set_looped(&mut self, is_looped: bool)
     {
         self.is_looped = is_looped;
     }
+
+    pub fn get_debug_info(&self) -> EnvelopeDebugInfo
+    {
+        EnvelopeDebugInfo
+        {
+            volume: self.volume,
+            decay: self.decay_count,
+            constant: self.disable,
+            looped: self.is_looped,
+            output: self.output
+        }
+    }
 }
 
 impl Default for Envelope {
@@ -92,6 +111,32 @@ fn default() -> Self {
     }
 }
 
+impl Saveable for Envelope
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        savestate::write_bool(writer, self.start)?;
+        savestate::write_bool(writer, self.disable)?;
+        writer.write_u16::<LittleEndian>(self.divider_count)?;
+        writer.write_u16::<LittleEndian>(self.volume)?;
+        writer.write_u16::<LittleEndian>(self.output)?;
+        writer.write_u16::<LittleEndian>(self.decay_count)?;
+        savestate::write_bool(writer, self.is_looped)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.start = savestate::read_bool(reader)?;
+        self.disable = savestate::read_bool(reader)?;
+        self.divider_count = reader.read_u16::<LittleEndian>()?;
+        self.volume = reader.read_u16::<LittleEndian>()?;
+        self.output = reader.read_u16::<LittleEndian>()?;
+        self.decay_count = reader.read_u16::<LittleEndian>()?;
+        self.is_looped = savestate::read_bool(reader)?;
+        Ok(())
+    }
+}
+
 impl Clockable for Envelope
 {
     fn clock_tick(&mut self) -> bool
@@ -0,0 +1,77 @@
+use std::f64::consts::PI;
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::traits::Saveable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterType
+{
+    HighPass,
+    LowPass
+}
+
+// First order RC filter, the kind the NES has between the APU and the audio out jack
+// (https://www.nesdev.org/wiki/APU_Mixer)
+pub struct Filter
+{
+    filter_type: FilterType,
+    cutoff_frequency: f64,
+    alpha: f64,
+    previous_input: f64,
+    previous_output: f64
+}
+
+impl Filter
+{
+    pub fn new(filter_type: FilterType, sample_rate: f64, cutoff_frequency: f64) -> Self
+    {
+        let mut s = Filter { filter_type, cutoff_frequency, alpha: 0.0, previous_input: 0.0, previous_output: 0.0 };
+        s.set_sample_rate(sample_rate);
+        s
+    }
+
+    // Keeps the filter's memory, so the rate can change while audio is playing
+    pub fn set_sample_rate(&mut self, sample_rate: f64)
+    {
+        let rc = 1.0 / (2.0 * PI * self.cutoff_frequency);
+        let dt = 1.0 / sample_rate;
+
+        self.alpha = match self.filter_type
+        {
+            FilterType::HighPass => rc / (rc + dt),
+            FilterType::LowPass => dt / (rc + dt)
+        };
+    }
+
+    pub fn process(&mut self, input: f64) -> f64
+    {
+        let output = match self.filter_type
+        {
+            FilterType::HighPass => self.alpha * (self.previous_output + input - self.previous_input),
+            FilterType::LowPass => self.previous_output + self.alpha * (input - self.previous_output)
+        };
+
+        self.previous_input = input;
+        self.previous_output = output;
+        output
+    }
+}
+
+// The cutoff is fixed at construction, only the filter's memory is saved
+impl Saveable for Filter
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_f64::<LittleEndian>(self.previous_input)?;
+        writer.write_f64::<LittleEndian>(self.previous_output)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.previous_input = reader.read_f64::<LittleEndian>()?;
+        self.previous_output = reader.read_f64::<LittleEndian>()?;
+        Ok(())
+    }
+}
\ No newline at end of file
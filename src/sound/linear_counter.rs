@@ -0,0 +1,95 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
+
+// The triangle channel's second length counter, clocked every quarter frame. It is reloaded
+// from reload_value while the reload flag is set, and the flag stays set for as long as the
+// control flag (which doubles as the length counter halt) is set.
+pub struct LinearCounter
+{
+    counter: u8,
+    reload_value: u8,
+    reload: bool,
+    control: bool
+}
+
+impl LinearCounter
+{
+    pub fn new() -> Self
+    {
+        LinearCounter { counter: 0, reload_value: 0, reload: false, control: false }
+    }
+
+    pub fn set_reload_value(&mut self, reload_value: u8)
+    {
+        self.reload_value = reload_value;
+    }
+
+    pub fn set_reload(&mut self, reload: bool)
+    {
+        self.reload = reload;
+    }
+
+    pub fn set_control(&mut self, control: bool)
+    {
+        self.control = control;
+    }
+
+    pub fn get_counter(&self) -> u8
+    {
+        self.counter
+    }
+}
+
+impl Default for LinearCounter
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Saveable for LinearCounter
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u8(self.counter)?;
+        writer.write_u8(self.reload_value)?;
+        savestate::write_bool(writer, self.reload)?;
+        savestate::write_bool(writer, self.control)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.counter = reader.read_u8()?;
+        self.reload_value = reader.read_u8()?;
+        self.reload = savestate::read_bool(reader)?;
+        self.control = savestate::read_bool(reader)?;
+        Ok(())
+    }
+}
+
+impl Clockable for LinearCounter
+{
+    fn clock_tick(&mut self) -> bool
+    {
+        if self.reload
+        {
+            self.counter = self.reload_value;
+        }
+        else if self.counter > 0
+        {
+            self.counter -= 1;
+        }
+
+        if !self.control
+        {
+            self.reload = false;
+        }
+
+        self.counter > 0
+    }
+}
\ No newline at end of file
@@ -1,7 +1,17 @@
 pub mod sound_engine;
 pub mod apu2a03;
 pub mod sequencer;
-pub mod oscillator;
 pub mod envelope;
 pub mod sound_length_counter;
 pub mod sweeper;
+pub mod linear_counter;
+pub mod pulse;
+pub mod triangle;
+pub mod noise;
+pub mod dmc;
+pub mod filter;
+pub mod blip_buffer;
+pub mod ring_buffer;
+pub mod wav_writer;
+pub mod vgm_logger;
+pub mod debug_info;
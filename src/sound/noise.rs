@@ -0,0 +1,264 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
+
+use super::debug_info::ChannelDebugInfo;
+use super::{sequencer::Sequencer, envelope::Envelope, sound_length_counter::{SoundLengthCounter, self}};
+
+// NTSC timer periods in CPU cycles, indexed by the low nibble of $400E
+const PERIOD_TABLE: [u16; 16] = [ 4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068 ];
+
+// The noise channel, based on https://www.nesdev.org/wiki/APU_Noise
+//
+// A 15-bit linear feedback shift register produces the pseudo-random bits, the mode flag
+// picks which bit feeds back to make either hiss or a metallic buzz.
+pub struct Noise
+{
+    sample: f64,
+    halt: bool,
+    seq: Sequencer,
+    env: Envelope,
+    lc: SoundLengthCounter
+}
+
+impl Noise
+{
+    pub fn new() -> Self
+    {
+        let mut s = Noise
+        {
+            sample: 0.0,
+            halt: false,
+            seq: Sequencer::new(),
+            env: Envelope::new(),
+            lc: SoundLengthCounter::new()
+        };
+
+        // The shift register starts out as 1
+        s.seq.set_sequence(0x1);
+        s.seq.set_callback(Noise::shift_register_update);
+        s
+    }
+
+    // Bit 0 is XORed with bit 1, or bit 6 in short mode, and shifted in at the top
+    fn shift_register_update(s: &mut u32, mode: bool)
+    {
+        let tap = if mode { 6 } else { 1 };
+        let feedback = (*s & 0x0001) ^ ((*s >> tap) & 0x0001);
+        *s = (feedback << 14) | ((*s & 0x7FFF) >> 1);
+    }
+
+    // The timer is clocked at the APU rate, half the CPU's, and the sequencer goes around
+    // once every reload + 2 ticks
+    fn get_timer_reload(index: usize) -> u16
+    {
+        PERIOD_TABLE[index] / 2 - 2
+    }
+
+    // $400C
+    pub fn set_control(&mut self, data: u8)
+    {
+        self.env.set_volume((data & 0x0F) as u16);
+        self.env.set_disable(data & 0x10 == 0x10);
+        self.halt = data & 0x20 == 0x20;
+    }
+
+    // $400E
+    pub fn set_period(&mut self, data: u8)
+    {
+        self.seq.set_mode(data & 0x80 == 0x80);
+        self.seq.set_reload(Noise::get_timer_reload((data & 0x0F) as usize));
+    }
+
+    // $400F, also restarts the note
+    pub fn set_length(&mut self, data: u8)
+    {
+        if self.seq.get_enable()
+        {
+            self.lc.set_counter(sound_length_counter::LENGTH_TABLE[((data & 0xF8) >> 3) as usize]);
+        }
+        self.env.set_start(true);
+    }
+
+    // $4015, disabling the channel silences it straight away
+    pub fn set_enable(&mut self, enable: bool)
+    {
+        self.seq.set_enable(enable);
+        self.lc.set_enable(enable);
+        if !enable
+        {
+            self.lc.set_counter(0);
+        }
+    }
+
+    pub fn is_active(&self) -> bool
+    {
+        self.lc.get_counter() > 0
+    }
+
+    // Raw level, 0-15
+    pub fn get_output(&self) -> f64
+    {
+        self.sample
+    }
+
+    pub fn get_debug_info(&self) -> ChannelDebugInfo
+    {
+        ChannelDebugInfo
+        {
+            output: self.sample,
+            period: self.seq.get_reload(),
+            frequency: None,
+            length: self.lc.get_counter() as u16,
+            linear_counter: None,
+            envelope: Some(self.env.get_debug_info()),
+            sweep: None
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self)
+    {
+        self.env.set_looped(self.halt);
+        self.env.clock_tick();
+    }
+
+    pub fn clock_half_frame(&mut self)
+    {
+        self.lc.set_halt(self.halt);
+        self.lc.clock_tick();
+    }
+}
+
+impl Default for Noise
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Saveable for Noise
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_f64::<LittleEndian>(self.sample)?;
+        savestate::write_bool(writer, self.halt)?;
+        self.seq.save_state(writer)?;
+        self.env.save_state(writer)?;
+        self.lc.save_state(writer)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.sample = reader.read_f64::<LittleEndian>()?;
+        self.halt = savestate::read_bool(reader)?;
+        self.seq.load_state(reader)?;
+        self.env.load_state(reader)?;
+        self.lc.load_state(reader)
+    }
+}
+
+// Runs at the APU rate, every other CPU cycle
+impl Clockable for Noise
+{
+    fn clock_tick(&mut self) -> bool
+    {
+        self.seq.clock_tick();
+
+        if self.seq.get_enable() && self.lc.get_counter() > 0
+        {
+            self.sample = (self.seq.get_output() as u16 * self.env.get_output()) as f64;
+        }
+        else
+        {
+            self.sample = 0.0;
+        }
+
+        true
+    }
+}
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // Number of shifts it takes the register to come back to 1
+    fn lfsr_period(mode: bool) -> u32
+    {
+        let mut s: u32 = 1;
+        let mut shifts = 0;
+        loop
+        {
+            Noise::shift_register_update(&mut s, mode);
+            shifts += 1;
+            if s == 1
+            {
+                return shifts;
+            }
+        }
+    }
+
+    #[test]
+    fn shift_register_long_mode_repeats_after_32767_steps()
+    {
+        let mut s: u32 = 1;
+        Noise::shift_register_update(&mut s, false);
+        assert_eq!(s, 0x4000);
+
+        assert_eq!(lfsr_period(false), 32767);
+    }
+
+    #[test]
+    fn shift_register_short_mode_repeats_after_93_steps()
+    {
+        // Bits 0 and 6 of 0x41 are both set, so nothing is fed back
+        let mut s: u32 = 0x41;
+        Noise::shift_register_update(&mut s, true);
+        assert_eq!(s, 0x20);
+
+        assert_eq!(lfsr_period(true), 93);
+    }
+
+    #[test]
+    fn period_sets_mode_from_bit_7()
+    {
+        let mut noise = Noise::new();
+        noise.set_period(0x80);
+        assert!(noise.seq.get_mode());
+
+        noise.set_period(0x70);
+        assert!(!noise.seq.get_mode());
+    }
+
+    // The register shifts once every table entry's worth of CPU cycles, which is half as many
+    // APU ticks
+    #[test]
+    fn period_lookup_matches_ntsc_table()
+    {
+        for (index, cpu_cycles) in PERIOD_TABLE.iter().enumerate()
+        {
+            let mut noise = Noise::new();
+            noise.set_enable(true);
+            noise.set_period(index as u8);
+
+            // The timer starts at 0, so the first tick reloads it
+            noise.clock_tick();
+            let reloaded = noise.seq.get_timer();
+
+            for _ in 0..cpu_cycles / 2
+            {
+                noise.clock_tick();
+            }
+            assert_eq!(noise.seq.get_timer(), reloaded, "period {}", index);
+
+            for _ in 1..cpu_cycles / 2
+            {
+                noise.clock_tick();
+                assert_ne!(noise.seq.get_timer(), reloaded, "period {}", index);
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
+
+use super::apu2a03::CPU_CLOCK_FREQUENCY;
+use super::debug_info::ChannelDebugInfo;
+use super::{sequencer::Sequencer, envelope::Envelope, sound_length_counter::{SoundLengthCounter, self}, sweeper::Sweeper};
+
+// Duty cycles, one bit per step of the 8 step sequence
+const DUTY_TABLE: [u32; 4] = [ 0b00000001, 0b00000011, 0b00001111, 0b11111100 ];
+
+// One of the two square wave channels, based on https://www.nesdev.org/wiki/APU_Pulse
+//
+// The two only differ in how the sweep unit negates its change, which is what
+// second_channel is for.
+pub struct Pulse
+{
+    sample: f64,
+    halt: bool,
+    second_channel: bool,
+    seq: Sequencer,
+    env: Envelope,
+    lc: SoundLengthCounter,
+    sweep: Sweeper
+}
+
+impl Pulse
+{
+    pub fn new(second_channel: bool) -> Self
+    {
+        let mut s = Pulse
+        {
+            sample: 0.0,
+            halt: false,
+            second_channel,
+            seq: Sequencer::new(),
+            env: Envelope::new(),
+            lc: SoundLengthCounter::new(),
+            sweep: Sweeper::new()
+        };
+
+        s.seq.set_callback(Pulse::rotate_sequence);
+        s
+    }
+
+    fn rotate_sequence(s: &mut u32, _: bool)
+    {
+        *s = ((*s & 0x0001) << 7) | ((*s & 0x00FE) >> 1);
+    }
+
+    // $4000 / $4004
+    pub fn set_control(&mut self, data: u8)
+    {
+        self.seq.set_sequence(DUTY_TABLE[((data & 0xC0) >> 6) as usize]);
+        self.halt = data & 0x20 == 0x20;
+        self.env.set_volume(data as u16 & 0x0F);
+        self.env.set_disable(data & 0x10 == 0x10);
+    }
+
+    // $4001 / $4005
+    pub fn set_sweep(&mut self, data: u8)
+    {
+        self.sweep.set_enabled(data & 0x80 == 0x80);
+        self.sweep.set_period((data & 0x70) >> 4);
+        self.sweep.set_down(data & 0x08 == 0x08);
+        self.sweep.set_shift(data & 0x07);
+        self.sweep.set_reload(true);
+    }
+
+    // $4002 / $4006
+    pub fn set_timer_low(&mut self, data: u8)
+    {
+        self.seq.set_reload(self.seq.get_reload() & 0xFF00 | data as u16);
+    }
+
+    // $4003 / $4007, also restarts the note
+    pub fn set_timer_high(&mut self, data: u8)
+    {
+        self.seq.set_reload(((data as u16 & 0x07) << 8) | self.seq.get_reload() & 0x00FF);
+        self.seq.set_timer(self.seq.get_reload());
+        if self.seq.get_enable()
+        {
+            self.lc.set_counter(sound_length_counter::LENGTH_TABLE[((data & 0xF8) >> 3) as usize]);
+        }
+        self.env.set_start(true);
+    }
+
+    // $4015, disabling the channel silences it straight away
+    pub fn set_enable(&mut self, enable: bool)
+    {
+        self.seq.set_enable(enable);
+        self.lc.set_enable(enable);
+        if !enable
+        {
+            self.lc.set_counter(0);
+        }
+    }
+
+    pub fn is_active(&self) -> bool
+    {
+        self.lc.get_counter() > 0
+    }
+
+    // Raw level, 0-15
+    pub fn get_output(&self) -> f64
+    {
+        self.sample
+    }
+
+    pub fn get_debug_info(&self) -> ChannelDebugInfo
+    {
+        let period = self.seq.get_reload();
+        ChannelDebugInfo
+        {
+            output: self.sample,
+            period,
+            frequency: if period >= 8 { Some(CPU_CLOCK_FREQUENCY / (16.0 * (period as f64 + 1.0))) } else { None },
+            length: self.lc.get_counter() as u16,
+            linear_counter: None,
+            envelope: Some(self.env.get_debug_info()),
+            sweep: Some(self.sweep.get_debug_info())
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self)
+    {
+        self.env.set_looped(self.halt);
+        self.env.clock_tick();
+    }
+
+    pub fn clock_half_frame(&mut self)
+    {
+        self.lc.set_halt(self.halt);
+        self.lc.clock_tick();
+
+        self.sweep.set_target(self.seq.get_reload());
+        self.sweep.set_channel(self.second_channel);
+        self.sweep.clock_tick();
+        self.seq.set_reload(self.sweep.get_target());
+    }
+
+    // The sweep unit follows period changes continuously, not just on half frames
+    pub fn track_sweep(&mut self)
+    {
+        self.sweep.set_target(self.seq.get_reload());
+        self.sweep.track();
+        self.seq.set_reload(self.sweep.get_target());
+    }
+}
+
+impl Default for Pulse
+{
+    fn default() -> Self
+    {
+        Self::new(false)
+    }
+}
+
+impl Saveable for Pulse
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_f64::<LittleEndian>(self.sample)?;
+        savestate::write_bool(writer, self.halt)?;
+        self.seq.save_state(writer)?;
+        self.env.save_state(writer)?;
+        self.lc.save_state(writer)?;
+        self.sweep.save_state(writer)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.sample = reader.read_f64::<LittleEndian>()?;
+        self.halt = savestate::read_bool(reader)?;
+        self.seq.load_state(reader)?;
+        self.env.load_state(reader)?;
+        self.lc.load_state(reader)?;
+        self.sweep.load_state(reader)
+    }
+}
+
+// Runs at the APU rate, every other CPU cycle
+impl Clockable for Pulse
+{
+    fn clock_tick(&mut self) -> bool
+    {
+        self.seq.clock_tick();
+
+        // Periods under 8 are silenced, as is anything the sweep would push out of range
+        if self.seq.get_enable() && self.lc.get_counter() > 0 && self.seq.get_reload() >= 8 && !self.sweep.get_mute()
+        {
+            self.sample = (self.seq.get_output() as u16 * self.env.get_output()) as f64;
+        }
+        else
+        {
+            self.sample = 0.0;
+        }
+
+        true
+    }
+}
\ No newline at end of file
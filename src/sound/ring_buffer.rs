@@ -0,0 +1,74 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+// Lock-free single producer, single consumer queue of samples. The emulator pushes from
+// the frame loop and the audio callback pops, neither ever waits on the other.
+//
+// Samples are stored as their bit patterns in atomics, so even a second producer or
+// consumer can only garble audio and never memory. One slot is always left empty to tell
+// a full buffer from an empty one.
+pub struct RingBuffer
+{
+    slots: Box<[AtomicU32]>,
+    // Next slot to write, only the producer moves it
+    head: AtomicUsize,
+    // Next slot to read, only the consumer moves it
+    tail: AtomicUsize
+}
+
+impl RingBuffer
+{
+    pub fn new(capacity: usize) -> Self
+    {
+        RingBuffer
+        {
+            slots: (0..capacity + 1).map(|_| AtomicU32::new(0)).collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0)
+        }
+    }
+
+    // Returns false and drops the sample if the buffer is full
+    pub fn push(&self, sample: f32) -> bool
+    {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = (head + 1) % self.slots.len();
+        if next == self.tail.load(Ordering::Acquire)
+        {
+            return false;
+        }
+
+        self.slots[head].store(sample.to_bits(), Ordering::Relaxed);
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    pub fn pop(&self) -> Option<f32>
+    {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire)
+        {
+            return None;
+        }
+
+        let sample = f32::from_bits(self.slots[tail].load(Ordering::Relaxed));
+        self.tail.store((tail + 1) % self.slots.len(), Ordering::Release);
+        Some(sample)
+    }
+
+    pub fn len(&self) -> usize
+    {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        (head + self.slots.len() - tail) % self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize
+    {
+        self.slots.len() - 1
+    }
+}
\ No newline at end of file
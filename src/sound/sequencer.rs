@@ -1,4 +1,9 @@
-use crate::traits::Clockable;
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
 
 pub struct Sequencer
 {
@@ -89,6 +94,31 @@ fn default() -> Self {
     }
 }
 
+// The callback isn't saved, the owning channel sets it when it is created
+impl Saveable for Sequencer
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u32::<LittleEndian>(self.sequence)?;
+        writer.write_u16::<LittleEndian>(self.timer)?;
+        writer.write_u16::<LittleEndian>(self.reload)?;
+        writer.write_u8(self.output)?;
+        savestate::write_bool(writer, self.enable)?;
+        savestate::write_bool(writer, self.mode)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.sequence = reader.read_u32::<LittleEndian>()?;
+        self.timer = reader.read_u16::<LittleEndian>()?;
+        self.reload = reader.read_u16::<LittleEndian>()?;
+        self.output = reader.read_u8()?;
+        self.enable = savestate::read_bool(reader)?;
+        self.mode = savestate::read_bool(reader)?;
+        Ok(())
+    }
+}
+
 impl Clockable for Sequencer
 {
     fn clock_tick(&mut self) -> bool
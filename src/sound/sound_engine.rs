@@ -1,139 +1,149 @@
-use std::sync::{Arc, Mutex};
+use std::io;
+use std::sync::Arc;
 use cpal::traits::{DeviceTrait, HostTrait};
 
-use crate::{traits::Clockable, MainState};
+use super::ring_buffer::RingBuffer;
 
+// Somewhere for the emulator's audio to go: the sound card, a file, a test. Samples are mono
+// at whatever rate the Nes was told to produce.
+pub trait AudioSink
+{
+    fn write_samples(&mut self, samples: &[f32]) -> io::Result<()>;
+
+    fn get_sample_rate(&self) -> u32;
+
+    // The rate the emulator should produce samples at for the next frame. Sinks that play
+    // in real time may nudge it to keep up with their own clock.
+    fn get_resampling_rate(&self) -> u32
+    {
+        self.get_sample_rate()
+    }
+}
+
+// Plays whatever the emulator queues through cpal. The emulator runs on the frame loop and
+// the cpal callback only drains the ring buffer, so neither paces the other. To keep the two
+// clocks from drifting apart, get_resampling_rate nudges the rate the APU resamples to
+// depending on how full the buffer is (dynamic rate control).
 pub struct SoundEngine
 {
     sample_rate: u32,
-    channels: usize,
-    audio_time_per_system_sample: f64,
-    audio_time_per_nes_clock: f64,
-    audio_time: f64,
-    final_mix: f64,
-    emulator_tick_callback: fn() -> bool
+    buffer: Arc<RingBuffer>
 }
 
 impl SoundEngine
 {
-    pub fn new(emulator_tick_callback: fn() -> bool) -> Self
+    // How much audio to keep queued, enough to ride out a late frame
+    const TARGET_LATENCY_SECONDS: f64 = 0.05;
+    const BUFFER_SECONDS: f64 = 0.25;
+
+    // Largest change to the resampling rate, small enough that the pitch change can't be heard
+    const MAX_RATE_ADJUSTMENT: f64 = 0.005;
+
+    pub fn new() -> Self
     {
-        SoundEngine {
+        SoundEngine
+        {
             sample_rate: 0,
-            channels: 0,
-            audio_time_per_system_sample: 0.0,
-            audio_time_per_nes_clock: 0.0,
-            audio_time: 0.0,
-            final_mix: 0.0,
-            emulator_tick_callback
+            buffer: Arc::new(RingBuffer::new(0))
         }
     }
 
-    pub fn sound_out(engine: Arc<Mutex<SoundEngine>>, data: &mut [f32])
+    // Fills the device's buffer from the queue. After running dry (at startup, while paused
+    // or after a stall) it plays silence until the queue is back at the target, rather than
+    // stuttering through single frames of audio.
+    fn sound_out(buffer: &RingBuffer, channels: usize, target_fill: usize, primed: &mut bool, data: &mut [f32])
     {
+        if !*primed && buffer.len() >= target_fill
+        {
+            *primed = true;
+        }
+
+        for frame in data.chunks_mut(channels)
         {
-            let channels = engine.lock().unwrap().channels;
-            for frame in data.chunks_mut(channels)
+            let mut sample = 0.0;
+            if *primed
             {
-                // Generate the next sample
+                match buffer.pop()
                 {
-                    let inner = engine.lock().unwrap();
-        
-                    // This is set elsewhere
-                    let callback = inner.emulator_tick_callback;
-                    drop(inner);
-        
-                    let mut sample_ready = false;
-                    while !sample_ready
-                    {
-                        // Call into MainState and keep clocking; this takes the engine lock
-                        // so we need to drop it before continuing
-                        sample_ready = callback();
-                    }
+                    Some(x) => sample = x,
+                    None => *primed = false
                 }
+            }
 
-                let inner = engine.lock().unwrap();
-                for sample_slot in frame.iter_mut()
-                {
-                    *sample_slot = inner.final_mix as f32;
-                }
+            for sample_slot in frame.iter_mut()
+            {
+                *sample_slot = sample;
             }
         }
     }
 
-    pub fn initialize(engine: Arc<Mutex<SoundEngine>>) -> cpal::Stream
+    pub fn initialize(&mut self) -> cpal::Stream
     {
         let host = cpal::default_host();
         let device = host.default_output_device().expect("Failed to get default output device");
         let supported_config = device.default_output_config().expect("Failed to get default output config");
 
-        let cloned_engine = engine.clone();
-        let mut inner = cloned_engine.lock().unwrap();
-        {
-            inner.audio_time_per_system_sample = 1.0 / supported_config.sample_rate().0 as f64;
-            inner.audio_time_per_nes_clock = 1.0 / 5369318.0; // PPU Clock Frequency, based on NTSC NES core frequency
-            inner.sample_rate = supported_config.sample_rate().0;
-            inner.channels = supported_config.channels() as usize;
-        }
-        drop(inner);
+        self.sample_rate = supported_config.sample_rate().0;
+        self.buffer = Arc::new(RingBuffer::new((self.sample_rate as f64 * SoundEngine::BUFFER_SECONDS) as usize));
+
+        let channels = supported_config.channels() as usize;
+        let target_fill = self.get_target_fill();
+        let buffer = Arc::clone(&self.buffer);
+        let mut primed = false;
 
         let config: cpal::StreamConfig = supported_config.into();
         device.build_output_stream(
             &config,
             move |data: &mut [f32], _: &cpal::OutputCallbackInfo|
             {
-                let engine_ref = cloned_engine.clone();
-                SoundEngine::sound_out(engine_ref, data);
+                SoundEngine::sound_out(&buffer, channels, target_fill, &mut primed, data);
             },
             move |err| {
                 eprintln!("An error occurred on stream: {}", err);
             }, None).unwrap()
     }
 
-    pub fn get_oscillator_sample_rate(&self) -> f64
+    fn get_target_fill(&self) -> usize
     {
-        // This is a bit complex, so adding a comment here.
-        // The oscillator expects to be sampled at the same rate as the sound
-        // thread runs and requests samples.
-
-        // The oscillator sample rate affects the frequency of the notes since
-        // it also determines the time domain of the wave (since sound sample rate is
-        // samples per second)
-        
-        // Because we are sampling the oscillator in the APU on the game thread,
-        // we need to adjust the oscillator sample rate so that it
-        // doesn't produce the wrong frequencies when the samples are used in the
-        // sound thread.
-
-        // For each call of sound_engine::sound_out on the sound thread, we call clock_tick
-        // until a single audio sample is ready. One sample being ready depends on the audio
-        // sample rate and has the formula:
-        // clock_tick calls = audio_time_per_system_sample / audio_time_per_nes_clock
-        // clock_tick calls = (1 / audio sample rate) / (1 / PPU clock frequency)
-
-        // For an example audio sample rate of 48000:
-        // clock_tick calls = (1 / 48000) / (1 / 5369318.0) which is ~112
-        
-        // For each six clock ticks we do one APU clock tick.
-        // For each APU clock tick, we request a single sample from the oscillator
-        // Thus, the oscillator sample rate is: sound sample rate * ((clock_tick calls) / 6)
-    
-        self.sample_rate as f64  * self.audio_time_per_system_sample / self.audio_time_per_nes_clock / 6.0
+        ((self.sample_rate as f64 * SoundEngine::TARGET_LATENCY_SECONDS) as usize).max(1)
     }
 }
 
-impl Clockable for SoundEngine
+impl AudioSink for SoundEngine
 {
-    fn clock_tick(&mut self) -> bool
+    // Samples that don't fit are dropped, which only happens if the callback stops draining
+    fn write_samples(&mut self, samples: &[f32]) -> io::Result<()>
     {
-        self.audio_time += self.audio_time_per_nes_clock;
-        if self.audio_time >= self.audio_time_per_system_sample
+        for sample in samples
         {
-            self.audio_time -= self.audio_time_per_system_sample;
-            self.final_mix = MainState::get_instance().apu.as_ref().unwrap().lock().unwrap().get_final_mix();
-            return true;
+            if !self.buffer.push(*sample)
+            {
+                break;
+            }
         }
 
-        false
+        Ok(())
+    }
+
+    fn get_sample_rate(&self) -> u32
+    {
+        self.sample_rate
+    }
+
+    // The rate that brings the queue back to the target: a little under the device rate
+    // while there is too much queued, a little over while there is too little
+    fn get_resampling_rate(&self) -> u32
+    {
+        let target_fill = self.get_target_fill() as f64;
+        let deviation = ((self.buffer.len() as f64 - target_fill) / target_fill).clamp(-1.0, 1.0);
+        (self.sample_rate as f64 * (1.0 - SoundEngine::MAX_RATE_ADJUSTMENT * deviation)).round() as u32
+    }
+}
+
+impl Default for SoundEngine
+{
+    fn default() -> Self
+    {
+        Self::new()
     }
 }
\ No newline at end of file
@@ -1,4 +1,9 @@
-use crate::traits::Clockable;
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
 
 pub struct SoundLengthCounter
 {
@@ -54,6 +59,26 @@ fn default() -> Self
     }
 }
 
+impl Saveable for SoundLengthCounter
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_u8(self.counter)?;
+        savestate::write_bool(writer, self.enable)?;
+        savestate::write_bool(writer, self.halt)?;
+        writer.write_u8(self.result)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.counter = reader.read_u8()?;
+        self.enable = savestate::read_bool(reader)?;
+        self.halt = savestate::read_bool(reader)?;
+        self.result = reader.read_u8()?;
+        Ok(())
+    }
+}
+
 impl Clockable for SoundLengthCounter
 {
     fn clock_tick(&mut self) -> bool
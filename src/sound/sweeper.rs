@@ -1,4 +1,11 @@
-use crate::traits::Clockable;
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
+
+use super::debug_info::SweepDebugInfo;
 
 pub struct Sweeper
 {
@@ -147,6 +154,18 @@ pub fn set_changed_by_tick(&mut self, changed_by_tick: bool)
         self.changed_by_tick = changed_by_tick;
     }
 
+    pub fn get_debug_info(&self) -> SweepDebugInfo
+    {
+        SweepDebugInfo
+        {
+            enabled: self.enabled,
+            down: self.down,
+            period: self.period,
+            shift: self.shift,
+            mute: self.mute
+        }
+    }
+
     pub fn track(&mut self)
     {
         if self.enabled
@@ -165,6 +184,40 @@ fn default() -> Self
     }
 }
 
+impl Saveable for Sweeper
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        savestate::write_bool(writer, self.enabled)?;
+        savestate::write_bool(writer, self.down)?;
+        savestate::write_bool(writer, self.reload)?;
+        writer.write_u8(self.shift)?;
+        writer.write_u8(self.timer)?;
+        writer.write_u8(self.period)?;
+        writer.write_u16::<LittleEndian>(self.change)?;
+        savestate::write_bool(writer, self.mute)?;
+        savestate::write_bool(writer, self.channel)?;
+        writer.write_u16::<LittleEndian>(self.target)?;
+        savestate::write_bool(writer, self.changed_by_tick)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.enabled = savestate::read_bool(reader)?;
+        self.down = savestate::read_bool(reader)?;
+        self.reload = savestate::read_bool(reader)?;
+        self.shift = reader.read_u8()?;
+        self.timer = reader.read_u8()?;
+        self.period = reader.read_u8()?;
+        self.change = reader.read_u16::<LittleEndian>()?;
+        self.mute = savestate::read_bool(reader)?;
+        self.channel = savestate::read_bool(reader)?;
+        self.target = reader.read_u16::<LittleEndian>()?;
+        self.changed_by_tick = savestate::read_bool(reader)?;
+        Ok(())
+    }
+}
+
 impl Clockable for Sweeper
 {
     fn clock_tick(&mut self) -> bool
@@ -0,0 +1,193 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::savestate;
+use crate::traits::{Clockable, Saveable};
+
+use super::apu2a03::CPU_CLOCK_FREQUENCY;
+use super::debug_info::ChannelDebugInfo;
+use super::linear_counter::LinearCounter;
+use super::sound_length_counter::{SoundLengthCounter, self};
+
+// The triangle steps down from 15 to 0 and back up again
+const TRIANGLE_SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10,  9,  8,  7,  6,  5,  4,  3,  2,  1,  0,
+     0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15 ];
+
+// The triangle channel, based on https://www.nesdev.org/wiki/APU_Triangle
+//
+// It has no volume control, a linear counter gives finer control over note length than
+// the length counter alone.
+pub struct Triangle
+{
+    sample: f64,
+    halt: bool,
+    enable: bool,
+    timer: u16,
+    reload: u16,
+    step: u8,
+    linear: LinearCounter,
+    lc: SoundLengthCounter
+}
+
+impl Triangle
+{
+    pub fn new() -> Self
+    {
+        Triangle
+        {
+            sample: 0.0,
+            halt: false,
+            enable: false,
+            timer: 0,
+            reload: 0,
+            step: 0,
+            linear: LinearCounter::new(),
+            lc: SoundLengthCounter::new()
+        }
+    }
+
+    // $4008, the control flag halts the length counter and keeps the linear counter reloading
+    pub fn set_control(&mut self, data: u8)
+    {
+        self.halt = data & 0x80 == 0x80;
+        self.linear.set_control(self.halt);
+        self.linear.set_reload_value(data & 0x7F);
+    }
+
+    // $400A
+    pub fn set_timer_low(&mut self, data: u8)
+    {
+        self.reload = self.reload & 0xFF00 | data as u16;
+    }
+
+    // $400B
+    pub fn set_timer_high(&mut self, data: u8)
+    {
+        self.reload = ((data as u16 & 0x07) << 8) | self.reload & 0x00FF;
+        if self.enable
+        {
+            self.lc.set_counter(sound_length_counter::LENGTH_TABLE[((data & 0xF8) >> 3) as usize]);
+        }
+        self.linear.set_reload(true);
+    }
+
+    // $4015, disabling the channel silences it straight away
+    pub fn set_enable(&mut self, enable: bool)
+    {
+        self.enable = enable;
+        self.lc.set_enable(enable);
+        if !enable
+        {
+            self.lc.set_counter(0);
+        }
+    }
+
+    pub fn is_active(&self) -> bool
+    {
+        self.lc.get_counter() > 0
+    }
+
+    // Raw level, 0-15
+    pub fn get_output(&self) -> f64
+    {
+        self.sample
+    }
+
+    pub fn get_debug_info(&self) -> ChannelDebugInfo
+    {
+        ChannelDebugInfo
+        {
+            output: self.sample,
+            period: self.reload,
+            frequency: if self.reload >= 2 { Some(CPU_CLOCK_FREQUENCY / (32.0 * (self.reload as f64 + 1.0))) } else { None },
+            length: self.lc.get_counter() as u16,
+            linear_counter: Some(self.linear.get_counter()),
+            envelope: None,
+            sweep: None
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self)
+    {
+        self.linear.clock_tick();
+    }
+
+    pub fn clock_half_frame(&mut self)
+    {
+        self.lc.set_halt(self.halt);
+        self.lc.clock_tick();
+    }
+}
+
+impl Default for Triangle
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Saveable for Triangle
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>
+    {
+        writer.write_f64::<LittleEndian>(self.sample)?;
+        savestate::write_bool(writer, self.halt)?;
+        savestate::write_bool(writer, self.enable)?;
+        writer.write_u16::<LittleEndian>(self.timer)?;
+        writer.write_u16::<LittleEndian>(self.reload)?;
+        writer.write_u8(self.step)?;
+        self.linear.save_state(writer)?;
+        self.lc.save_state(writer)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>
+    {
+        self.sample = reader.read_f64::<LittleEndian>()?;
+        self.halt = savestate::read_bool(reader)?;
+        self.enable = savestate::read_bool(reader)?;
+        self.timer = reader.read_u16::<LittleEndian>()?;
+        self.reload = reader.read_u16::<LittleEndian>()?;
+        self.step = reader.read_u8()?;
+        self.linear.load_state(reader)?;
+        self.lc.load_state(reader)
+    }
+}
+
+// The triangle's timer runs at the CPU rate, twice as fast as the other channels
+impl Clockable for Triangle
+{
+    fn clock_tick(&mut self) -> bool
+    {
+        if self.timer == 0
+        {
+            self.timer = self.reload;
+
+            // The sequencer only moves while both counters are non-zero, otherwise it holds
+            // its current step so the output doesn't pop
+            if self.linear.get_counter() > 0 && self.lc.get_counter() > 0
+            {
+                self.step = (self.step + 1) & 0x1F;
+            }
+        }
+        else
+        {
+            self.timer -= 1;
+        }
+
+        self.sample = if self.reload < 2
+        {
+            // Periods of 0 and 1 step the sequencer at over 50kHz. The hardware happily does
+            // that, and after the analog output filters all that is left is the midpoint.
+            7.5
+        }
+        else
+        {
+            TRIANGLE_SEQUENCE[self.step as usize] as f64
+        };
+
+        true
+    }
+}
\ No newline at end of file
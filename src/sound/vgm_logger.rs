@@ -0,0 +1,178 @@
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+// Records APU register writes as a VGM 1.61 file (https://vgmrips.net/wiki/VGM_Specification)
+// that external players and converters understand.
+//
+// Timing comes from the bus's clock counter, which ticks once per PPU clock. VGM waits are
+// counted in 44.1kHz samples, so the elapsed clocks are converted and only whole samples
+// are written out, the remainder carrying over to the next wait.
+//
+// The DMC plays its samples from PRG space, which a VGM player doesn't have. Whatever the
+// sample registers point at is copied into the log as a RAM data block, only where it
+// differs from what was copied before.
+pub struct VgmLogger
+{
+    data: Vec<u8>,
+    last_clock: u32,
+    elapsed_clocks: u64,
+    samples_written: u64,
+    // What the player's copy of $8000-$FFFF holds so far
+    dmc_memory: Vec<Option<u8>>
+}
+
+impl VgmLogger
+{
+    const HEADER_SIZE: usize = 0x100;
+    const VERSION: u32 = 0x00000161;
+    const SAMPLE_RATE: u64 = 44100;
+    const APU_CLOCK: u32 = 1789773;
+    const PPU_CLOCK: u64 = VgmLogger::APU_CLOCK as u64 * 3;
+
+    const CMD_APU_WRITE: u8 = 0xB4;
+    const CMD_WAIT: u8 = 0x61;
+    const CMD_WAIT_NTSC_FRAME: u8 = 0x62;
+    const CMD_WAIT_PAL_FRAME: u8 = 0x63;
+    const CMD_WAIT_SHORT: u8 = 0x70;
+    const CMD_DATA_BLOCK: u8 = 0x67;
+    const CMD_END: u8 = 0x66;
+    const DATA_BLOCK_APU_RAM: u8 = 0xC2;
+
+    // Starts the log at the given bus clock
+    pub fn new(clock: u32) -> Self
+    {
+        VgmLogger
+        {
+            data: vec![0; VgmLogger::HEADER_SIZE],
+            last_clock: clock,
+            elapsed_clocks: 0,
+            samples_written: 0,
+            dmc_memory: vec![None; 0x8000]
+        }
+    }
+
+    // Moves the log's notion of now to the bus clock without any time passing, for when the
+    // clock jumps (reset, loading a state)
+    pub fn resync(&mut self, clock: u32)
+    {
+        self.last_clock = clock;
+    }
+
+    // Logs a write to $4000-$4017
+    pub fn write_register(&mut self, clock: u32, address: u16, data: u8)
+    {
+        self.advance(clock);
+        self.data.push(VgmLogger::CMD_APU_WRITE);
+        self.data.push((address - 0x4000) as u8);
+        self.data.push(data);
+    }
+
+    // Makes sure the player's memory matches the given bytes, starting at address. The DMC
+    // wraps from $FFFF to $8000, so the bytes may too.
+    pub fn write_dmc_memory(&mut self, address: u16, bytes: &[u8])
+    {
+        let mut changed: Option<(usize, usize)> = None;
+        for (i, byte) in bytes.iter().enumerate()
+        {
+            let offset = (address as usize - 0x8000 + i) % 0x8000;
+
+            // A block can't wrap, so anything past the wrap starts a new one
+            if offset == 0 && i > 0
+            {
+                self.flush_dmc_memory(address, bytes, changed.take());
+            }
+
+            if self.dmc_memory[offset] != Some(*byte)
+            {
+                self.dmc_memory[offset] = Some(*byte);
+                changed = Some((changed.map_or(i, |x| x.0), i));
+            }
+        }
+
+        self.flush_dmc_memory(address, bytes, changed);
+    }
+
+    fn flush_dmc_memory(&mut self, address: u16, bytes: &[u8], changed: Option<(usize, usize)>)
+    {
+        let (first, last) = match changed
+        {
+            Some(x) => x,
+            None => return
+        };
+
+        let start = 0x8000 + ((address as usize - 0x8000 + first) % 0x8000) as u16;
+        let block = &bytes[first..=last];
+
+        self.data.push(VgmLogger::CMD_DATA_BLOCK);
+        self.data.push(VgmLogger::CMD_END);
+        self.data.push(VgmLogger::DATA_BLOCK_APU_RAM);
+        self.data.write_u32::<LittleEndian>(block.len() as u32 + 2).unwrap();
+        self.data.write_u16::<LittleEndian>(start).unwrap();
+        self.data.extend_from_slice(block);
+    }
+
+    // Emits waits up to the given bus clock
+    fn advance(&mut self, clock: u32)
+    {
+        self.elapsed_clocks += clock.wrapping_sub(self.last_clock) as u64;
+        self.last_clock = clock;
+
+        let target = self.elapsed_clocks * VgmLogger::SAMPLE_RATE / VgmLogger::PPU_CLOCK;
+        let mut remaining = target - self.samples_written;
+        self.samples_written = target;
+
+        while remaining > 0
+        {
+            match remaining
+            {
+                735 =>
+                {
+                    self.data.push(VgmLogger::CMD_WAIT_NTSC_FRAME);
+                    remaining = 0;
+                },
+                882 =>
+                {
+                    self.data.push(VgmLogger::CMD_WAIT_PAL_FRAME);
+                    remaining = 0;
+                },
+                1..=16 =>
+                {
+                    self.data.push(VgmLogger::CMD_WAIT_SHORT + (remaining - 1) as u8);
+                    remaining = 0;
+                },
+                _ =>
+                {
+                    let wait = remaining.min(0xFFFF);
+                    self.data.push(VgmLogger::CMD_WAIT);
+                    self.data.write_u16::<LittleEndian>(wait as u16).unwrap();
+                    remaining -= wait;
+                }
+            }
+        }
+    }
+
+    pub fn get_samples_written(&self) -> u64
+    {
+        self.samples_written
+    }
+
+    // Ends the log at the given bus clock and returns the whole file
+    pub fn finish(mut self, clock: u32) -> Vec<u8>
+    {
+        self.advance(clock);
+        self.data.push(VgmLogger::CMD_END);
+
+        let eof_offset = self.data.len() as u32 - 0x04;
+        let total_samples = self.samples_written.min(u32::MAX as u64) as u32;
+
+        self.data[0x00..0x04].copy_from_slice(b"Vgm ");
+        LittleEndian::write_u32(&mut self.data[0x04..], eof_offset);
+        LittleEndian::write_u32(&mut self.data[0x08..], VgmLogger::VERSION);
+        LittleEndian::write_u32(&mut self.data[0x18..], total_samples);
+        LittleEndian::write_u32(&mut self.data[0x24..], 60);
+        // Relative to the field itself
+        LittleEndian::write_u32(&mut self.data[0x34..], (VgmLogger::HEADER_SIZE - 0x34) as u32);
+        LittleEndian::write_u32(&mut self.data[0x84..], VgmLogger::APU_CLOCK);
+
+        self.data
+    }
+}
\ No newline at end of file
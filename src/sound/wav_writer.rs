@@ -0,0 +1,103 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use super::sound_engine::AudioSink;
+
+// Writes mono 16-bit PCM .wav files. The RIFF and data chunk sizes aren't known until the
+// recording ends, so the header is written with zero sizes first and patched by finish().
+pub struct WavWriter<W: Write + Seek>
+{
+    writer: W,
+    sample_rate: u32,
+    samples_written: u32
+}
+
+impl WavWriter<BufWriter<File>>
+{
+    pub fn create(path: &Path, sample_rate: u32) -> io::Result<Self>
+    {
+        WavWriter::new(BufWriter::new(File::create(path)?), sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W>
+{
+    const CHANNELS: u16 = 1;
+    const BITS_PER_SAMPLE: u16 = 16;
+    const BYTES_PER_SAMPLE: u32 = (WavWriter::<W>::BITS_PER_SAMPLE / 8) as u32;
+
+    pub fn new(writer: W, sample_rate: u32) -> io::Result<Self>
+    {
+        let mut s = WavWriter { writer, sample_rate, samples_written: 0 };
+        s.write_header()?;
+        Ok(s)
+    }
+
+    fn write_header(&mut self) -> io::Result<()>
+    {
+        let data_size = self.samples_written * WavWriter::<W>::BYTES_PER_SAMPLE;
+        let block_align = WavWriter::<W>::CHANNELS * WavWriter::<W>::BITS_PER_SAMPLE / 8;
+
+        self.writer.write_all(b"RIFF")?;
+        self.writer.write_u32::<LittleEndian>(36 + data_size)?;
+        self.writer.write_all(b"WAVE")?;
+
+        self.writer.write_all(b"fmt ")?;
+        self.writer.write_u32::<LittleEndian>(16)?;
+        // PCM
+        self.writer.write_u16::<LittleEndian>(1)?;
+        self.writer.write_u16::<LittleEndian>(WavWriter::<W>::CHANNELS)?;
+        self.writer.write_u32::<LittleEndian>(self.sample_rate)?;
+        self.writer.write_u32::<LittleEndian>(self.sample_rate * block_align as u32)?;
+        self.writer.write_u16::<LittleEndian>(block_align)?;
+        self.writer.write_u16::<LittleEndian>(WavWriter::<W>::BITS_PER_SAMPLE)?;
+
+        self.writer.write_all(b"data")?;
+        self.writer.write_u32::<LittleEndian>(data_size)
+    }
+
+    // Fills in the chunk sizes and hands back the underlying writer
+    pub fn finish(mut self) -> io::Result<W>
+    {
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    pub fn get_samples_written(&self) -> u32
+    {
+        self.samples_written
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavWriter<W>
+{
+    fn write_samples(&mut self, samples: &[f32]) -> io::Result<()>
+    {
+        // The data chunk size is a u32, anything past that can't be described by the header
+        let max_samples = (u32::MAX - 36) / WavWriter::<W>::BYTES_PER_SAMPLE;
+        if self.samples_written as usize + samples.len() > max_samples as usize
+        {
+            return Err(io::Error::other("the recording is too long for a .wav file"));
+        }
+
+        for sample in samples
+        {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            self.writer.write_i16::<LittleEndian>(value)?;
+        }
+
+        self.samples_written += samples.len() as u32;
+        Ok(())
+    }
+
+    fn get_sample_rate(&self) -> u32
+    {
+        self.sample_rate
+    }
+}
\ No newline at end of file
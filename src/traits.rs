@@ -1,3 +1,7 @@
+use std::io::{self, Read, Write};
+
+use crate::cartridge::cart::MirrorMode;
+
 pub trait ReadWrite
 {
     fn cpu_write(&mut self, address: u16, data: u8) -> bool;
@@ -6,13 +10,54 @@ pub trait ReadWrite
     fn ppu_read(&self, address: u16, data: &mut u8) -> bool;
 }
 
-pub trait MapperTrait
+// What the CPU needs from the bus it sits on. The NES has its MainBus behind this, but anything
+// that answers reads and writes over a 16-bit address space will do (e.g. memory::flat_ram).
+pub trait CpuBus
+{
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, data: u8);
+
+    // A read for the debugger, which mustn't disturb anything. Buses with registers that react
+    // to reads have to override this.
+    fn peek(&mut self, address: u16) -> u8
+    {
+        self.read(address)
+    }
+}
+
+// A mapper sets mapped_addr to this when it handled an access itself (e.g. a register
+// write), so the cartridge must not touch its memory
+pub const MAPPED_INTERNALLY: u32 = 0xFFFFFFFF;
+
+pub trait MapperTrait: Saveable
 {
     fn cpu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool;
     fn cpu_map_write(&mut self, address: u16, mapped_addr: &mut u32, data: u8) -> bool;
     fn ppu_map_read(&self, address: u16, mapped_addr: &mut u32) -> bool;
     fn ppu_map_write(&mut self, address: u16, mapped_addr: &mut u32, data: u8) -> bool;
     fn reset(&mut self);
+
+    // Asked every time the PPU resolves a nametable address. Boards without mirroring control
+    // report the mirroring they were wired with (the header bit), others what was last written.
+    fn mirror_mode(&self) -> MirrorMode;
+
+    // Called with every address the PPU puts on its bus, for mappers that watch the PPU's
+    // fetches (e.g. to count scanlines)
+    fn ppu_address(&mut self, _address: u16)
+    {
+    }
+
+    // Called once per CPU cycle, including the cycles the CPU is stalled for DMA
+    fn cpu_clock(&mut self)
+    {
+    }
+
+    // Whether the mapper is asserting the CPU's IRQ line. The line stays asserted until the
+    // mapper clears it, usually when the game acknowledges the interrupt through a register.
+    fn irq_active(&self) -> bool
+    {
+        false
+    }
 }
 
 pub trait Clockable
@@ -23,4 +68,12 @@ pub trait Clockable
 pub trait Resettable
 {
     fn reset(&mut self);
+}
+
+// Components that are part of a save state write their fields in a fixed order and read
+// them back in the same order, see savestate.rs for the surrounding file format
+pub trait Saveable
+{
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>;
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>;
 }
\ No newline at end of file
@@ -0,0 +1,59 @@
+use singularity_emu::sound::apu2a03::Apu2a03;
+use singularity_emu::traits::{Clockable, ReadWrite, Resettable};
+
+// The APU is clocked at the PPU rate, three ticks to each CPU cycle
+const TICKS_PER_CPU_CYCLE: u32 = 3;
+
+// Runs the APU until the 4-step frame sequence raises its interrupt
+fn run_until_frame_irq(apu: &mut Apu2a03)
+{
+    for _ in 0..30000 * TICKS_PER_CPU_CYCLE
+    {
+        apu.clock_tick();
+        if apu.is_irq_active()
+        {
+            return;
+        }
+    }
+
+    panic!("The frame IRQ never fired");
+}
+
+#[test]
+fn reset_clears_pending_frame_irq()
+{
+    let mut apu = Apu2a03::new();
+    run_until_frame_irq(&mut apu);
+
+    apu.reset();
+    assert!(!apu.is_irq_active());
+
+    // The sequence starts over, so the interrupt is a whole frame away again
+    for _ in 0..29000 * TICKS_PER_CPU_CYCLE
+    {
+        apu.clock_tick();
+    }
+    assert!(!apu.is_irq_active());
+    run_until_frame_irq(&mut apu);
+}
+
+#[test]
+fn reset_disables_channels()
+{
+    let mut apu = Apu2a03::new();
+    apu.cpu_write(0x4015, 0x0F);
+
+    // Load every length counter
+    for address in [0x4003, 0x4007, 0x400B, 0x400F]
+    {
+        apu.cpu_write(address, 0x08);
+    }
+
+    let mut status: u8 = 0;
+    apu.cpu_read(0x4015, &mut status);
+    assert_eq!(status & 0x0F, 0x0F);
+
+    apu.reset();
+    apu.cpu_read(0x4015, &mut status);
+    assert_eq!(status & 0x1F, 0x00);
+}
@@ -0,0 +1,238 @@
+use std::io::{Cursor, ErrorKind};
+
+use singularity_emu::cartridge::cart::{Cart, MirrorMode};
+use singularity_emu::cartridge::ines_header::{CartridgeInfo, ConsoleType, HeaderFormat, InesHeader, TimingMode};
+use singularity_emu::nes::Nes;
+use singularity_emu::traits::{ReadWrite, Resettable};
+
+// Builds a header from bytes 4-15, the NES<EOF> marker is filled in
+fn header(bytes: [u8; 12]) -> Vec<u8>
+{
+    let mut header = b"NES\x1A".to_vec();
+    header.extend_from_slice(&bytes);
+    header
+}
+
+fn parse(bytes: [u8; 12]) -> std::io::Result<CartridgeInfo>
+{
+    InesHeader::new(Cursor::new(header(bytes)))?.parse()
+}
+
+// A header followed by size bytes of ROM
+fn load(bytes: [u8; 12], size: usize) -> std::io::Result<Cart>
+{
+    let mut rom = header(bytes);
+    rom.resize(rom.len() + size, 0xEA);
+    Cart::from_reader(Cursor::new(rom))
+}
+
+// Bytes 4-15, then the mapper, PRG ROM, CHR ROM, PRG RAM, PRG NVRAM and CHR RAM sizes
+type InesCase = ([u8; 12], u16, usize, usize, usize, usize, usize);
+
+#[test]
+fn ines_headers()
+{
+    let cases: [InesCase; 5] = [
+        // Mapper 1, vertical mirroring
+        ([2, 1, 0x11, 0x00, 0, 0, 0, 0, 0, 0, 0, 0], 1, 0x8000, 0x2000, 0x2000, 0, 0),
+        // Mapper 4 split over both nibbles, two banks of battery backed RAM
+        ([8, 16, 0x42, 0x00, 2, 0, 0, 0, 0, 0, 0, 0], 4, 0x20000, 0x20000, 0, 0x4000, 0),
+        // No CHR ROM means 8KB of CHR RAM
+        ([1, 0, 0x20, 0x00, 0, 0, 0, 0, 0, 0, 0, 0], 2, 0x4000, 0, 0x2000, 0, 0x2000),
+        // Mapper 66 from the upper nibble in flags 7
+        ([1, 1, 0x20, 0x40, 0, 0, 0, 0, 0, 0, 0, 0], 66, 0x4000, 0x2000, 0x2000, 0, 0),
+        // "DiskDude!" in bytes 7-15 leaves only the lower nibble of the mapper
+        ([1, 1, 0x10, b'D', b'i', b's', b'k', b'D', b'u', b'd', b'e', b'!'], 1, 0x4000, 0x2000, 0x2000, 0, 0)
+    ];
+
+    for (bytes, mapper, prg_rom, chr_rom, prg_ram, prg_nvram, chr_ram) in cases
+    {
+        let info = parse(bytes).unwrap();
+        assert_eq!(info.format, HeaderFormat::Ines, "{:02X?}", bytes);
+        assert_eq!(info.mapper, mapper, "{:02X?}", bytes);
+        assert_eq!(info.prg_rom_size, prg_rom, "{:02X?}", bytes);
+        assert_eq!(info.chr_rom_size, chr_rom, "{:02X?}", bytes);
+        assert_eq!(info.prg_ram_size, prg_ram, "{:02X?}", bytes);
+        assert_eq!(info.prg_nvram_size, prg_nvram, "{:02X?}", bytes);
+        assert_eq!(info.chr_ram_size, chr_ram, "{:02X?}", bytes);
+    }
+}
+
+#[test]
+fn ines_flags()
+{
+    let info = parse([1, 1, 0x0F, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert!(matches!(info.mirror_mode, MirrorMode::Vertical));
+    assert!(info.battery && info.trainer && info.four_screen);
+    assert_eq!(info.timing, TimingMode::Pal);
+
+    let info = parse([1, 1, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert!(matches!(info.mirror_mode, MirrorMode::Horizontal));
+    assert_eq!(info.console_type, ConsoleType::VsSystem { ppu_type: 0, hardware_type: 0 });
+
+    let info = parse([1, 1, 0x00, 0x02, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!(info.console_type, ConsoleType::Playchoice10);
+
+    // Garbage in bytes 12-15 means bytes 7-10 can't be trusted either
+    let info = parse([1, 1, 0x00, 0x01, 4, 1, 0, 0, 0xFF, 0, 0, 0]).unwrap();
+    assert_eq!(info.console_type, ConsoleType::Nes);
+    assert_eq!(info.timing, TimingMode::Ntsc);
+    assert_eq!(info.prg_ram_size, 0x2000);
+}
+
+#[test]
+fn nes20_headers()
+{
+    // Bytes 4-15, then the mapper, submapper, PRG ROM and CHR ROM sizes
+    let cases: [([u8; 12], u16, u8, usize, usize); 6] = [
+        // Mapper 4 with 256KB of each
+        ([16, 32, 0x40, 0x08, 0x00, 0x00, 0, 0, 0, 0, 0, 0], 4, 0, 0x40000, 0x40000),
+        // Mapper 0x123 with submapper 5
+        ([1, 1, 0x30, 0x28, 0x51, 0x00, 0, 0, 0, 0, 0, 0], 0x123, 5, 0x4000, 0x2000),
+        // Size MSBs: $100 banks of PRG, $201 banks of CHR
+        ([0x00, 0x01, 0x00, 0x08, 0x00, 0x21, 0, 0, 0, 0, 0, 0], 0, 0, 0x400000, 0x402000),
+        // Exponent-multiplier sizes: 2^14 * 3 PRG, 2^13 * 1 CHR
+        ([(14 << 2) | 1, 13 << 2, 0x00, 0x08, 0x00, 0xFF, 0, 0, 0, 0, 0, 0], 0, 0, 0xC000, 0x2000),
+        // 2^10 * 7 PRG with plain CHR
+        ([(10 << 2) | 3, 2, 0x00, 0x08, 0x00, 0x0F, 0, 0, 0, 0, 0, 0], 0, 0, 0x1C00, 0x4000),
+        // 2^0 * 5 CHR with plain PRG
+        ([1, 2, 0x00, 0x08, 0x00, 0xF0, 0, 0, 0, 0, 0, 0], 0, 0, 0x4000, 5)
+    ];
+
+    for (bytes, mapper, submapper, prg_rom, chr_rom) in cases
+    {
+        let info = parse(bytes).unwrap();
+        assert_eq!(info.format, HeaderFormat::Nes20, "{:02X?}", bytes);
+        assert_eq!(info.mapper, mapper, "{:02X?}", bytes);
+        assert_eq!(info.submapper, submapper, "{:02X?}", bytes);
+        assert_eq!(info.prg_rom_size, prg_rom, "{:02X?}", bytes);
+        assert_eq!(info.chr_rom_size, chr_rom, "{:02X?}", bytes);
+    }
+}
+
+#[test]
+fn nes20_ram_timing_and_console()
+{
+    let info = parse([1, 0, 0x02, 0x08, 0x00, 0x00, 0x70, 0x07, 0x03, 0x00, 0x02, 0x3F]).unwrap();
+    assert_eq!(info.prg_ram_size, 0);
+    assert_eq!(info.prg_nvram_size, 0x2000);
+    assert_eq!(info.chr_ram_size, 0x2000);
+    assert_eq!(info.chr_nvram_size, 0);
+    assert_eq!(info.timing, TimingMode::Dendy);
+    assert_eq!(info.miscellaneous_roms, 2);
+    assert_eq!(info.default_expansion_device, 0x3F);
+
+    let timings = [TimingMode::Ntsc, TimingMode::Pal, TimingMode::MultiRegion, TimingMode::Dendy];
+    for (byte, timing) in timings.iter().enumerate()
+    {
+        let info = parse([1, 1, 0x00, 0x08, 0, 0, 0, 0, byte as u8, 0, 0, 0]).unwrap();
+        assert_eq!(info.timing, *timing);
+    }
+
+    let info = parse([1, 1, 0x00, 0x09, 0, 0, 0, 0, 0, 0x5A, 0, 0]).unwrap();
+    assert_eq!(info.console_type, ConsoleType::VsSystem { ppu_type: 0x0A, hardware_type: 0x05 });
+
+    let info = parse([1, 1, 0x00, 0x0B, 0, 0, 0, 0, 0, 0x03, 0, 0]).unwrap();
+    assert_eq!(info.console_type, ConsoleType::Extended(3));
+}
+
+#[test]
+fn bad_headers_are_rejected()
+{
+    // Too short to be a header
+    let error = InesHeader::new(Cursor::new(b"NES\x1A\x01\x01".to_vec())).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    // Missing the marker
+    let mut bytes = header([1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    bytes[3] = 0x00;
+    let error = InesHeader::new(Cursor::new(bytes)).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    // An exponent too large for any ROM
+    let error = parse([(63 << 2) | 3, 1, 0x00, 0x08, 0x00, 0x0F, 0, 0, 0, 0, 0, 0]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn cartridges_load_from_memory()
+{
+    let cart = load([1, 1, 0x01, 0x00, 0, 0, 0, 0, 0, 0, 0, 0], 0x6000).unwrap();
+    assert_eq!(cart.get_info().mapper, 0);
+
+    // The trainer sits between the header and the PRG ROM
+    let cart = load([2, 0, 0x14, 0x00, 0, 0, 0, 0, 0, 0, 0, 0], 512 + 0x8000).unwrap();
+    assert_eq!(cart.get_info().mapper, 1);
+    assert!(cart.get_info().trainer);
+}
+
+#[test]
+fn cartridges_that_cannot_load()
+{
+    // Bytes 4-15, how much ROM follows them and the error
+    let cases: [([u8; 12], usize, ErrorKind); 8] = [
+        // Truncated PRG ROM, CHR ROM and trainer
+        ([1, 1, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0], 100, ErrorKind::InvalidData),
+        ([1, 1, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0], 0x4000 + 100, ErrorKind::InvalidData),
+        ([1, 1, 0x04, 0x00, 0, 0, 0, 0, 0, 0, 0, 0], 300, ErrorKind::InvalidData),
+        // Not for an NES
+        ([1, 1, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0], 0x6000, ErrorKind::Unsupported),
+        ([1, 1, 0x00, 0x02, 0, 0, 0, 0, 0, 0, 0, 0], 0x6000, ErrorKind::Unsupported),
+        ([1, 1, 0x00, 0x0B, 0, 0, 0, 0, 0, 0x03, 0, 0], 0x6000, ErrorKind::Unsupported),
+        // Mapper 5
+        ([1, 1, 0x50, 0x00, 0, 0, 0, 0, 0, 0, 0, 0], 0x6000, ErrorKind::Unsupported),
+        // PRG ROM that isn't a whole number of banks
+        ([(10 << 2) | 3, 1, 0x00, 0x08, 0x00, 0x0F, 0, 0, 0, 0, 0, 0], 0x4000, ErrorKind::Unsupported)
+    ];
+
+    for (bytes, size, kind) in cases
+    {
+        let error = load(bytes, size).err().unwrap();
+        assert_eq!(error.kind(), kind, "{:02X?}: {}", bytes, error);
+    }
+}
+
+// INC on MMC1's registers writes $FF, which resets the shift register, and then $00 on the very
+// next cycle. MMC1 ignores that second write, so the five writes after it load the PRG bank
+// register cleanly.
+#[test]
+fn mmc1_ignores_writes_on_consecutive_cycles()
+{
+    let program: [u8; 24] = [
+        0xEE, 0x00, 0x80, // $C000 INC $8000
+        0xA9, 0x01,       // $C003 LDA #$01
+        0x8D, 0x00, 0xE0, // $C005 STA $E000
+        0x4A,             // $C008 LSR A
+        0x8D, 0x00, 0xE0, // $C009 STA $E000
+        0x8D, 0x00, 0xE0, // $C00C STA $E000
+        0x8D, 0x00, 0xE0, // $C00F STA $E000
+        0x8D, 0x00, 0xE0, // $C012 STA $E000
+        0x4C, 0x15, 0xC0  // $C015 JMP $C015
+    ];
+
+    // Two 16KB banks, each with a marker at $BFF0 when it's switched in. Bank 1 is fixed at
+    // $C000 after reset and holds the program.
+    let mut prg = vec![0xEA; 0x8000];
+    prg[0x0000] = 0xFF;
+    prg[0x3FF0] = 0xAA;
+    prg[0x7FF0] = 0xBB;
+    prg[0x4000..0x4000 + program.len()].copy_from_slice(&program);
+    prg[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0xC0]);
+
+    let mut rom = header([2, 0, 0x10, 0x00, 0, 0, 0, 0, 0, 0, 0, 0]);
+    rom.extend_from_slice(&prg);
+
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cart::from_reader(Cursor::new(rom)).unwrap());
+    nes.reset();
+
+    // The rest of the reset sequence, then everything up to the JMP
+    for _ in 0..9
+    {
+        nes.step_instruction();
+    }
+
+    let mut data: u8 = 0;
+    nes.get_bus().lock().unwrap().cpu_read(0xBFF0, &mut data);
+    assert_eq!(data, 0xBB);
+}
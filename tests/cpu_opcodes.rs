@@ -0,0 +1,569 @@
+use singularity_emu::cpu::cpu6502::{Cpu6502, Flags6502};
+use singularity_emu::memory::flat_ram::FlatRam;
+use singularity_emu::traits::{Clockable, Resettable};
+
+// The CPU on its own, with 64KB of RAM and the program at $0200. Memory is otherwise zero, so
+// after reset only I is set and every pointer leads somewhere harmless.
+const PROGRAM_ADDRESS: u16 = 0x0200;
+
+fn load_program_at(address: u16, program: &[u8]) -> Cpu6502<FlatRam>
+{
+    let mut ram = FlatRam::new();
+    ram.load(address, program);
+    ram.load(0xFFFC, &address.to_le_bytes());
+
+    let mut cpu = Cpu6502::new(ram);
+    cpu.reset();
+    run_instruction(&mut cpu);
+    cpu
+}
+
+fn load_program(program: &[u8]) -> Cpu6502<FlatRam>
+{
+    load_program_at(PROGRAM_ADDRESS, program)
+}
+
+// Clocks the CPU through the rest of the current instruction (or reset, or interrupt) and
+// returns how many cycles that took
+fn run_instruction(cpu: &mut Cpu6502<FlatRam>) -> u32
+{
+    let mut cycles = 0;
+    loop
+    {
+        cpu.clock_tick();
+        cycles += 1;
+        if cpu.complete()
+        {
+            return cycles;
+        }
+    }
+}
+
+fn run_program(program: &[u8], instructions: usize) -> Cpu6502<FlatRam>
+{
+    let mut cpu = load_program(program);
+    for _ in 0..instructions
+    {
+        run_instruction(&mut cpu);
+    }
+
+    cpu
+}
+
+fn tick(cpu: &mut Cpu6502<FlatRam>, cycles: u32)
+{
+    for _ in 0..cycles
+    {
+        cpu.clock_tick();
+    }
+}
+
+fn flag(cpu: &Cpu6502<FlatRam>, f: Flags6502) -> bool
+{
+    cpu.get_flag(f) == 1
+}
+
+// Every official opcode, with X and Y zero so nothing crosses a page. After reset only I is
+// set, so BCC, BNE, BPL and BVC are taken and the other branches aren't.
+const OFFICIAL_CYCLES: [(u8, u32); 151] = [
+    (0x69, 2), (0x65, 3), (0x75, 4), (0x6D, 4), (0x7D, 4), (0x79, 4), (0x61, 6), (0x71, 5), // ADC
+    (0x29, 2), (0x25, 3), (0x35, 4), (0x2D, 4), (0x3D, 4), (0x39, 4), (0x21, 6), (0x31, 5), // AND
+    (0x0A, 2), (0x06, 5), (0x16, 6), (0x0E, 6), (0x1E, 7),                                  // ASL
+    (0x90, 3), (0xB0, 2), (0xF0, 2), (0x30, 2), (0xD0, 3), (0x10, 3), (0x50, 3), (0x70, 2), // Branches
+    (0x24, 3), (0x2C, 4),                                                                   // BIT
+    (0x00, 7),                                                                              // BRK
+    (0x18, 2), (0xD8, 2), (0x58, 2), (0xB8, 2),                                             // CLC, CLD, CLI, CLV
+    (0xC9, 2), (0xC5, 3), (0xD5, 4), (0xCD, 4), (0xDD, 4), (0xD9, 4), (0xC1, 6), (0xD1, 5), // CMP
+    (0xE0, 2), (0xE4, 3), (0xEC, 4),                                                        // CPX
+    (0xC0, 2), (0xC4, 3), (0xCC, 4),                                                        // CPY
+    (0xC6, 5), (0xD6, 6), (0xCE, 6), (0xDE, 7), (0xCA, 2), (0x88, 2),                       // DEC, DEX, DEY
+    (0x49, 2), (0x45, 3), (0x55, 4), (0x4D, 4), (0x5D, 4), (0x59, 4), (0x41, 6), (0x51, 5), // EOR
+    (0xE6, 5), (0xF6, 6), (0xEE, 6), (0xFE, 7), (0xE8, 2), (0xC8, 2),                       // INC, INX, INY
+    (0x4C, 3), (0x6C, 5), (0x20, 6),                                                        // JMP, JSR
+    (0xA9, 2), (0xA5, 3), (0xB5, 4), (0xAD, 4), (0xBD, 4), (0xB9, 4), (0xA1, 6), (0xB1, 5), // LDA
+    (0xA2, 2), (0xA6, 3), (0xB6, 4), (0xAE, 4), (0xBE, 4),                                  // LDX
+    (0xA0, 2), (0xA4, 3), (0xB4, 4), (0xAC, 4), (0xBC, 4),                                  // LDY
+    (0x4A, 2), (0x46, 5), (0x56, 6), (0x4E, 6), (0x5E, 7),                                  // LSR
+    (0xEA, 2),                                                                              // NOP
+    (0x09, 2), (0x05, 3), (0x15, 4), (0x0D, 4), (0x1D, 4), (0x19, 4), (0x01, 6), (0x11, 5), // ORA
+    (0x48, 3), (0x08, 3), (0x68, 4), (0x28, 4),                                             // PHA, PHP, PLA, PLP
+    (0x2A, 2), (0x26, 5), (0x36, 6), (0x2E, 6), (0x3E, 7),                                  // ROL
+    (0x6A, 2), (0x66, 5), (0x76, 6), (0x6E, 6), (0x7E, 7),                                  // ROR
+    (0x40, 6), (0x60, 6),                                                                   // RTI, RTS
+    (0xE9, 2), (0xE5, 3), (0xF5, 4), (0xED, 4), (0xFD, 4), (0xF9, 4), (0xE1, 6), (0xF1, 5), // SBC
+    (0x38, 2), (0xF8, 2), (0x78, 2),                                                        // SEC, SED, SEI
+    (0x85, 3), (0x95, 4), (0x8D, 4), (0x9D, 5), (0x99, 5), (0x81, 6), (0x91, 6),            // STA
+    (0x86, 3), (0x96, 4), (0x8E, 4),                                                        // STX
+    (0x84, 3), (0x94, 4), (0x8C, 4),                                                        // STY
+    (0xAA, 2), (0xA8, 2), (0xBA, 2), (0x8A, 2), (0x9A, 2), (0x98, 2)                        // Transfers
+];
+
+#[test]
+fn official_opcodes_take_the_right_number_of_cycles()
+{
+    for (opcode, expected) in OFFICIAL_CYCLES
+    {
+        // Operands are $10 for one byte and $0310 for two, both of which hold zero
+        let mut cpu = load_program(&[opcode, 0x10, 0x03]);
+        assert_eq!(run_instruction(&mut cpu), expected, "opcode ${:02X}", opcode);
+    }
+}
+
+#[test]
+fn crossing_a_page_adds_a_cycle_to_reads_only()
+{
+    // LDX #$01, then each of these with the index carrying into the high byte
+    let cases: [(&[u8], u32); 5] = [
+        (&[0xBD, 0xFF, 0x02], 5), // LDA $02FF,X
+        (&[0xFE, 0xFF, 0x02], 7), // INC $02FF,X
+        (&[0x9D, 0xFF, 0x02], 5), // STA $02FF,X
+        (&[0xBC, 0xFF, 0x02], 5), // LDY $02FF,X
+        (&[0x5D, 0xFF, 0x02], 5)  // EOR $02FF,X
+    ];
+
+    for (instruction, expected) in cases
+    {
+        let mut program = vec![0xA2, 0x01];
+        program.extend_from_slice(instruction);
+        let mut cpu = run_program(&program, 1);
+        assert_eq!(run_instruction(&mut cpu), expected, "opcode ${:02X}", instruction[0]);
+    }
+
+    // LDY #$01, then the Y indexed forms
+    let cases: [(&[u8], u32); 4] = [
+        (&[0xB9, 0xFF, 0x02], 5), // LDA $02FF,Y
+        (&[0xBE, 0xFF, 0x02], 5), // LDX $02FF,Y
+        (&[0x99, 0xFF, 0x02], 5), // STA $02FF,Y
+        (&[0x19, 0xFF, 0x02], 5)  // ORA $02FF,Y
+    ];
+
+    for (instruction, expected) in cases
+    {
+        let mut program = vec![0xA0, 0x01];
+        program.extend_from_slice(instruction);
+        let mut cpu = run_program(&program, 1);
+        assert_eq!(run_instruction(&mut cpu), expected, "opcode ${:02X}", instruction[0]);
+    }
+
+    // LDA ($10),Y and STA ($10),Y with the pointer at $02FF and Y = 1
+    let mut cpu = run_program(&[0xA0, 0x01, 0xB1, 0x10], 1);
+    cpu.get_bus_mut().load(0x0010, &[0xFF, 0x02]);
+    assert_eq!(run_instruction(&mut cpu), 6);
+
+    let mut cpu = run_program(&[0xA0, 0x01, 0x91, 0x10], 1);
+    cpu.get_bus_mut().load(0x0010, &[0xFF, 0x02]);
+    assert_eq!(run_instruction(&mut cpu), 6);
+}
+
+#[test]
+fn indexing_within_the_page_adds_no_cycle_to_reads()
+{
+    // LDX #$01, LDA $0210,X, then LDY #$01 and LDA ($10),Y with the pointer at $0300
+    let mut cpu = run_program(&[0xA2, 0x01, 0xBD, 0x10, 0x02], 1);
+    assert_eq!(run_instruction(&mut cpu), 4);
+
+    let mut cpu = run_program(&[0xA0, 0x01, 0xB1, 0x10], 1);
+    cpu.get_bus_mut().load(0x0010, &[0x00, 0x03]);
+    assert_eq!(run_instruction(&mut cpu), 5);
+}
+
+#[test]
+fn taken_branch_to_another_page_takes_four_cycles()
+{
+    // BNE from $02F0 to $0312
+    let mut cpu = load_program_at(0x02F0, &[0xD0, 0x20]);
+    assert_eq!(run_instruction(&mut cpu), 4);
+    assert_eq!(cpu.get_pc(), 0x0312);
+}
+
+#[test]
+fn branch_timing_depends_on_whether_it_is_taken_and_where_it_lands()
+{
+    // BEQ isn't taken after reset, since Z is clear
+    let mut cpu = load_program(&[0xF0, 0x10]);
+    assert_eq!(run_instruction(&mut cpu), 2);
+    assert_eq!(cpu.get_pc(), 0x0202);
+
+    // BNE to $0212
+    let mut cpu = load_program(&[0xD0, 0x10]);
+    assert_eq!(run_instruction(&mut cpu), 3);
+    assert_eq!(cpu.get_pc(), 0x0212);
+
+    // BNE backwards from $0300 to $02F2
+    let mut cpu = load_program_at(0x0300, &[0xD0, 0xF0]);
+    assert_eq!(run_instruction(&mut cpu), 4);
+    assert_eq!(cpu.get_pc(), 0x02F2);
+
+    // The page is compared with the instruction after the branch. BNE at $02FE to itself
+    // stays on the branch's page but leaves $0300's.
+    let mut cpu = load_program_at(0x02FE, &[0xD0, 0xFE]);
+    assert_eq!(run_instruction(&mut cpu), 4);
+    assert_eq!(cpu.get_pc(), 0x02FE);
+}
+
+#[test]
+fn indirect_jump_wraps_within_the_page()
+{
+    let mut cpu = load_program(&[0x6C, 0xFF, 0x03]);
+    cpu.get_bus_mut().load(0x03FF, &[0x34]);
+    cpu.get_bus_mut().load(0x0300, &[0x12]);
+    cpu.get_bus_mut().load(0x0400, &[0x56]);
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_pc(), 0x1234);
+}
+
+#[test]
+fn adc_sets_carry_and_overflow()
+{
+    // LDA #$50, ADC #$50: two positives make a negative
+    let cpu = run_program(&[0xA9, 0x50, 0x69, 0x50], 2);
+    assert_eq!(cpu.get_a(), 0xA0);
+    assert!(flag(&cpu, Flags6502::V) && flag(&cpu, Flags6502::N));
+    assert!(!flag(&cpu, Flags6502::C) && !flag(&cpu, Flags6502::Z));
+
+    // LDA #$FF, ADC #$01: carries out to zero
+    let cpu = run_program(&[0xA9, 0xFF, 0x69, 0x01], 2);
+    assert_eq!(cpu.get_a(), 0x00);
+    assert!(flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::Z));
+    assert!(!flag(&cpu, Flags6502::V) && !flag(&cpu, Flags6502::N));
+}
+
+#[test]
+fn sbc_borrows_through_carry()
+{
+    // SEC, LDA #$50, SBC #$B0: a positive minus a negative makes a negative
+    let cpu = run_program(&[0x38, 0xA9, 0x50, 0xE9, 0xB0], 3);
+    assert_eq!(cpu.get_a(), 0xA0);
+    assert!(flag(&cpu, Flags6502::V) && flag(&cpu, Flags6502::N));
+    assert!(!flag(&cpu, Flags6502::C));
+
+    // CLC, LDA #$05, SBC #$03: without carry an extra one is taken off
+    let cpu = run_program(&[0x18, 0xA9, 0x05, 0xE9, 0x03], 3);
+    assert_eq!(cpu.get_a(), 0x01);
+    assert!(flag(&cpu, Flags6502::C) && !flag(&cpu, Flags6502::V));
+}
+
+#[test]
+fn compare_and_bit_set_flags()
+{
+    // LDA #$40, CMP #$40
+    let cpu = run_program(&[0xA9, 0x40, 0xC9, 0x40], 2);
+    assert!(flag(&cpu, Flags6502::Z) && flag(&cpu, Flags6502::C) && !flag(&cpu, Flags6502::N));
+
+    // LDA #$40, CMP #$41
+    let cpu = run_program(&[0xA9, 0x40, 0xC9, 0x41], 2);
+    assert!(!flag(&cpu, Flags6502::Z) && !flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::N));
+
+    // LDA #$01, BIT $10 with $C0 at $10: N and V come from memory, Z from the AND
+    let mut cpu = load_program(&[0xA9, 0x01, 0x24, 0x10]);
+    cpu.get_bus_mut().load(0x0010, &[0xC0]);
+    run_instruction(&mut cpu);
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_a(), 0x01);
+    assert!(flag(&cpu, Flags6502::Z) && flag(&cpu, Flags6502::N) && flag(&cpu, Flags6502::V));
+}
+
+#[test]
+fn shifts_move_bits_through_carry()
+{
+    // LDA #$81, ASL A
+    let cpu = run_program(&[0xA9, 0x81, 0x0A], 2);
+    assert_eq!(cpu.get_a(), 0x02);
+    assert!(flag(&cpu, Flags6502::C));
+
+    // LDA #$81, ASL A, ROR A: the carry goes back into bit 7
+    let cpu = run_program(&[0xA9, 0x81, 0x0A, 0x6A], 3);
+    assert_eq!(cpu.get_a(), 0x81);
+    assert!(!flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::N));
+
+    // LSR $10 with $01 at $10
+    let mut cpu = load_program(&[0x46, 0x10]);
+    cpu.get_bus_mut().load(0x0010, &[0x01]);
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0010], 0x00);
+    assert!(flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::Z));
+}
+
+#[test]
+fn inc_and_dec_wrap_memory()
+{
+    // DEC $10
+    let mut cpu = run_program(&[0xC6, 0x10, 0xE6, 0x10], 1);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0010], 0xFF);
+    assert!(flag(&cpu, Flags6502::N) && !flag(&cpu, Flags6502::Z));
+
+    // INC $10
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0010], 0x00);
+    assert!(!flag(&cpu, Flags6502::N) && flag(&cpu, Flags6502::Z));
+}
+
+#[test]
+fn decimal_flag_only_matters_in_decimal_mode()
+{
+    // SED, CLC, LDA #$58, ADC #$46
+    let adc = [0xF8, 0x18, 0xA9, 0x58, 0x69, 0x46];
+    let cpu = run_program(&adc, 4);
+    assert_eq!(cpu.get_a(), 0x9E);
+    assert!(!flag(&cpu, Flags6502::C));
+
+    let mut cpu = load_program(&adc);
+    cpu.set_decimal_mode(true);
+    for _ in 0..4
+    {
+        run_instruction(&mut cpu);
+    }
+    assert_eq!(cpu.get_a(), 0x04);
+    assert!(flag(&cpu, Flags6502::C));
+
+    // SED, SEC, LDA #$00, SBC #$01
+    let sbc = [0xF8, 0x38, 0xA9, 0x00, 0xE9, 0x01];
+    let cpu = run_program(&sbc, 4);
+    assert_eq!(cpu.get_a(), 0xFF);
+
+    let mut cpu = load_program(&sbc);
+    cpu.set_decimal_mode(true);
+    for _ in 0..4
+    {
+        run_instruction(&mut cpu);
+    }
+    assert_eq!(cpu.get_a(), 0x99);
+    assert!(!flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::N));
+
+    // SED, LDA #$FF, ARR #$99: the rotated $4C has both digits fixed up
+    let arr = [0xF8, 0xA9, 0xFF, 0x6B, 0x99];
+    let cpu = run_program(&arr, 3);
+    assert_eq!(cpu.get_a(), 0x4C);
+
+    let mut cpu = load_program(&arr);
+    cpu.set_decimal_mode(true);
+    for _ in 0..3
+    {
+        run_instruction(&mut cpu);
+    }
+    assert_eq!(cpu.get_a(), 0xA2);
+    assert!(flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::V) && !flag(&cpu, Flags6502::N));
+}
+
+#[test]
+fn irq_waits_for_the_instruction_after_cli()
+{
+    // CLI, NOP, NOP with the IRQ line held from the start
+    let mut cpu = load_program(&[0x58, 0xEA, 0xEA]);
+    cpu.get_bus_mut().load(0xFFFE, &[0x00, 0x04]);
+    cpu.set_irq_line(true);
+
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_pc(), 0x0201);
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_pc(), 0x0202);
+
+    assert_eq!(run_instruction(&mut cpu), 7);
+    assert_eq!(cpu.get_pc(), 0x0400);
+    assert!(flag(&cpu, Flags6502::I));
+
+    // Return address, then the status with B clear
+    let stack = &cpu.get_bus_mut().buffer[0x01FB..=0x01FD];
+    assert_eq!(stack, [0x20, 0x02, 0x02]);
+}
+
+#[test]
+fn nmi_ignores_the_interrupt_disable_flag()
+{
+    let mut cpu = load_program(&[0xEA, 0xEA]);
+    cpu.get_bus_mut().load(0xFFFA, &[0x00, 0x05]);
+    assert!(flag(&cpu, Flags6502::I));
+
+    // Raised between instructions, so the NOP already under way still runs
+    cpu.nmi();
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_pc(), 0x0201);
+
+    assert_eq!(run_instruction(&mut cpu), 7);
+    assert_eq!(cpu.get_pc(), 0x0500);
+}
+
+// CLI, LDA $0310, NOP, NOP with the IRQ handler at $0400, and CLI's delay already over
+fn load_irq_program() -> Cpu6502<FlatRam>
+{
+    let mut cpu = run_program(&[0x58, 0xAD, 0x10, 0x03, 0xEA, 0xEA], 1);
+    cpu.get_bus_mut().load(0xFFFE, &[0x00, 0x04]);
+    cpu
+}
+
+#[test]
+fn irq_before_the_last_cycle_is_taken_after_the_instruction()
+{
+    // Raised after the LDA's second cycle, in time for the poll on its third
+    let mut cpu = load_irq_program();
+    tick(&mut cpu, 2);
+    cpu.set_irq_line(true);
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_pc(), 0x0204);
+
+    assert_eq!(run_instruction(&mut cpu), 7);
+    assert_eq!(cpu.get_pc(), 0x0400);
+}
+
+#[test]
+fn irq_on_the_last_cycle_waits_for_the_next_instruction()
+{
+    // Raised for the LDA's fourth and last cycle, after the poll that counts
+    let mut cpu = load_irq_program();
+    tick(&mut cpu, 3);
+    cpu.set_irq_line(true);
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_pc(), 0x0204);
+
+    assert_eq!(run_instruction(&mut cpu), 2);
+    assert_eq!(cpu.get_pc(), 0x0205);
+
+    assert_eq!(run_instruction(&mut cpu), 7);
+    assert_eq!(cpu.get_pc(), 0x0400);
+}
+
+#[test]
+fn nmi_latency_follows_the_same_poll_as_irq()
+{
+    // LDA $0310, NOP with the NMI handler at $0500
+    let program = [0xAD, 0x10, 0x03, 0xEA];
+
+    let mut cpu = load_program(&program);
+    cpu.get_bus_mut().load(0xFFFA, &[0x00, 0x05]);
+    tick(&mut cpu, 2);
+    cpu.nmi();
+    run_instruction(&mut cpu);
+    assert_eq!(run_instruction(&mut cpu), 7);
+    assert_eq!(cpu.get_pc(), 0x0500);
+
+    let mut cpu = load_program(&program);
+    cpu.get_bus_mut().load(0xFFFA, &[0x00, 0x05]);
+    tick(&mut cpu, 3);
+    cpu.nmi();
+    run_instruction(&mut cpu);
+    assert_eq!(run_instruction(&mut cpu), 2);
+    assert_eq!(cpu.get_pc(), 0x0204);
+    assert_eq!(run_instruction(&mut cpu), 7);
+    assert_eq!(cpu.get_pc(), 0x0500);
+}
+
+#[test]
+fn high_byte_stores_and_with_the_address()
+{
+    // LDX #$FF, LDY #$10, SHX $0300,Y: X & ($03 + 1)
+    let mut cpu = run_program(&[0xA2, 0xFF, 0xA0, 0x10, 0x9E, 0x00, 0x03], 3);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0310], 0x04);
+
+    // LDY #$FF, LDX #$10, SHY $0300,X: Y & ($03 + 1)
+    let mut cpu = run_program(&[0xA0, 0xFF, 0xA2, 0x10, 0x9C, 0x00, 0x03], 3);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0310], 0x04);
+
+    // LDA #$FF, LDX #$0F, AHX $0300,Y: A & X & ($03 + 1)
+    let mut cpu = run_program(&[0xA9, 0xFF, 0xA2, 0x0F, 0x9F, 0x00, 0x03], 3);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0300], 0x04);
+
+    // LDA #$FF, LDX #$0F, LDY #$01, AHX ($10),Y with the pointer at $0300
+    let mut cpu = load_program(&[0xA9, 0xFF, 0xA2, 0x0F, 0xA0, 0x01, 0x93, 0x10]);
+    cpu.get_bus_mut().load(0x0010, &[0x00, 0x03]);
+    for _ in 0..4
+    {
+        run_instruction(&mut cpu);
+    }
+    assert_eq!(cpu.get_bus_mut().buffer[0x0301], 0x04);
+
+    // LDA #$FF, LDX #$1E, TAS $0300,Y: SP = A & X, then SP & ($03 + 1)
+    let mut cpu = run_program(&[0xA9, 0xFF, 0xA2, 0x1E, 0x9B, 0x00, 0x03], 3);
+    assert_eq!(cpu.get_stkp(), 0x1E);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0300], 0x04);
+}
+
+#[test]
+fn high_byte_stores_that_cross_a_page_corrupt_the_address()
+{
+    // LDX #$01, LDY #$01, SHX $02FF,Y: the value is X & ($02 + 1) = $01, which also
+    // replaces the high byte of $0300
+    let mut cpu = run_program(&[0xA2, 0x01, 0xA0, 0x01, 0x9E, 0xFF, 0x02], 3);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0100], 0x01);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0300], 0x00);
+
+    // LDY #$01, LDX #$01, SHY $02FF,X
+    let mut cpu = run_program(&[0xA0, 0x01, 0xA2, 0x01, 0x9C, 0xFF, 0x02], 3);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0100], 0x01);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0300], 0x00);
+}
+
+#[test]
+fn immediate_illegal_opcodes_set_flags()
+{
+    // LDA #$FF, ALR #$03: AND then LSR
+    let cpu = run_program(&[0xA9, 0xFF, 0x4B, 0x03], 2);
+    assert_eq!(cpu.get_a(), 0x01);
+    assert!(flag(&cpu, Flags6502::C) && !flag(&cpu, Flags6502::Z) && !flag(&cpu, Flags6502::N));
+
+    // LDA #$FF, ARR #$FF: AND then ROR, C from bit 6 and V from bit 6 XOR bit 5
+    let cpu = run_program(&[0xA9, 0xFF, 0x6B, 0xFF], 2);
+    assert_eq!(cpu.get_a(), 0x7F);
+    assert!(flag(&cpu, Flags6502::C) && !flag(&cpu, Flags6502::V) && !flag(&cpu, Flags6502::N));
+
+    // LDA #$FF, ARR #$40
+    let cpu = run_program(&[0xA9, 0xFF, 0x6B, 0x40], 2);
+    assert_eq!(cpu.get_a(), 0x20);
+    assert!(!flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::V));
+
+    // SEC, LDA #$FF, ARR #$00: the carry goes into bit 7
+    let cpu = run_program(&[0x38, 0xA9, 0xFF, 0x6B, 0x00], 3);
+    assert_eq!(cpu.get_a(), 0x80);
+    assert!(flag(&cpu, Flags6502::N) && !flag(&cpu, Flags6502::C) && !flag(&cpu, Flags6502::V));
+
+    // LDA #$F0, LDX #$3C, AXS #$10: X = (A & X) - M like CMP
+    let cpu = run_program(&[0xA9, 0xF0, 0xA2, 0x3C, 0xCB, 0x10], 3);
+    assert_eq!(cpu.get_x(), 0x20);
+    assert_eq!(cpu.get_a(), 0xF0);
+    assert!(flag(&cpu, Flags6502::C) && !flag(&cpu, Flags6502::N));
+
+    // LDA #$F0, LDX #$3C, AXS #$40
+    let cpu = run_program(&[0xA9, 0xF0, 0xA2, 0x3C, 0xCB, 0x40], 3);
+    assert_eq!(cpu.get_x(), 0xF0);
+    assert!(!flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::N));
+
+    // LDA #$00, LDX #$FF, XAA #$FF: (A | $EE) & X & M
+    let cpu = run_program(&[0xA9, 0x00, 0xA2, 0xFF, 0x8B, 0xFF], 3);
+    assert_eq!(cpu.get_a(), 0xEE);
+    assert!(flag(&cpu, Flags6502::N));
+
+    // LDA #$80, ANC #$FF: C is copied from N
+    let cpu = run_program(&[0xA9, 0x80, 0x0B, 0xFF], 2);
+    assert!(flag(&cpu, Flags6502::C) && flag(&cpu, Flags6502::N));
+}
+
+#[test]
+fn las_ands_memory_with_the_stack_pointer()
+{
+    // LAS $0300,Y with $F0 there and SP at $FD after reset
+    let mut cpu = load_program(&[0xBB, 0x00, 0x03]);
+    cpu.get_bus_mut().load(0x0300, &[0xF0]);
+    run_instruction(&mut cpu);
+    assert_eq!(cpu.get_stkp(), 0xF0);
+    assert_eq!(cpu.get_a(), 0xF0);
+    assert_eq!(cpu.get_x(), 0xF0);
+    assert!(flag(&cpu, Flags6502::N) && !flag(&cpu, Flags6502::Z));
+}
+
+#[test]
+fn pushes_wrap_the_stack_pointer()
+{
+    // LDX #$00, TXS, LDA #$42, PHA: stored at $0100, SP wraps to $FF
+    let mut cpu = run_program(&[0xA2, 0x00, 0x9A, 0xA9, 0x42, 0x48], 4);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0100], 0x42);
+    assert_eq!(cpu.get_stkp(), 0xFF);
+
+    // LDX #$00, TXS, PHP: B and U are set in the pushed copy
+    let mut cpu = run_program(&[0xA2, 0x00, 0x9A, 0x08], 3);
+    assert_eq!(cpu.get_bus_mut().buffer[0x0100], 0x36);
+    assert_eq!(cpu.get_stkp(), 0xFF);
+
+    // And PLA wraps back
+    let cpu = run_program(&[0xA2, 0x00, 0x9A, 0xA9, 0x42, 0x48, 0xA9, 0x00, 0x68], 6);
+    assert_eq!(cpu.get_a(), 0x42);
+    assert_eq!(cpu.get_stkp(), 0x00);
+}
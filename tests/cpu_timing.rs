@@ -0,0 +1,139 @@
+use singularity_emu::cartridge::cart::Cart;
+use singularity_emu::nes::Nes;
+use singularity_emu::traits::{ReadWrite, Resettable};
+
+// Small programs run from RAM at $0200 that check the CPU's bus accesses through their side
+// effects. nestest's ROM is only there to give the console its vectors.
+const PROGRAM_ADDRESS: u16 = 0x0200;
+
+fn load_program(program: &[u8]) -> Nes
+{
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cart::new("data/nestest.nes".to_string()).unwrap());
+    nes.reset();
+
+    // Let the reset sequence finish before moving the PC
+    nes.step_instruction();
+
+    let bus = nes.get_bus();
+    let mut bus = bus.lock().unwrap();
+    for (i, data) in program.iter().enumerate()
+    {
+        bus.cpu_write(PROGRAM_ADDRESS + i as u16, *data);
+    }
+    drop(bus);
+
+    nes.get_cpu().lock().unwrap().set_pc(PROGRAM_ADDRESS);
+    nes
+}
+
+fn read(nes: &Nes, address: u16) -> u8
+{
+    let mut data: u8 = 0;
+    nes.get_bus().lock().unwrap().cpu_read(address, &mut data);
+    data
+}
+
+// Points the PPU at the start of the first name table
+const SET_PPU_ADDRESS_2000: [u8; 10] = [
+    0xA9, 0x20,       // LDA #$20
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x00,       // LDA #$00
+    0x8D, 0x06, 0x20  // STA $2006
+];
+
+// LDA $20FF,X with X = 8 reads $2007 before the carry reaches the high byte, which moves the
+// PPU address on a second time
+#[test]
+fn page_crossing_makes_a_dummy_read()
+{
+    let mut program = SET_PPU_ADDRESS_2000.to_vec();
+    program.extend_from_slice(&[
+        0xA2, 0x08,       // LDX #$08
+        0xBD, 0xFF, 0x20, // LDA $20FF,X
+        0xA9, 0x55,       // LDA #$55
+        0x8D, 0x07, 0x20  // STA $2007
+    ]);
+
+    let mut nes = load_program(&program);
+    for _ in 0..8
+    {
+        nes.step_instruction();
+    }
+
+    let name_table = nes.get_ppu().lock().unwrap().get_name_table();
+    assert_eq!(name_table[0][0..4], [0x00, 0x00, 0x55, 0x00]);
+}
+
+// INC $2007 reads once and writes twice, the old value and then the new one, each of which
+// moves the PPU address on
+#[test]
+fn read_modify_write_writes_twice()
+{
+    let mut program = SET_PPU_ADDRESS_2000.to_vec();
+    program.extend_from_slice(&[
+        0xEE, 0x07, 0x20 // INC $2007
+    ]);
+
+    let mut nes = load_program(&program);
+    for _ in 0..5
+    {
+        nes.step_instruction();
+    }
+
+    let name_table = nes.get_ppu().lock().unwrap().get_name_table();
+    assert_eq!(name_table[0][0..4], [0x00, 0x00, 0x01, 0x00]);
+}
+
+// nestest's board has nothing at $5000, so the write is dropped and the program carries on
+#[test]
+fn write_to_open_bus_is_dropped()
+{
+    let mut nes = load_program(&[
+        0xA9, 0x42,       // LDA #$42
+        0x8D, 0x00, 0x50, // STA $5000
+        0x85, 0x10        // STA $10
+    ]);
+    for _ in 0..3
+    {
+        nes.step_instruction();
+    }
+
+    assert_eq!(read(&nes, 0x0010), 0x42);
+}
+
+#[test]
+fn brk_pushes_pc_past_padding_byte_and_b_flag()
+{
+    let mut nes = load_program(&[0x00, 0xEA]);
+    nes.step_instruction();
+
+    assert_eq!(nes.get_cpu().lock().unwrap().get_stkp(), 0xFA);
+    assert_eq!([read(&nes, 0x01FD), read(&nes, 0x01FC), read(&nes, 0x01FB)], [0x02, 0x02, 0x34]);
+}
+
+// The APU frame IRQ interrupts a JMP loop between two instructions. The status that gets pushed
+// has I clear from the CLI and no B flag.
+#[test]
+fn irq_interrupts_between_instructions()
+{
+    let mut nes = load_program(&[
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x17, 0x40, // STA $4017
+        0x58,             // CLI
+        0x4C, 0x06, 0x02  // JMP $0206
+    ]);
+
+    // The frame IRQ comes after about 30000 cycles
+    for _ in 0..20000
+    {
+        nes.step_instruction();
+        if nes.get_cpu().lock().unwrap().get_stkp() != 0xFD
+        {
+            break;
+        }
+    }
+
+    assert_eq!(nes.get_cpu().lock().unwrap().get_stkp(), 0xFA);
+    assert_eq!([read(&nes, 0x01FD), read(&nes, 0x01FC), read(&nes, 0x01FB)], [0x02, 0x06, 0x22]);
+}
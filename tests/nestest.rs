@@ -0,0 +1,12 @@
+use singularity_emu::nestest;
+
+// Runs the whole of nestest's automation mode against the reference log shipped in data/
+#[test]
+fn nestest_matches_reference_log()
+{
+    match nestest::run("data/nestest.nes", "data/nestest_output.log")
+    {
+        Ok(lines) => assert_eq!(lines, 8991),
+        Err(e) => panic!("{}", e)
+    }
+}